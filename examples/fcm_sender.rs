@@ -1,6 +1,6 @@
 use std::future::Future;
 
-use rex_axum_sdk::fcm_messaging::{FCMSender, FCMTokenRepository};
+use rex_axum_sdk::fcm_messaging::{FCMSender, FCMTokenRepository, RepositoryError};
 use serde_json::json;
 use std::error::Error;
 // 實作一個簡單的 FCMTokenRepository
@@ -10,7 +10,7 @@ impl FCMTokenRepository for MyFCMTokenRepository {
     fn get_user_fcm_token(
         &self,
         user_email: String,
-    ) -> impl Future<Output = Result<Option<String>, Box<dyn Error>>> + Send {
+    ) -> impl Future<Output = Result<Option<String>, RepositoryError>> + Send {
         let _ = user_email;
         async move {
             // 在實際應用中，這裡會從資料庫或其他存儲中獲取 token
@@ -21,7 +21,7 @@ impl FCMTokenRepository for MyFCMTokenRepository {
     fn get_group_fcm_tokens(
         &self,
         group_id: i32,
-    ) -> impl Future<Output = Result<Vec<String>, Box<dyn Error>>> + Send {
+    ) -> impl Future<Output = Result<Vec<String>, RepositoryError>> + Send {
         let _ = group_id;
         async move {
             // 在實際應用中，這裡會從資料庫獲取群組所有成員的 token
@@ -43,8 +43,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let repository = MyFCMTokenRepository;
 
-    // 發送給單一用戶
-    fcm_sender
+    // 發送給單一用戶，回傳 FCM 指派的 message name
+    let message_name = fcm_sender
         .send_notification_to_user(
             &repository,
             "user@example.com".to_string(),
@@ -56,6 +56,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
             })),
         )
         .await?;
+    println!("已發送：{}", message_name);
 
     // 發送給群組
     fcm_sender
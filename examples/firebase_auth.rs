@@ -34,11 +34,10 @@ impl FirebaseAuthRequest for SignInRequest {
 
 #[tokio::main]
 async fn main() {
-    let service = FirebaseAuthService {
-        client: reqwest::Client::new(),
-        base_url: "https://identitytoolkit.googleapis.com".to_string(),
-        api_token: "your-firebase-api-key".to_string(), // 替換成你的 API key
-    };
+    let service = FirebaseAuthService::new(
+        "https://identitytoolkit.googleapis.com",
+        "your-firebase-api-key", // 替換成你的 API key
+    );
 
     let sign_in_request = SignInRequest {
         email: "user@example.com".to_string(), // 替換成實際的 email
@@ -0,0 +1,254 @@
+use crate::fcm_messaging::FcmError;
+use crate::firebase_auth::FirebaseAuthError;
+use crate::sqlx::PgExtError;
+use crate::utilty::JwtError;
+use axum::response::{IntoResponse, Response};
+use hyper::StatusCode;
+use std::error::Error;
+use std::fmt;
+
+// 整個 SDK 共用的錯誤型別，讓 handler 可以統一回傳 Result<_, SdkError>
+#[derive(Debug)]
+pub enum SdkError {
+    Jwt(JwtError),
+    Fcm(FcmError),
+    FirebaseAuth(FirebaseAuthError),
+    Database(sqlx::Error),
+    // 唯一 / 外鍵 / NOT NULL / CHECK 約束違反
+    Constraint(PgExtError),
+}
+
+impl fmt::Display for SdkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdkError::Jwt(e) => write!(f, "{}", e),
+            SdkError::Fcm(e) => write!(f, "{}", e),
+            SdkError::FirebaseAuth(e) => write!(f, "{}", e),
+            SdkError::Database(e) => write!(f, "Database error: {}", e),
+            SdkError::Constraint(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SdkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SdkError::Jwt(e) => Some(e),
+            SdkError::Fcm(e) => Some(e),
+            SdkError::FirebaseAuth(e) => Some(e),
+            SdkError::Database(e) => Some(e),
+            SdkError::Constraint(e) => Some(e),
+        }
+    }
+}
+
+impl From<JwtError> for SdkError {
+    fn from(e: JwtError) -> Self {
+        SdkError::Jwt(e)
+    }
+}
+
+impl From<FcmError> for SdkError {
+    fn from(e: FcmError) -> Self {
+        SdkError::Fcm(e)
+    }
+}
+
+impl From<FirebaseAuthError> for SdkError {
+    fn from(e: FirebaseAuthError) -> Self {
+        SdkError::FirebaseAuth(e)
+    }
+}
+
+// 約束違反轉成 Constraint，讓 handler 直接以 ? 傳遞 execute 的錯誤也能回傳 409 / 400
+impl From<sqlx::Error> for SdkError {
+    fn from(e: sqlx::Error) -> Self {
+        PgExtError::from(e).into()
+    }
+}
+
+impl From<PgExtError> for SdkError {
+    fn from(e: PgExtError) -> Self {
+        match e {
+            PgExtError::Database(e) => SdkError::Database(e),
+            violation => SdkError::Constraint(violation),
+        }
+    }
+}
+
+impl IntoResponse for SdkError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            // JwtError 已有自己的回應格式，直接沿用
+            SdkError::Jwt(e) => return e.into_response(),
+            SdkError::Fcm(FcmError::InvalidArgument(msg)) => (StatusCode::BAD_REQUEST, msg),
+            SdkError::Fcm(FcmError::NoToken) => (
+                StatusCode::NOT_FOUND,
+                "User does not have an FCM token".to_string(),
+            ),
+            SdkError::Fcm(FcmError::Repository(e)) => {
+                tracing::error!("FCM token 查詢失敗：{}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to look up notification tokens".to_string(),
+                )
+            }
+            SdkError::Fcm(FcmError::QuotaExceeded) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Notification quota exceeded".to_string(),
+            ),
+            SdkError::Fcm(_) => (
+                StatusCode::BAD_GATEWAY,
+                "Failed to send notification".to_string(),
+            ),
+            SdkError::FirebaseAuth(e) => {
+                let status = match e {
+                    FirebaseAuthError::EmailNotFound
+                    | FirebaseAuthError::InvalidPassword
+                    | FirebaseAuthError::InvalidCustomToken
+                    | FirebaseAuthError::CredentialMismatch => StatusCode::UNAUTHORIZED,
+                    FirebaseAuthError::UserDisabled => StatusCode::FORBIDDEN,
+                    FirebaseAuthError::UserNotFound => StatusCode::NOT_FOUND,
+                    FirebaseAuthError::EmailExists => StatusCode::CONFLICT,
+                    FirebaseAuthError::TooManyAttempts => StatusCode::TOO_MANY_REQUESTS,
+                    FirebaseAuthError::ExpiredOobCode | FirebaseAuthError::InvalidOobCode => {
+                        StatusCode::BAD_REQUEST
+                    }
+                    FirebaseAuthError::Unknown(_) | FirebaseAuthError::Request(_) => {
+                        return (StatusCode::BAD_GATEWAY, "Authentication service error")
+                            .into_response()
+                    }
+                };
+                (status, e.to_string())
+            }
+            SdkError::Database(sqlx::Error::RowNotFound) => {
+                (StatusCode::NOT_FOUND, "Resource not found".to_string())
+            }
+            SdkError::Constraint(PgExtError::UniqueViolation { .. }) => {
+                (StatusCode::CONFLICT, "Resource already exists".to_string())
+            }
+            SdkError::Constraint(e) => {
+                tracing::warn!("資料違反約束：{}", e);
+                (StatusCode::BAD_REQUEST, "Constraint violation".to_string())
+            }
+            SdkError::Database(e) => {
+                tracing::error!("資料庫錯誤：{}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Database error".to_string(),
+                )
+            }
+        };
+
+        (status, message).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_of(error: impl Into<SdkError>) -> StatusCode {
+        error.into().into_response().status()
+    }
+
+    #[test]
+    fn test_from_conversions() {
+        assert!(matches!(
+            SdkError::from(JwtError::MissingToken),
+            SdkError::Jwt(JwtError::MissingToken)
+        ));
+        assert!(matches!(
+            SdkError::from(FcmError::Unregistered),
+            SdkError::Fcm(FcmError::Unregistered)
+        ));
+        assert!(matches!(
+            SdkError::from(FirebaseAuthError::EmailExists),
+            SdkError::FirebaseAuth(FirebaseAuthError::EmailExists)
+        ));
+        assert!(matches!(
+            SdkError::from(sqlx::Error::RowNotFound),
+            SdkError::Database(sqlx::Error::RowNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_question_mark_propagation() {
+        fn handler() -> Result<(), SdkError> {
+            Err(JwtError::InvalidToken)?;
+            Ok(())
+        }
+        assert!(matches!(
+            handler(),
+            Err(SdkError::Jwt(JwtError::InvalidToken))
+        ));
+    }
+
+    #[test]
+    fn test_status_mapping() {
+        assert_eq!(status_of(JwtError::MissingToken), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status_of(JwtError::NoValidKeyError),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status_of(FcmError::InvalidArgument("bad topic".to_string())),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status_of(FcmError::QuotaExceeded),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(status_of(FcmError::Unregistered), StatusCode::BAD_GATEWAY);
+        assert_eq!(status_of(FcmError::NoToken), StatusCode::NOT_FOUND);
+        assert_eq!(
+            status_of(FcmError::Repository("connection refused".into())),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status_of(FirebaseAuthError::InvalidPassword),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status_of(FirebaseAuthError::UserDisabled),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status_of(FirebaseAuthError::UserNotFound),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status_of(FirebaseAuthError::EmailExists),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            status_of(FirebaseAuthError::TooManyAttempts),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            status_of(FirebaseAuthError::ExpiredOobCode),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status_of(FirebaseAuthError::Unknown("OOPS".to_string())),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(status_of(sqlx::Error::RowNotFound), StatusCode::NOT_FOUND);
+        assert_eq!(
+            status_of(PgExtError::UniqueViolation {
+                constraint: Some("users_email_key".to_string())
+            }),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            status_of(PgExtError::NotNull {
+                column: Some("email".to_string())
+            }),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status_of(sqlx::Error::PoolTimedOut),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
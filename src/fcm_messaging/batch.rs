@@ -0,0 +1,307 @@
+use super::models::{self, Target};
+use super::{BuiltMessage, FCMSender, FcmError, SendResult};
+use rand::Rng;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+
+// FCM batch 端點單次請求最多可包含的子請求數
+const MAX_BATCH_SIZE: usize = 500;
+
+impl FCMSender {
+    // 以 multipart/mixed 將多則訊息合併成一次 /batch 請求，每 500 則一組依序送出，
+    // 結果順序與 messages 相同，個別訊息的錯誤放在各自的 SendResult 中。
+    // batch 請求本身失敗（例如 Unauthenticated、QuotaExceeded）時不會重試，直接回傳該錯誤；
+    // 先前已送出的組不受影響，但它們的結果不會回傳。
+    // 注意 Google 已宣布停用 batch 端點，仍可使用的專案才適合呼叫
+    pub async fn send_batch(
+        &self,
+        messages: Vec<BuiltMessage>,
+    ) -> Result<Vec<SendResult>, FcmError> {
+        let mut results = Vec::with_capacity(messages.len());
+        for chunk in messages.chunks(MAX_BATCH_SIZE) {
+            if self.noop_messages.is_some() {
+                for message in chunk {
+                    results.push(SendResult {
+                        token: target_value(&message.0),
+                        outcome: self.post_message(&message.0, false).await,
+                    });
+                }
+                continue;
+            }
+
+            let outcomes = self.post_batch(chunk).await.inspect_err(|error| {
+                tracing::warn!("FCM batch 請求失敗：{}", error);
+            })?;
+            results.extend(
+                chunk
+                    .iter()
+                    .zip(outcomes)
+                    .map(|(message, outcome)| SendResult {
+                        token: target_value(&message.0),
+                        outcome,
+                    }),
+            );
+        }
+        Ok(results)
+    }
+
+    async fn post_batch(
+        &self,
+        messages: &[BuiltMessage],
+    ) -> Result<Vec<Result<String, FcmError>>, FcmError> {
+        let boundary = format!("batch_{:016x}", rand::thread_rng().gen::<u64>());
+        let body = self.batch_body(messages, &boundary)?;
+
+        let mut request = self
+            .client
+            .post(format!("{}/batch", self.base_url))
+            .headers(self.headers.clone())
+            .bearer_auth(self.access_token().await?)
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/mixed; boundary={}", boundary),
+            )
+            .body(body);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(FcmError::from_response(status, &body));
+        }
+        let boundary = boundary_of(&content_type).ok_or_else(|| {
+            FcmError::Other(format!("unexpected batch content type: {}", content_type))
+        })?;
+        parse_batch_response(&body, boundary, messages.len())
+    }
+
+    fn batch_body(&self, messages: &[BuiltMessage], boundary: &str) -> Result<String, FcmError> {
+        let path = format!("/v1/projects/{}/messages:send", self.project_id);
+        let mut body = String::new();
+        for (index, message) in messages.iter().enumerate() {
+            let payload = serde_json::to_string(&models::FCMMessage {
+                validate_only: self.dry_run,
+                message: &message.0,
+            })
+            .map_err(|e| FcmError::Other(e.to_string()))?;
+            body.push_str(&format!(
+                "--{boundary}\r\n\
+                 Content-Type: application/http\r\n\
+                 Content-Transfer-Encoding: binary\r\n\
+                 Content-ID: <{id}>\r\n\
+                 \r\n\
+                 POST {path}\r\n\
+                 Content-Type: application/json\r\n\
+                 \r\n\
+                 {payload}\r\n",
+                boundary = boundary,
+                id = index + 1,
+                path = path,
+                payload = payload,
+            ));
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+        Ok(body)
+    }
+}
+
+fn target_value(message: &models::Message) -> String {
+    match &message.target {
+        Target::Token(value) | Target::Topic(value) | Target::Condition(value) => value.clone(),
+    }
+}
+
+// 例如 multipart/mixed; boundary=batch_abc 或 boundary="batch_abc"
+fn boundary_of(content_type: &str) -> Option<&str> {
+    content_type
+        .split(';')
+        .filter_map(|param| param.trim().split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim_matches('"'))
+}
+
+// 每個回應 part 的 Content-ID 為 response-N，對應請求中的第 N 則訊息；缺少時依出現順序對應
+fn parse_batch_response(
+    body: &str,
+    boundary: &str,
+    expected: usize,
+) -> Result<Vec<Result<String, FcmError>>, FcmError> {
+    let mut outcomes: Vec<Option<Result<String, FcmError>>> = (0..expected).map(|_| None).collect();
+    let delimiter = format!("--{}", boundary);
+    let parts = body
+        .split(delimiter.as_str())
+        .skip(1)
+        .take_while(|part| !part.starts_with("--"));
+
+    for (position, part) in parts.enumerate() {
+        let (part_headers, http) = split_headers(part.trim_start_matches(['\r', '\n']));
+        let index = part_headers
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("content-id"))
+            .and_then(|(_, value)| {
+                value
+                    .trim()
+                    .trim_matches(['<', '>'])
+                    .strip_prefix("response-")?
+                    .parse::<usize>()
+                    .ok()
+            })
+            .map(|id| id.saturating_sub(1))
+            .unwrap_or(position);
+
+        let (response_head, response_body) = split_headers(http);
+        let status = response_head
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .ok_or_else(|| FcmError::Other(format!("invalid batch response part: {}", part)))?;
+        let response_body = response_body.trim();
+
+        let outcome = if status.is_success() {
+            serde_json::from_str::<models::SendResponse>(response_body)
+                .map(|response| response.name)
+                .map_err(|e| FcmError::Other(e.to_string()))
+        } else {
+            Err(FcmError::from_response(status, response_body))
+        };
+        if let Some(slot) = outcomes.get_mut(index) {
+            *slot = Some(outcome);
+        }
+    }
+
+    Ok(outcomes
+        .into_iter()
+        .map(|outcome| {
+            outcome.unwrap_or_else(|| {
+                Err(FcmError::Other(
+                    "missing response in batch result".to_string(),
+                ))
+            })
+        })
+        .collect())
+}
+
+// 以第一個空行分開標頭與內容，容許 \r\n 或 \n 換行
+fn split_headers(text: &str) -> (&str, &str) {
+    for separator in ["\r\n\r\n", "\n\n"] {
+        if let Some((head, rest)) = text.split_once(separator) {
+            return (head, rest);
+        }
+    }
+    (text, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fcm_messaging::MessageBuilder;
+    use crate::test_support::spawn_mock_server;
+    use axum::{
+        http::{header, HeaderMap},
+        response::IntoResponse,
+        Router,
+    };
+
+    #[tokio::test]
+    async fn test_send_batch_parses_multipart_response() {
+        let app = Router::new().fallback(|headers: HeaderMap, body: String| async move {
+            assert_eq!(headers[header::AUTHORIZATION], "Bearer test-token");
+            let content_type = headers[header::CONTENT_TYPE].to_str().unwrap();
+            let boundary = boundary_of(content_type).unwrap();
+            assert_eq!(body.matches("POST /v1/projects/test-project/messages:send").count(), 3);
+            assert!(body.ends_with(&format!("--{}--\r\n", boundary)));
+            assert!(body.contains(r#""token":"device-1""#));
+
+            // 故意打亂回應順序，確認依 Content-ID 對應
+            let response = "--batch_resp\r\n\
+                Content-Type: application/http\r\n\
+                Content-ID: response-2\r\n\
+                \r\n\
+                HTTP/1.1 404 Not Found\r\n\
+                Content-Type: application/json; charset=UTF-8\r\n\
+                \r\n\
+                {\"error\": {\"code\": 404, \"message\": \"not found\", \"status\": \"NOT_FOUND\", \"details\": [{\"errorCode\": \"UNREGISTERED\"}]}}\r\n\
+                --batch_resp\r\n\
+                Content-Type: application/http\r\n\
+                Content-ID: response-1\r\n\
+                \r\n\
+                HTTP/1.1 200 OK\r\n\
+                Content-Type: application/json; charset=UTF-8\r\n\
+                \r\n\
+                {\"name\": \"projects/test-project/messages/1\"}\r\n\
+                --batch_resp\r\n\
+                Content-Type: application/http\r\n\
+                Content-ID: response-3\r\n\
+                \r\n\
+                HTTP/1.1 200 OK\r\n\
+                Content-Type: application/json; charset=UTF-8\r\n\
+                \r\n\
+                {\"name\": \"projects/test-project/messages/3\"}\r\n\
+                --batch_resp--\r\n";
+            (
+                [(header::CONTENT_TYPE, "multipart/mixed; boundary=batch_resp")],
+                response,
+            )
+                .into_response()
+        });
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(spawn_mock_server(app).await);
+
+        let messages = ["device-1", "device-2", "device-3"]
+            .iter()
+            .map(|token| {
+                MessageBuilder::token(*token)
+                    .notification("Title", "Body")
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        let results = sender.send_batch(messages).await.unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].token, "device-1");
+        assert_eq!(
+            results[0].outcome.as_deref().unwrap(),
+            "projects/test-project/messages/1"
+        );
+        assert_eq!(results[1].token, "device-2");
+        assert!(matches!(results[1].outcome, Err(FcmError::Unregistered)));
+        assert_eq!(
+            results[2].outcome.as_deref().unwrap(),
+            "projects/test-project/messages/3"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_returns_typed_error_when_request_fails() {
+        let app = Router::new().fallback(|| async {
+            (
+                axum::http::StatusCode::UNAUTHORIZED,
+                r#"{"error": {"code": 401, "message": "bad token", "status": "UNAUTHENTICATED"}}"#,
+            )
+        });
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(spawn_mock_server(app).await);
+        let message = MessageBuilder::token("device-1")
+            .notification("Title", "Body")
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            sender.send_batch(vec![message]).await,
+            Err(FcmError::Unauthenticated)
+        ));
+    }
+}
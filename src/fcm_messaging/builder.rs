@@ -0,0 +1,100 @@
+use super::service_account::ServiceAccountTokenProvider;
+use super::{FCMSender, FcmError, RetryConfig, ServiceAccount, FCM_BASE_URL};
+use reqwest::{header::HeaderMap, Client};
+use std::sync::Arc;
+use std::time::Duration;
+
+// FCMSender 的設定：以固定的 access token 或 service account 驗證，二擇一
+#[derive(Debug, Clone, Default)]
+pub struct FcmSenderBuilder {
+    project_id: Option<String>,
+    access_token: Option<String>,
+    service_account: Option<ServiceAccount>,
+    timeout: Option<Duration>,
+    retry: Option<RetryConfig>,
+    client: Option<Client>,
+    base_url: Option<String>,
+    dry_run: bool,
+    headers: HeaderMap,
+}
+
+impl FcmSenderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 使用 service account 時可省略，預設取自 service account 的 project_id
+    pub fn project_id(mut self, project_id: impl Into<String>) -> Self {
+        self.project_id = Some(project_id.into());
+        self
+    }
+
+    pub fn access_token(mut self, access_token: impl Into<String>) -> Self {
+        self.access_token = Some(access_token.into());
+        self
+    }
+
+    // access token 會在每次發送前視需要自動換發，設定後會忽略 access_token
+    pub fn service_account(mut self, account: ServiceAccount) -> Self {
+        self.service_account = Some(account);
+        self
+    }
+
+    // 每個 FCM 請求的逾時，與 client 本身的設定分開，自訂 client 時同樣有效
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    // 附加在每個請求上的標頭，例如追蹤用的 X-Cloud-Trace-Context；與自訂 client 的設定並存
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    // 只向 FCM 驗證訊息（validate_only），不會真的推播
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn build(self) -> Result<FCMSender, FcmError> {
+        let service_account = self.service_account.map(ServiceAccountTokenProvider::new);
+        let project_id = self
+            .project_id
+            .or_else(|| {
+                service_account
+                    .as_ref()
+                    .map(|provider| provider.project_id().to_string())
+            })
+            .ok_or_else(|| FcmError::Other("project_id is required".to_string()))?;
+
+        Ok(FCMSender {
+            client: self.client.unwrap_or_default(),
+            base_url: self.base_url.unwrap_or_else(|| FCM_BASE_URL.to_string()),
+            project_id,
+            access_token: self.access_token.unwrap_or_default(),
+            service_account: service_account.map(Arc::new),
+            noop_messages: None,
+            retry: self.retry.unwrap_or_default(),
+            timeout: self.timeout,
+            dry_run: self.dry_run,
+            headers: self.headers,
+        })
+    }
+}
@@ -0,0 +1,712 @@
+use super::models::{
+    AndroidConfig, ApnsConfig, FCMMessage, Message, Notification, Priority, Target, WebpushConfig,
+};
+use super::FcmError;
+use serde_json::Value;
+use std::time::Duration;
+
+// 經過驗證、可直接交給 FCMSender::send 的訊息
+#[derive(Debug, Clone)]
+pub struct BuiltMessage(pub(crate) Message);
+
+impl BuiltMessage {
+    // 序列化成 FCM v1 send 請求的 body（{"message": {...}}）
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(FCMMessage {
+            validate_only: false,
+            message: &self.0,
+        })
+        .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    target: Target,
+    notification: Option<Notification>,
+    image: Option<String>,
+    data: Option<Value>,
+    priority: Option<Priority>,
+    ttl: Option<Duration>,
+    collapse_key: Option<String>,
+    android: Option<AndroidConfig>,
+    apns: Option<ApnsConfig>,
+    webpush: Option<WebpushConfig>,
+}
+
+impl MessageBuilder {
+    fn new(target: Target) -> Self {
+        Self {
+            target,
+            notification: None,
+            image: None,
+            data: None,
+            priority: None,
+            ttl: None,
+            collapse_key: None,
+            android: None,
+            apns: None,
+            webpush: None,
+        }
+    }
+
+    // 前後的空白（例如從表單或資料庫帶出的換行）會在 build 時去除
+    pub fn token(token: impl Into<String>) -> Self {
+        Self::new(Target::Token(token.into()))
+    }
+
+    pub fn topic(topic: impl Into<String>) -> Self {
+        Self::new(Target::Topic(topic.into()))
+    }
+
+    pub fn condition(condition: impl Into<String>) -> Self {
+        Self::new(Target::Condition(condition.into()))
+    }
+
+    pub fn notification(mut self, title: impl Into<String>, body: impl Into<String>) -> Self {
+        self.notification = Some(Notification {
+            title: title.into(),
+            body: body.into(),
+            image: None,
+        });
+        self
+    }
+
+    // 通知的大圖網址，必須是 http(s) URL，且需搭配 notification 使用
+    pub fn image(mut self, url: impl Into<String>) -> Self {
+        self.image = Some(url.into());
+        self
+    }
+
+    pub fn data(mut self, data: impl Into<Option<Value>>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    // 訊息在裝置離線時的保留時間，超過後 FCM 不再投遞
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    // 同一個 key 的後續訊息會取代裝置尚未收到的前一則，例如「你有 3 則未讀」；
+    // 會設定 Android 的 collapse_key 與 APNs 的 apns-collapse-id
+    pub fn collapse_key(mut self, key: impl Into<String>) -> Self {
+        self.collapse_key = Some(key.into());
+        self
+    }
+
+    pub fn android(mut self, config: AndroidConfig) -> Self {
+        self.android = Some(config);
+        self
+    }
+
+    pub fn apns(mut self, config: ApnsConfig) -> Self {
+        self.apns = Some(config);
+        self
+    }
+
+    pub fn webpush(mut self, config: WebpushConfig) -> Self {
+        self.webpush = Some(config);
+        self
+    }
+
+    pub fn build(self) -> Result<BuiltMessage, FcmError> {
+        let target = match self.target {
+            Target::Token(token) => {
+                let token = token.trim();
+                validate_token(token)?;
+                Target::Token(token.to_string())
+            }
+            Target::Topic(topic) => Target::Topic(validate_topic(&topic)?.to_string()),
+            Target::Condition(condition) if condition.trim().is_empty() => {
+                return Err(FcmError::InvalidArgument(
+                    "condition must not be empty".to_string(),
+                ))
+            }
+            target => target,
+        };
+        if let Some(data) = &self.data {
+            validate_data(data)?;
+        }
+        let mut notification = self.notification;
+        if let Some(image) = self.image {
+            validate_image_url(&image)?;
+            let notification = notification.as_mut().ok_or_else(|| {
+                FcmError::InvalidArgument("image requires a notification".to_string())
+            })?;
+            notification.image = Some(image);
+        }
+
+        if let Some(key) = &self.collapse_key {
+            validate_collapse_key(key)?;
+        }
+
+        let mut android = self.android;
+        let mut apns = self.apns;
+        // priority / ttl / collapse_key 同時套用到 Android 與 APNs，已在平台設定中明確指定的值優先
+        if self.priority.is_some() || self.ttl.is_some() || self.collapse_key.is_some() {
+            let android = android.get_or_insert_with(AndroidConfig::default);
+            let apns = apns.get_or_insert_with(ApnsConfig::default);
+            if let Some(priority) = self.priority {
+                android.priority.get_or_insert(priority);
+                let apns_priority = match priority {
+                    Priority::High => "10",
+                    Priority::Normal => "5",
+                };
+                apns.headers
+                    .entry("apns-priority".to_string())
+                    .or_insert_with(|| apns_priority.to_string());
+            }
+            if let Some(ttl) = self.ttl {
+                android
+                    .ttl
+                    .get_or_insert_with(|| format!("{}s", ttl.as_secs()));
+                let expiration = chrono::Utc::now().timestamp() + ttl.as_secs() as i64;
+                apns.headers
+                    .entry("apns-expiration".to_string())
+                    .or_insert_with(|| expiration.to_string());
+            }
+            if let Some(key) = self.collapse_key {
+                apns.headers
+                    .entry("apns-collapse-id".to_string())
+                    .or_insert_with(|| key.clone());
+                android.collapse_key.get_or_insert(key);
+            }
+        }
+
+        Ok(BuiltMessage(Message {
+            target,
+            notification,
+            data: self.data,
+            android,
+            apns,
+            webpush: self.webpush,
+        }))
+    }
+}
+
+// FCM registration token 目前是 ASCII 英數字加上 - _ : 的字串，但官方未保證格式，
+// 這裡只擋下明顯錯誤的值（空字串、含空白或控制字元、非 ASCII、過長），避免浪費一次 API 請求
+const MAX_TOKEN_LEN: usize = 4096;
+
+pub fn validate_token(token: &str) -> Result<(), FcmError> {
+    if token.is_empty() {
+        return Err(FcmError::InvalidArgument(
+            "token must not be empty".to_string(),
+        ));
+    }
+    if token.len() > MAX_TOKEN_LEN {
+        return Err(FcmError::InvalidArgument(format!(
+            "token is too long: {} bytes",
+            token.len()
+        )));
+    }
+    if !token.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(FcmError::InvalidArgument(
+            "token contains whitespace or non-ASCII characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// APNs 的 apns-collapse-id 最長 64 bytes
+const MAX_COLLAPSE_KEY_LEN: usize = 64;
+
+fn validate_collapse_key(key: &str) -> Result<(), FcmError> {
+    if key.is_empty() || key.len() > MAX_COLLAPSE_KEY_LEN {
+        return Err(FcmError::InvalidArgument(format!(
+            "collapse key must be 1 to {} bytes",
+            MAX_COLLAPSE_KEY_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn validate_image_url(url: &str) -> Result<(), FcmError> {
+    let valid = reqwest::Url::parse(url)
+        .map(|parsed| matches!(parsed.scheme(), "http" | "https") && parsed.has_host())
+        .unwrap_or(false);
+    if valid {
+        Ok(())
+    } else {
+        Err(FcmError::InvalidArgument(format!(
+            "image must be an http(s) URL: {}",
+            url
+        )))
+    }
+}
+
+// FCM 主題名稱只允許 [a-zA-Z0-9-_.~%]+
+pub(crate) fn validate_topic(topic: &str) -> Result<&str, FcmError> {
+    let name = topic.strip_prefix("/topics/").unwrap_or(topic);
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.~%".contains(c));
+    if valid {
+        Ok(name)
+    } else {
+        Err(FcmError::InvalidArgument(format!(
+            "invalid topic name: {}",
+            topic
+        )))
+    }
+}
+
+// FCM 保留的 data key，送出時會被丟棄或直接拒絕
+const RESERVED_DATA_KEYS: [&str; 2] = ["from", "message_type"];
+const RESERVED_DATA_KEY_PREFIXES: [&str; 2] = ["google", "gcm"];
+
+fn is_reserved_data_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    RESERVED_DATA_KEYS.contains(&key.as_str())
+        || RESERVED_DATA_KEY_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+// FCM 的 data 必須是字串對字串的物件，且不能使用保留的 key
+pub(crate) fn validate_data(data: &Value) -> Result<(), FcmError> {
+    let map = data.as_object().ok_or_else(|| {
+        FcmError::InvalidArgument("data must be a JSON object of strings".to_string())
+    })?;
+
+    let reserved: Vec<&str> = map
+        .keys()
+        .map(String::as_str)
+        .filter(|key| is_reserved_data_key(key))
+        .collect();
+    if !reserved.is_empty() {
+        return Err(FcmError::InvalidArgument(format!(
+            "data contains reserved keys: {}",
+            reserved.join(", ")
+        )));
+    }
+
+    let invalid: Vec<&str> = map
+        .iter()
+        .filter(|(_, value)| !value.is_string())
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(FcmError::InvalidArgument(format!(
+            "data values must be strings: {}",
+            invalid.join(", ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fcm_messaging::{
+        AndroidNotification, ApnsPayload, Aps, ApsAlert, WebpushFcmOptions, WebpushNotification,
+    };
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_target_serializes_to_matching_key() {
+        let json = MessageBuilder::topic("news").build().unwrap().to_json();
+        assert_eq!(json["message"]["topic"], "news");
+        assert!(json["message"].get("token").is_none());
+
+        let condition = "'news' in topics || 'sports' in topics";
+        let json = MessageBuilder::condition(condition)
+            .build()
+            .unwrap()
+            .to_json();
+        assert_eq!(json["message"]["condition"], condition);
+        assert!(json["message"].get("topic").is_none());
+
+        let json = MessageBuilder::token("abc").build().unwrap().to_json();
+        assert_eq!(json["message"]["token"], "abc");
+    }
+
+    #[test]
+    fn test_validate_token() {
+        let valid = "dGVzdC1kZXZpY2U:APA91bH-x_1y2z3AbCdEfGhIjKlMnOpQrStUvWxYz0123456789";
+        assert!(validate_token(valid).is_ok());
+        assert!(matches!(
+            validate_token(""),
+            Err(FcmError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            validate_token("   "),
+            Err(FcmError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            validate_token("abc def"),
+            Err(FcmError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            validate_token(&"a".repeat(MAX_TOKEN_LEN + 1)),
+            Err(FcmError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_is_trimmed_and_validated_on_build() {
+        let json = MessageBuilder::token(" abc\n").build().unwrap().to_json();
+        assert_eq!(json["message"]["token"], "abc");
+
+        assert!(matches!(
+            MessageBuilder::token(" \t ").build(),
+            Err(FcmError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_topic() {
+        assert_eq!(validate_topic("news").unwrap(), "news");
+        assert_eq!(
+            validate_topic("/topics/news-2024_v1.0~%20").unwrap(),
+            "news-2024_v1.0~%20"
+        );
+        assert!(matches!(
+            validate_topic(""),
+            Err(FcmError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            validate_topic("bad topic!"),
+            Err(FcmError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_simple_message_has_no_platform_blocks() {
+        let json = MessageBuilder::token("abc")
+            .notification("Title", "Body")
+            .build()
+            .unwrap()
+            .to_json();
+        assert_eq!(
+            json,
+            json!({
+                "message": {
+                    "token": "abc",
+                    "notification": { "title": "Title", "body": "Body" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_collapse_key_sets_android_and_apns_fields() {
+        let json = MessageBuilder::token("abc")
+            .notification("Inbox", "You have 3 unread messages")
+            .collapse_key("unread-count")
+            .build()
+            .unwrap()
+            .to_json();
+        assert_eq!(json["message"]["android"]["collapse_key"], "unread-count");
+        assert_eq!(
+            json["message"]["apns"]["headers"]["apns-collapse-id"],
+            "unread-count"
+        );
+
+        // 平台設定中明確指定的值優先
+        let json = MessageBuilder::token("abc")
+            .collapse_key("unread-count")
+            .android(AndroidConfig {
+                collapse_key: Some("android-key".to_string()),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .to_json();
+        assert_eq!(json["message"]["android"]["collapse_key"], "android-key");
+        assert_eq!(
+            json["message"]["apns"]["headers"]["apns-collapse-id"],
+            "unread-count"
+        );
+
+        for key in ["", &"k".repeat(MAX_COLLAPSE_KEY_LEN + 1)] {
+            assert!(matches!(
+                MessageBuilder::token("abc").collapse_key(key).build(),
+                Err(FcmError::InvalidArgument(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_data_only_message_has_no_notification() {
+        let json = MessageBuilder::token("abc")
+            .data(json!({ "type": "sync" }))
+            .build()
+            .unwrap()
+            .to_json();
+        assert_eq!(
+            json,
+            json!({
+                "message": {
+                    "token": "abc",
+                    "data": { "type": "sync" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_non_string_data_value_is_rejected() {
+        let result = MessageBuilder::token("abc")
+            .data(json!({ "type": "sync", "count": 3 }))
+            .build();
+        match result {
+            Err(FcmError::InvalidArgument(message)) => assert!(message.contains("count")),
+            other => panic!("非字串的 data 應該被拒絕，實際得到 {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reserved_data_keys_are_rejected() {
+        let result = MessageBuilder::token("abc")
+            .data(json!({ "from": "server", "google.sent_time": "1", "type": "sync" }))
+            .build();
+        match result {
+            Err(FcmError::InvalidArgument(message)) => {
+                assert!(message.contains("reserved"));
+                assert!(message.contains("from"));
+                assert!(message.contains("google.sent_time"));
+                assert!(!message.contains("type"));
+            }
+            other => panic!("保留的 data key 應該被拒絕，實際得到 {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_android_config_shape() {
+        let json = MessageBuilder::token("abc")
+            .notification("Title", "Body")
+            .android(AndroidConfig {
+                notification: Some(AndroidNotification {
+                    channel_id: Some("alerts".to_string()),
+                    sound: Some("default".to_string()),
+                    click_action: Some("OPEN_ALERT".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .to_json();
+        assert_eq!(
+            json["message"]["android"],
+            json!({
+                "notification": {
+                    "channel_id": "alerts",
+                    "sound": "default",
+                    "click_action": "OPEN_ALERT"
+                }
+            })
+        );
+    }
+
+    // 在地化的鍵放在 android.notification 與 apns.payload.aps.alert 中，頂層的 title / body 照常送出
+    #[test]
+    fn test_localized_notification_shape() {
+        let json = MessageBuilder::token("abc")
+            .notification("New message", "Alice sent you a message")
+            .android(AndroidConfig {
+                notification: Some(AndroidNotification {
+                    title_loc_key: Some("new_message_title".to_string()),
+                    body_loc_key: Some("new_message_body".to_string()),
+                    body_loc_args: vec!["Alice".to_string(), "3".to_string()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .apns(ApnsConfig {
+                payload: Some(ApnsPayload {
+                    aps: Aps {
+                        alert: Some(ApsAlert {
+                            title_loc_key: Some("NEW_MESSAGE_TITLE".to_string()),
+                            body_loc_key: Some("NEW_MESSAGE_BODY".to_string()),
+                            body_loc_args: vec!["Alice".to_string()],
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                }),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .to_json();
+
+        assert_eq!(
+            json["message"]["notification"],
+            json!({ "title": "New message", "body": "Alice sent you a message" })
+        );
+        assert_eq!(
+            json["message"]["android"],
+            json!({
+                "notification": {
+                    "title_loc_key": "new_message_title",
+                    "body_loc_key": "new_message_body",
+                    "body_loc_args": ["Alice", "3"]
+                }
+            })
+        );
+        assert_eq!(
+            json["message"]["apns"]["payload"]["aps"],
+            json!({
+                "alert": {
+                    "title-loc-key": "NEW_MESSAGE_TITLE",
+                    "loc-key": "NEW_MESSAGE_BODY",
+                    "loc-args": ["Alice"]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_image_and_platform_icons() {
+        let json = MessageBuilder::token("abc")
+            .notification("Sale", "50% off today")
+            .image("https://cdn.example.com/banner.png")
+            .android(AndroidConfig {
+                notification: Some(AndroidNotification {
+                    icon: Some("ic_sale".to_string()),
+                    color: Some("#ff5722".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .webpush(WebpushConfig {
+                notification: Some(WebpushNotification {
+                    icon: Some("https://cdn.example.com/icon.png".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .to_json();
+
+        assert_eq!(
+            json["message"]["notification"],
+            json!({
+                "title": "Sale",
+                "body": "50% off today",
+                "image": "https://cdn.example.com/banner.png"
+            })
+        );
+        assert_eq!(
+            json["message"]["android"]["notification"],
+            json!({ "icon": "ic_sale", "color": "#ff5722" })
+        );
+        assert_eq!(
+            json["message"]["webpush"]["notification"]["icon"],
+            "https://cdn.example.com/icon.png"
+        );
+    }
+
+    #[test]
+    fn test_image_must_be_http_url_with_notification() {
+        for url in ["ftp://example.com/a.png", "not a url", "https://"] {
+            assert!(
+                matches!(
+                    MessageBuilder::token("abc")
+                        .notification("T", "B")
+                        .image(url)
+                        .build(),
+                    Err(FcmError::InvalidArgument(_))
+                ),
+                "{} 應該被拒絕",
+                url
+            );
+        }
+        assert!(matches!(
+            MessageBuilder::token("abc")
+                .image("https://example.com/a.png")
+                .build(),
+            Err(FcmError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_high_priority_with_ttl() {
+        let now = chrono::Utc::now().timestamp();
+        let json = MessageBuilder::token("abc")
+            .notification("Alert", "Body")
+            .priority(Priority::High)
+            .ttl(Duration::from_secs(3600))
+            .build()
+            .unwrap()
+            .to_json();
+
+        assert_eq!(
+            json["message"]["android"],
+            json!({ "priority": "HIGH", "ttl": "3600s" })
+        );
+        let headers = &json["message"]["apns"]["headers"];
+        assert_eq!(headers["apns-priority"], "10");
+        let expiration: i64 = headers["apns-expiration"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((now + 3600..=now + 3602).contains(&expiration));
+    }
+
+    #[test]
+    fn test_apns_config_shape() {
+        let json = MessageBuilder::token("abc")
+            .notification("Title", "Body")
+            .apns(ApnsConfig {
+                headers: HashMap::from([("apns-push-type".to_string(), "alert".to_string())]),
+                payload: Some(ApnsPayload {
+                    aps: Aps {
+                        sound: Some("default".to_string()),
+                        badge: Some(3),
+                        ..Default::default()
+                    },
+                }),
+            })
+            .build()
+            .unwrap()
+            .to_json();
+        assert_eq!(
+            json["message"]["apns"],
+            json!({
+                "headers": { "apns-push-type": "alert" },
+                "payload": { "aps": { "sound": "default", "badge": 3 } }
+            })
+        );
+    }
+
+    #[test]
+    fn test_webpush_config_shape() {
+        let json = MessageBuilder::token("abc")
+            .notification("Title", "Body")
+            .webpush(WebpushConfig {
+                notification: Some(WebpushNotification {
+                    title: Some("Web title".to_string()),
+                    ..Default::default()
+                }),
+                fcm_options: Some(WebpushFcmOptions {
+                    link: Some("https://example.com/inbox".to_string()),
+                }),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .to_json();
+        assert_eq!(
+            json["message"]["webpush"],
+            json!({
+                "notification": { "title": "Web title" },
+                "fcm_options": { "link": "https://example.com/inbox" }
+            })
+        );
+    }
+}
@@ -1,29 +1,31 @@
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use futures::stream::{self, Stream, StreamExt};
+use rand::Rng;
+use reqwest::{header::HeaderMap, Client};
 use serde_json::Value;
-use std::{error::Error, future::Future};
+use std::{
+    collections::HashSet,
+    error::Error,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-mod models {
-    use super::*;
-
-    #[derive(Debug, Serialize, Deserialize)]
-    pub(crate) struct FCMMessage {
-        pub message: Message,
-    }
-
-    #[derive(Debug, Serialize, Deserialize)]
-    pub(crate) struct Message {
-        pub token: String,
-        pub notification: Notification,
-        pub data: Option<Value>,
-    }
+mod batch;
+mod builder;
+mod message;
+mod models;
+mod pg_repository;
+mod service_account;
 
-    #[derive(Debug, Serialize, Deserialize)]
-    pub(crate) struct Notification {
-        pub title: String,
-        pub body: String,
-    }
-}
+pub use builder::FcmSenderBuilder;
+pub use message::{validate_token, BuiltMessage, MessageBuilder};
+pub use models::{
+    AndroidConfig, AndroidNotification, ApnsConfig, ApnsPayload, Aps, ApsAlert, Priority,
+    WebpushConfig, WebpushFcmOptions, WebpushNotification,
+};
+pub use pg_repository::{PgFcmTokenConfig, PgFcmTokenRepository};
+pub use service_account::ServiceAccount;
+use service_account::ServiceAccountTokenProvider;
 
 // 定義一個錯誤類型用於不支援的操作
 #[derive(Debug)]
@@ -37,74 +39,540 @@ impl std::fmt::Display for UnsupportedOperationError {
 
 impl Error for UnsupportedOperationError {}
 
+// FCMTokenRepository 回傳的錯誤，發送時會包成 FcmError::Repository
+pub type RepositoryError = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug)]
+pub enum FcmError {
+    Unregistered,
+    InvalidArgument(String),
+    SenderIdMismatch,
+    QuotaExceeded,
+    Unauthenticated,
+    Auth(String),
+    Request(reqwest::Error),
+    // 查詢 token 失敗，與 FCM 拒絕 token 的錯誤區分
+    Repository(RepositoryError),
+    NoToken,
+    Other(String),
+}
+
+impl std::fmt::Display for FcmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FcmError::Unregistered => write!(f, "FCM token is no longer registered"),
+            FcmError::InvalidArgument(msg) => write!(f, "Invalid FCM request: {}", msg),
+            FcmError::SenderIdMismatch => write!(f, "FCM token belongs to a different sender"),
+            FcmError::QuotaExceeded => write!(f, "FCM sending quota exceeded"),
+            FcmError::Unauthenticated => write!(f, "FCM request was not authenticated"),
+            FcmError::Auth(msg) => write!(f, "FCM authentication failed: {}", msg),
+            FcmError::Request(e) => write!(f, "FCM request failed: {}", e),
+            FcmError::Repository(e) => write!(f, "FCM token lookup failed: {}", e),
+            FcmError::NoToken => write!(f, "User does not have an FCM token"),
+            FcmError::Other(msg) => write!(f, "FCM error: {}", msg),
+        }
+    }
+}
+
+impl Error for FcmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FcmError::Request(e) => Some(e),
+            FcmError::Repository(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl FcmError {
+    // 解析 FCM 的錯誤回應：優先使用 details 中的 errorCode，其次是 error.status，最後才看 HTTP 狀態碼
+    pub(crate) fn from_response(status: reqwest::StatusCode, body: &str) -> Self {
+        let envelope = serde_json::from_str::<models::ErrorResponse>(body).ok();
+        let Some(error) = envelope.map(|e| e.error) else {
+            return match status {
+                reqwest::StatusCode::NOT_FOUND => FcmError::Unregistered,
+                reqwest::StatusCode::UNAUTHORIZED => FcmError::Unauthenticated,
+                reqwest::StatusCode::TOO_MANY_REQUESTS => FcmError::QuotaExceeded,
+                _ => FcmError::Other(format!("{}: {}", status, body)),
+            };
+        };
+
+        let error_code = error
+            .details
+            .iter()
+            .find_map(|detail| detail.error_code.as_deref());
+        match error_code.or(error.status.as_deref()) {
+            Some("UNREGISTERED") | Some("NOT_FOUND") => FcmError::Unregistered,
+            Some("INVALID_ARGUMENT") => FcmError::InvalidArgument(error.message),
+            Some("SENDER_ID_MISMATCH") | Some("PERMISSION_DENIED") => FcmError::SenderIdMismatch,
+            Some("QUOTA_EXCEEDED") | Some("RESOURCE_EXHAUSTED") => FcmError::QuotaExceeded,
+            Some("UNAUTHENTICATED") | Some("THIRD_PARTY_AUTH_ERROR") => FcmError::Unauthenticated,
+            Some(code) => FcmError::Other(format!("{}: {}", code, error.message)),
+            None => FcmError::Other(format!("{}: {}", status, error.message)),
+        }
+    }
+}
+
+impl From<reqwest::Error> for FcmError {
+    fn from(e: reqwest::Error) -> Self {
+        FcmError::Request(e)
+    }
+}
+
+// 單一 token 的發送結果，成功時帶有 FCM 回傳的 message name
+#[derive(Debug)]
+pub struct SendResult {
+    pub token: String,
+    pub outcome: Result<String, FcmError>,
+}
+
+// send_to_users 中單一使用者的結果
+#[derive(Debug)]
+pub enum UserSendOutcome {
+    // 成功時帶有 FCM 回傳的 message name
+    Sent(String),
+    NoToken,
+    // repository 查詢 token 失敗，保留原始錯誤供呼叫端 downcast
+    LookupFailed(RepositoryError),
+    Failed(FcmError),
+}
+
+#[derive(Debug)]
+pub struct UserSendResult {
+    pub email: String,
+    pub outcome: UserSendOutcome,
+}
+
 pub trait FCMTokenRepository {
     fn get_user_fcm_token(
         &self,
         user_email: String,
-    ) -> impl Future<Output = Result<Option<String>, Box<dyn Error>>> + Send;
+    ) -> impl Future<Output = Result<Option<String>, RepositoryError>> + Send;
 
     // 預設實現，回傳不支援的錯誤
     fn get_group_fcm_tokens(
         &self,
         _group_id: i32,
-    ) -> impl Future<Output = Result<Vec<String>, Box<dyn Error>>> + Send {
-        async { Err(Box::new(UnsupportedOperationError) as Box<dyn Error>) }
+    ) -> impl Future<Output = Result<Vec<String>, RepositoryError>> + Send {
+        async { Err(Box::new(UnsupportedOperationError) as RepositoryError) }
+    }
+
+    // 所有已儲存的 token，供定期清理失效 token 使用；預設實現回傳不支援的錯誤
+    fn get_all_fcm_tokens(
+        &self,
+    ) -> impl Future<Output = Result<Vec<String>, RepositoryError>> + Send {
+        async { Err(Box::new(UnsupportedOperationError) as RepositoryError) }
+    }
+
+    // 群組發送時 FCM 回報 UNREGISTERED 的 token 會以此刪除；預設實現不做任何事
+    fn remove_token(
+        &self,
+        _token: &str,
+    ) -> impl Future<Output = Result<(), RepositoryError>> + Send {
+        async { Ok(()) }
+    }
+}
+
+const FCM_BASE_URL: &str = "https://fcm.googleapis.com";
+const DEFAULT_USER_CONCURRENCY: usize = 50;
+
+// 暫時性失敗（429/500/503 與連線錯誤）的重試設定，延遲以指數成長並加入隨機抖動
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    // 第 attempt 次重試前的等待時間，取指數退避值的 50%~100%
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
     }
 }
 
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 500 | 503)
+}
+
+// 只支援秒數形式的 Retry-After
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
 #[derive(Clone, Debug)]
 pub struct FCMSender {
     client: Client,
+    base_url: String,
     project_id: String,
     access_token: String,
+    service_account: Option<Arc<ServiceAccountTokenProvider>>,
+    noop_messages: Option<Arc<Mutex<Vec<Value>>>>,
+    retry: RetryConfig,
+    timeout: Option<Duration>,
+    dry_run: bool,
+    // 逐一套用到每個請求上，不影響 client 本身的設定
+    headers: HeaderMap,
 }
 
 impl FCMSender {
     pub fn new(project_id: String, access_token: String) -> Self {
-        Self {
-            client: Client::new(),
-            project_id,
-            access_token,
-        }
+        Self::builder()
+            .project_id(project_id)
+            .access_token(access_token)
+            .build()
+            .expect("project_id is set")
+    }
+
+    pub fn builder() -> FcmSenderBuilder {
+        FcmSenderBuilder::new()
+    }
+
+    // 本地開發用：不連線 FCM，只記錄將要發送的訊息並一律回傳成功
+    pub fn noop() -> Self {
+        let mut sender = Self::new("noop".to_string(), String::new());
+        sender.noop_messages = Some(Arc::new(Mutex::new(Vec::new())));
+        sender
+    }
+
+    // noop 模式下已「發送」的訊息（FCM v1 的 message 物件）
+    pub fn noop_messages(&self) -> Vec<Value> {
+        self.noop_messages
+            .as_ref()
+            .map(|messages| messages.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    // 以 service account 建立 sender，access token 會在每次發送前視需要自動換發
+    pub fn from_service_account(path_or_json: &str) -> Result<Self, FcmError> {
+        Self::builder()
+            .service_account(ServiceAccount::load(path_or_json)?)
+            .build()
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // dry run 模式下所有請求都帶 validate_only，FCM 只檢查訊息格式而不會真的推播
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    // 附加在每個請求（含 service account 換發 access token）上的標頭，例如 X-Cloud-Trace-Context；
+    // 與 client 的 default headers 並存，同名時以這裡的值為準
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
     }
 
     pub fn update_access_token(&mut self, token: String) {
         self.access_token = token;
+        self.service_account = None;
     }
 
-    async fn send_fcm_message(
+    async fn access_token(&self) -> Result<String, FcmError> {
+        match &self.service_account {
+            Some(provider) => provider.access_token(&self.client, &self.headers).await,
+            None => Ok(self.access_token.clone()),
+        }
+    }
+
+    // 成功時回傳 FCM 指派的 message name（projects/{project}/messages/{id}），可用於追蹤送達與對照日誌
+    pub async fn send_fcm_message(
         &self,
         token: &str,
         title: &str,
         body: &str,
         data: Option<Value>,
-    ) -> Result<(), Box<dyn Error>> {
-        use models::*;
+    ) -> Result<String, FcmError> {
+        let message = MessageBuilder::token(token)
+            .notification(title, body)
+            .data(data)
+            .build()?;
+        self.send(message).await
+    }
+
+    // 發送不含 notification 的純資料訊息，由 App 在背景自行處理；data 的值必須都是字串
+    pub async fn send_data_message(&self, token: &str, data: Value) -> Result<String, FcmError> {
+        let message = MessageBuilder::token(token).data(data).build()?;
+        self.send(message).await
+    }
+
+    // 發送以 MessageBuilder 組好的訊息，可帶 Android / APNs / WebPush 平台設定
+    pub async fn send(&self, message: BuiltMessage) -> Result<String, FcmError> {
+        self.post_message(&message.0, false).await
+    }
+
+    // 以 validate_only 模式向 FCM 驗證 token，不會真的推播到裝置
+    async fn check_token_with_fcm(&self, token: &str) -> Result<(), FcmError> {
+        let message = MessageBuilder::token(token).build()?;
+        self.post_message(&message.0, true).await.map(|_| ())
+    }
+
+    // 以最多 concurrency 個並行請求驗證 tokens，回傳應該從資料庫刪除的 token
+    // 只有 FCM 明確表示 token 無效（UNREGISTERED / INVALID_ARGUMENT / SENDER_ID_MISMATCH）才會列入，其他錯誤僅記錄警告
+    pub async fn find_invalid_tokens<S>(&self, tokens: S, concurrency: usize) -> HashSet<String>
+    where
+        S: Stream<Item = String>,
+    {
+        tokens
+            .map(|token| async move {
+                let outcome = self.check_token_with_fcm(&token).await;
+                (token, outcome)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .filter_map(|(token, outcome)| async move {
+                match outcome {
+                    Ok(()) => None,
+                    Err(FcmError::Unregistered)
+                    | Err(FcmError::InvalidArgument(_))
+                    | Err(FcmError::SenderIdMismatch) => Some(token),
+                    Err(e) => {
+                        tracing::warn!("無法確認 token {} 是否有效：{}", token, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await
+    }
+
+    // 驗證 repository 中所有 token，回傳待清理的清單，適合搭配 Scheduler 定期執行
+    pub async fn find_invalid_repository_tokens(
+        &self,
+        repository: &impl FCMTokenRepository,
+        concurrency: usize,
+    ) -> Result<HashSet<String>, FcmError> {
+        let tokens = repository
+            .get_all_fcm_tokens()
+            .await
+            .map_err(FcmError::Repository)?;
+        Ok(self
+            .find_invalid_tokens(stream::iter(tokens), concurrency)
+            .await)
+    }
+
+    async fn post_message(
+        &self,
+        message: &models::Message,
+        validate_only: bool,
+    ) -> Result<String, FcmError> {
+        let validate_only = validate_only || self.dry_run;
+        if validate_only && self.noop_messages.is_some() {
+            return Ok(format!(
+                "projects/{}/messages/noop-validate",
+                self.project_id
+            ));
+        }
+        if let Some(recorded) = &self.noop_messages {
+            let payload =
+                serde_json::to_value(message).map_err(|e| FcmError::Other(e.to_string()))?;
+            tracing::info!("[noop] 略過 FCM 發送：{}", payload);
+            let mut recorded = recorded.lock().unwrap();
+            recorded.push(payload);
+            return Ok(format!(
+                "projects/{}/messages/noop-{}",
+                self.project_id,
+                recorded.len()
+            ));
+        }
 
         let url = format!(
-            "https://fcm.googleapis.com/v1/projects/{}/messages:send",
-            self.project_id
+            "{}/v1/projects/{}/messages:send",
+            self.base_url, self.project_id
         );
 
-        let message = FCMMessage {
-            message: Message {
-                token: token.to_string(),
-                notification: Notification {
-                    title: title.to_string(),
-                    body: body.to_string(),
-                },
-                data,
-            },
-        };
+        let mut attempt = 0;
+        loop {
+            let access_token = self.access_token().await?;
+            let mut request = self
+                .client
+                .post(&url)
+                .headers(self.headers.clone())
+                .bearer_auth(access_token)
+                .json(&models::FCMMessage {
+                    validate_only,
+                    message,
+                });
+            if let Some(timeout) = self.timeout {
+                request = request.timeout(timeout);
+            }
+            let result = request.send().await;
 
-        self.client
-            .post(&url)
-            .bearer_auth(&self.access_token)
-            .json(&message)
-            .send()
-            .await?;
+            let (error, wait) = match result {
+                Ok(response) if response.status().is_success() => {
+                    let result: models::SendResponse = response.json().await?;
+                    return Ok(result.name);
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let wait = retry_after(response.headers());
+                    let body = response.text().await.unwrap_or_default();
+                    let error = FcmError::from_response(status, &body);
+                    if !is_retryable_status(status) {
+                        return Err(error);
+                    }
+                    (error, wait)
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => (FcmError::Request(e), None),
+                Err(e) => return Err(FcmError::Request(e)),
+            };
 
-        Ok(())
+            if attempt >= self.retry.max_retries {
+                return Err(error);
+            }
+            let delay = wait.unwrap_or_else(|| self.retry.backoff(attempt));
+            attempt += 1;
+            tracing::warn!(
+                "FCM 發送暫時失敗，{:?} 後進行第 {} 次重試：{}",
+                delay,
+                attempt,
+                error
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    // 發送給訂閱了指定主題的所有裝置，topic 可帶或不帶 "/topics/" 前綴
+    pub async fn send_to_topic(
+        &self,
+        topic: &str,
+        title: &str,
+        body: &str,
+        data: Option<Value>,
+    ) -> Result<String, FcmError> {
+        let message = MessageBuilder::topic(topic)
+            .notification(title, body)
+            .data(data)
+            .build()?;
+        self.send(message).await
+    }
+
+    // 依條件表達式發送，例如 "'news' in topics && 'sports' in topics"
+    pub async fn send_to_condition(
+        &self,
+        condition: &str,
+        title: &str,
+        body: &str,
+        data: Option<Value>,
+    ) -> Result<String, FcmError> {
+        let message = MessageBuilder::condition(condition)
+            .notification(title, body)
+            .data(data)
+            .build()?;
+        self.send(message).await
+    }
+
+    // 逐一發送給多個 token，並回傳每個 token 各自的結果
+    pub async fn send_multicast(
+        &self,
+        tokens: &[String],
+        title: &str,
+        body: &str,
+        data: Option<Value>,
+    ) -> Vec<SendResult> {
+        let mut results = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let outcome = self
+                .send_fcm_message(token, title, body, data.clone())
+                .await;
+            results.push(SendResult {
+                token: token.clone(),
+                outcome,
+            });
+        }
+        results
+    }
+
+    // 同時對多位使用者發送，最多 50 個使用者並行處理
+    pub async fn send_to_users(
+        &self,
+        repository: &impl FCMTokenRepository,
+        emails: Vec<String>,
+        title: &str,
+        body: &str,
+        data: Option<Value>,
+    ) -> Vec<UserSendResult> {
+        self.send_to_users_with_concurrency(
+            repository,
+            emails,
+            title,
+            body,
+            data,
+            DEFAULT_USER_CONCURRENCY,
+        )
+        .await
+    }
+
+    // 結果順序與 emails 相同；沒有 token 的使用者回傳 NoToken，不視為錯誤
+    pub async fn send_to_users_with_concurrency(
+        &self,
+        repository: &impl FCMTokenRepository,
+        emails: Vec<String>,
+        title: &str,
+        body: &str,
+        data: Option<Value>,
+        concurrency: usize,
+    ) -> Vec<UserSendResult> {
+        let mut results: Vec<(usize, UserSendResult)> =
+            stream::iter(emails.into_iter().enumerate())
+                .map(|(index, email)| {
+                    let data = data.clone();
+                    async move {
+                        let outcome = match repository.get_user_fcm_token(email.clone()).await {
+                            Ok(Some(token)) => {
+                                match self.send_fcm_message(&token, title, body, data).await {
+                                    Ok(name) => UserSendOutcome::Sent(name),
+                                    Err(e) => UserSendOutcome::Failed(e),
+                                }
+                            }
+                            Ok(None) => UserSendOutcome::NoToken,
+                            Err(e) => UserSendOutcome::LookupFailed(e),
+                        };
+                        (index, UserSendResult { email, outcome })
+                    }
+                })
+                .buffer_unordered(concurrency.max(1))
+                .collect()
+                .await;
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
     }
 
     pub async fn send_notification_to_user(
@@ -114,11 +582,12 @@ impl FCMSender {
         title: &str,
         body: &str,
         data: Option<Value>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<String, FcmError> {
         let token = repository
             .get_user_fcm_token(user_email)
-            .await?
-            .ok_or("User does not have an FCM token")?;
+            .await
+            .map_err(FcmError::Repository)?
+            .ok_or(FcmError::NoToken)?;
 
         self.send_fcm_message(&token, title, body, data).await
     }
@@ -130,24 +599,34 @@ impl FCMSender {
         title: &str,
         body: &str,
         data: Option<Value>,
-    ) -> Result<(), Box<dyn Error>> {
-        let tokens = repository.get_group_fcm_tokens(group_id).await?;
+    ) -> Result<Vec<SendResult>, FcmError> {
+        let tokens = repository
+            .get_group_fcm_tokens(group_id)
+            .await
+            .map_err(FcmError::Repository)?;
 
-        for token in tokens {
-            if let Err(e) = self
-                .send_fcm_message(&token, title, body, data.clone())
-                .await
-            {
-                eprintln!("Failed to send notification: {}", e);
+        let results = self.send_multicast(&tokens, title, body, data).await;
+        for result in &results {
+            match &result.outcome {
+                Ok(_) => {}
+                // 刪除失敗不影響發送結果，下次發送或定期清理時會再處理
+                Err(FcmError::Unregistered) => {
+                    if let Err(e) = repository.remove_token(&result.token).await {
+                        tracing::warn!("無法刪除失效的 token {}：{}", result.token, e);
+                    }
+                }
+                Err(e) => tracing::warn!("發送通知到 token {} 失敗：{}", result.token, e),
             }
         }
 
-        Ok(())
+        Ok(results)
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::spawn_mock_server;
+    use axum::{http::StatusCode, response::IntoResponse, Json, Router};
     // 基本的測試用 Repository
     struct TestTokenRepository {
         user_token: Option<String>,
@@ -163,7 +642,7 @@ mod tests {
         fn get_user_fcm_token(
             &self,
             _user_email: String,
-        ) -> impl Future<Output = Result<Option<String>, Box<dyn Error>>> + Send {
+        ) -> impl Future<Output = Result<Option<String>, RepositoryError>> + Send {
             let token = self.user_token.clone();
             async move { Ok(token) }
         }
@@ -189,7 +668,7 @@ mod tests {
         fn get_user_fcm_token(
             &self,
             _user_email: String,
-        ) -> impl Future<Output = Result<Option<String>, Box<dyn Error>>> + Send {
+        ) -> impl Future<Output = Result<Option<String>, RepositoryError>> + Send {
             let token = self.user_token.clone();
             async move { Ok(token) }
         }
@@ -197,10 +676,129 @@ mod tests {
         fn get_group_fcm_tokens(
             &self,
             _group_id: i32,
-        ) -> impl Future<Output = Result<Vec<String>, Box<dyn Error>>> + Send {
+        ) -> impl Future<Output = Result<Vec<String>, RepositoryError>> + Send {
+            let tokens = self.group_tokens.clone();
+            async move { Ok(tokens) }
+        }
+
+        fn get_all_fcm_tokens(
+            &self,
+        ) -> impl Future<Output = Result<Vec<String>, RepositoryError>> + Send {
+            let tokens = self.group_tokens.clone();
+            async move { Ok(tokens) }
+        }
+    }
+
+    // 記錄被刪除的 token
+    struct RemovalRecordingRepository {
+        group_tokens: Vec<String>,
+        removed: Mutex<Vec<String>>,
+    }
+
+    impl FCMTokenRepository for RemovalRecordingRepository {
+        fn get_user_fcm_token(
+            &self,
+            _user_email: String,
+        ) -> impl Future<Output = Result<Option<String>, RepositoryError>> + Send {
+            let token = self.group_tokens.first().cloned();
+            async move { Ok(token) }
+        }
+
+        fn get_group_fcm_tokens(
+            &self,
+            _group_id: i32,
+        ) -> impl Future<Output = Result<Vec<String>, RepositoryError>> + Send {
             let tokens = self.group_tokens.clone();
             async move { Ok(tokens) }
         }
+
+        fn remove_token(
+            &self,
+            token: &str,
+        ) -> impl Future<Output = Result<(), RepositoryError>> + Send {
+            self.removed.lock().unwrap().push(token.to_string());
+            async { Ok(()) }
+        }
+    }
+
+    // 依 email 查詢 token 的 Repository，可模擬查詢失敗
+    struct MapTokenRepository {
+        tokens: std::collections::HashMap<String, String>,
+    }
+
+    impl FCMTokenRepository for MapTokenRepository {
+        fn get_user_fcm_token(
+            &self,
+            user_email: String,
+        ) -> impl Future<Output = Result<Option<String>, RepositoryError>> + Send {
+            let result = if user_email.starts_with("broken") {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionRefused,
+                    "database unavailable",
+                ))
+            } else {
+                Ok(self.tokens.get(&user_email).cloned())
+            };
+            async move { result.map_err(Into::into) }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_to_users_aggregates_results_with_capped_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let (in_flight_clone, max_clone) = (in_flight.clone(), max_in_flight.clone());
+        let app = Router::new().fallback(move |Json(body): Json<Value>| {
+            let in_flight = in_flight_clone.clone();
+            let max_in_flight = max_clone.clone();
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                let token = body["message"]["token"].as_str().unwrap_or_default();
+                Json(serde_json::json!({ "name": format!("projects/p/messages/{}", token) }))
+            }
+        });
+        let base_url = spawn_mock_server(app).await;
+        let sender = FCMSender::new("p".to_string(), "test-token".to_string())
+            .with_base_url(base_url)
+            .with_retry(RetryConfig::none());
+
+        let mut tokens = std::collections::HashMap::new();
+        for n in 0..6 {
+            tokens.insert(format!("user{}@example.com", n), format!("token-{}", n));
+        }
+        let repo = MapTokenRepository { tokens };
+        let mut emails: Vec<String> = (0..6).map(|n| format!("user{}@example.com", n)).collect();
+        emails.push("nobody@example.com".to_string());
+        emails.push("broken@example.com".to_string());
+
+        let results = sender
+            .send_to_users_with_concurrency(&repo, emails.clone(), "T", "B", None, 2)
+            .await;
+
+        assert_eq!(
+            results.iter().map(|r| r.email.clone()).collect::<Vec<_>>(),
+            emails,
+            "結果應與輸入順序一致"
+        );
+        for (n, result) in results.iter().take(6).enumerate() {
+            assert!(
+                matches!(&result.outcome, UserSendOutcome::Sent(name) if name == &format!("projects/p/messages/token-{}", n))
+            );
+        }
+        assert!(matches!(results[6].outcome, UserSendOutcome::NoToken));
+        assert!(matches!(
+            &results[7].outcome,
+            UserSendOutcome::LookupFailed(e)
+                if e.downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::ConnectionRefused)
+        ));
+        assert!(max_in_flight.load(Ordering::SeqCst) <= 2);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2, "應該有並行發送");
     }
 
     #[tokio::test]
@@ -226,6 +824,50 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_send_notification_to_user_returns_message_name() {
+        let base_url = spawn_mock_server(Router::new().fallback(mock_fcm_handler)).await;
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(base_url);
+        let repo = TestFullRepository::new(
+            Some("live-token".to_string()),
+            vec!["live-token".to_string(), "dead-token".to_string()],
+        );
+
+        let name = sender
+            .send_notification_to_user(&repo, "test@example.com".to_string(), "T", "B", None)
+            .await
+            .unwrap();
+        assert_eq!(name, "projects/test-project/messages/live-token");
+
+        let results = sender
+            .send_notifications_to_group(&repo, 1, "T", "B", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            results[0].outcome.as_deref().unwrap(),
+            "projects/test-project/messages/live-token"
+        );
+        assert!(matches!(results[1].outcome, Err(FcmError::Unregistered)));
+    }
+
+    #[tokio::test]
+    async fn test_send_notification_to_user_repository_error() {
+        let repo = MapTokenRepository {
+            tokens: std::collections::HashMap::new(),
+        };
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string());
+
+        let result = sender
+            .send_notification_to_user(&repo, "broken@example.com".to_string(), "T", "B", None)
+            .await;
+
+        match result {
+            Err(FcmError::Repository(e)) => assert_eq!(e.to_string(), "database unavailable"),
+            other => panic!("預期 Repository 錯誤，實際為 {:?}", other),
+        }
+    }
+
     #[tokio::test]
     async fn test_send_notifications_to_group() {
         let repo = TestFullRepository::new(None, vec!["token1".to_string(), "token2".to_string()]);
@@ -255,4 +897,427 @@ mod tests {
         let err = result.unwrap_err().to_string();
         assert!(err.contains("not supported"), "Unexpected error: {}", err);
     }
+
+    // 模擬 FCM：token 為 "dead-token" 時回傳 UNREGISTERED，其餘成功
+    async fn mock_fcm_handler(Json(body): Json<Value>) -> axum::response::Response {
+        let token = body["message"]["token"].as_str().unwrap_or_default();
+        if token == "dead-token" {
+            let error = serde_json::json!({
+                "error": {
+                    "code": 404,
+                    "message": "Requested entity was not found.",
+                    "status": "NOT_FOUND",
+                    "details": [{
+                        "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                        "errorCode": "UNREGISTERED"
+                    }]
+                }
+            });
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        } else {
+            Json(serde_json::json!({
+                "name": format!("projects/test-project/messages/{}", token)
+            }))
+            .into_response()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_multicast_reports_per_token_results() {
+        let base_url = spawn_mock_server(Router::new().fallback(mock_fcm_handler)).await;
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(base_url);
+
+        let tokens = vec!["live-token".to_string(), "dead-token".to_string()];
+        let results = sender
+            .send_multicast(&tokens, "Test Title", "Test Body", None)
+            .await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].token, "live-token");
+        assert_eq!(
+            results[0].outcome.as_ref().unwrap(),
+            "projects/test-project/messages/live-token"
+        );
+        assert_eq!(results[1].token, "dead-token");
+        assert!(matches!(results[1].outcome, Err(FcmError::Unregistered)));
+    }
+
+    #[tokio::test]
+    async fn test_group_send_removes_unregistered_tokens() {
+        let base_url = spawn_mock_server(Router::new().fallback(mock_fcm_handler)).await;
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(base_url);
+        let repo = RemovalRecordingRepository {
+            group_tokens: vec!["live-token".to_string(), "dead-token".to_string()],
+            removed: Mutex::new(Vec::new()),
+        };
+
+        let results = sender
+            .send_notifications_to_group(&repo, 1, "Test Title", "Test Body", None)
+            .await
+            .unwrap();
+
+        assert!(results[0].outcome.is_ok());
+        assert!(matches!(results[1].outcome, Err(FcmError::Unregistered)));
+        assert_eq!(
+            *repo.removed.lock().unwrap(),
+            vec!["dead-token".to_string()]
+        );
+    }
+
+    // 依 token 名稱回傳對應的 FCM 錯誤，模擬各種錯誤狀態
+    async fn mock_fcm_error_handler(Json(body): Json<Value>) -> axum::response::Response {
+        let token = body["message"]["token"].as_str().unwrap_or_default();
+        let (status, status_text, error_code) = match token {
+            "unregistered" => (StatusCode::NOT_FOUND, "NOT_FOUND", "UNREGISTERED"),
+            "invalid" => (
+                StatusCode::BAD_REQUEST,
+                "INVALID_ARGUMENT",
+                "INVALID_ARGUMENT",
+            ),
+            "mismatch" => (
+                StatusCode::FORBIDDEN,
+                "PERMISSION_DENIED",
+                "SENDER_ID_MISMATCH",
+            ),
+            "quota" => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "RESOURCE_EXHAUSTED",
+                "QUOTA_EXCEEDED",
+            ),
+            "unauthenticated" => (
+                StatusCode::UNAUTHORIZED,
+                "UNAUTHENTICATED",
+                "THIRD_PARTY_AUTH_ERROR",
+            ),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL", "INTERNAL"),
+        };
+        let error = serde_json::json!({
+            "error": {
+                "code": status.as_u16(),
+                "message": format!("mock error for {}", token),
+                "status": status_text,
+                "details": [{
+                    "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                    "errorCode": error_code
+                }]
+            }
+        });
+        (status, Json(error)).into_response()
+    }
+
+    #[tokio::test]
+    async fn test_structured_error_responses() {
+        let base_url = spawn_mock_server(Router::new().fallback(mock_fcm_error_handler)).await;
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(base_url);
+
+        let tokens: Vec<String> = [
+            "unregistered",
+            "invalid",
+            "mismatch",
+            "quota",
+            "unauthenticated",
+            "other",
+        ]
+        .iter()
+        .map(|t| t.to_string())
+        .collect();
+        let results = sender.send_multicast(&tokens, "Title", "Body", None).await;
+
+        assert!(matches!(results[0].outcome, Err(FcmError::Unregistered)));
+        assert!(
+            matches!(results[1].outcome, Err(FcmError::InvalidArgument(ref msg)) if msg == "mock error for invalid")
+        );
+        assert!(matches!(
+            results[2].outcome,
+            Err(FcmError::SenderIdMismatch)
+        ));
+        assert!(matches!(results[3].outcome, Err(FcmError::QuotaExceeded)));
+        assert!(matches!(results[4].outcome, Err(FcmError::Unauthenticated)));
+        assert!(matches!(results[5].outcome, Err(FcmError::Other(_))));
+    }
+
+    #[test]
+    fn test_error_response_without_json_body() {
+        let error = FcmError::from_response(reqwest::StatusCode::NOT_FOUND, "not json");
+        assert!(matches!(error, FcmError::Unregistered));
+
+        let error = FcmError::from_response(reqwest::StatusCode::BAD_GATEWAY, "bad gateway");
+        assert!(matches!(error, FcmError::Other(_)));
+    }
+
+    #[tokio::test]
+    async fn test_send_to_invalid_topic_fails_early() {
+        // 指向無法連線的位址，若有實際發出請求就會得到 Request 錯誤
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url("http://127.0.0.1:9");
+        let result = sender.send_to_topic("news/sports", "T", "B", None).await;
+        assert!(matches!(result, Err(FcmError::InvalidArgument(_))));
+    }
+
+    #[tokio::test]
+    async fn test_send_to_malformed_token_fails_early() {
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url("http://127.0.0.1:9")
+            .with_retry(RetryConfig::none());
+        for token in ["", "   "] {
+            let result = sender.send_fcm_message(token, "T", "B", None).await;
+            assert!(matches!(result, Err(FcmError::InvalidArgument(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_noop_sender_records_without_http() {
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let requests_clone = requests.clone();
+        let app = Router::new().fallback(move || {
+            let requests = requests_clone.clone();
+            async move {
+                requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                StatusCode::OK
+            }
+        });
+        let base_url = spawn_mock_server(app).await;
+        let sender = FCMSender::noop().with_base_url(base_url);
+
+        let name = sender
+            .send_fcm_message("device-token", "Hello", "World", None)
+            .await
+            .expect("noop 發送應該成功");
+        sender
+            .send_to_topic("news", "Topic", "Body", None)
+            .await
+            .unwrap();
+
+        assert_eq!(name, "projects/noop/messages/noop-1");
+        let recorded = sender.noop_messages();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0]["token"], "device-token");
+        assert_eq!(recorded[0]["notification"]["title"], "Hello");
+        assert_eq!(recorded[1]["topic"], "news");
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_failures() {
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let requests_clone = requests.clone();
+        let app = Router::new().fallback(move || {
+            let requests = requests_clone.clone();
+            async move {
+                let n = requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                if n < 2 {
+                    StatusCode::SERVICE_UNAVAILABLE.into_response()
+                } else {
+                    Json(serde_json::json!({ "name": "projects/test-project/messages/1" }))
+                        .into_response()
+                }
+            }
+        });
+        let base_url = spawn_mock_server(app).await;
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(base_url)
+            .with_retry(RetryConfig {
+                max_retries: 3,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(10),
+            });
+
+        let result = sender.send_fcm_message("token", "T", "B", None).await;
+
+        assert_eq!(result.unwrap(), "projects/test-project/messages/1");
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_custom_headers_are_sent() {
+        let app = Router::new().fallback(|headers: axum::http::HeaderMap| async move {
+            assert_eq!(headers["x-cloud-trace-context"], "trace-id/1;o=1");
+            assert_eq!(headers["x-request-source"], "scheduler");
+            Json(serde_json::json!({ "name": "projects/test-project/messages/1" }))
+        });
+        let base_url = spawn_mock_server(app).await;
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Cloud-Trace-Context", "trace-id/1;o=1".parse().unwrap());
+        headers.insert("X-Request-Source", "scheduler".parse().unwrap());
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(base_url)
+            .with_retry(RetryConfig::none())
+            .with_headers(headers);
+
+        let result = sender.send_fcm_message("token", "T", "B", None).await;
+        assert_eq!(result.unwrap(), "projects/test-project/messages/1");
+    }
+
+    #[tokio::test]
+    async fn test_builder_with_custom_client_and_timeout() {
+        let app = Router::new()
+            .route(
+                "/v1/projects/test-project/messages:send",
+                axum::routing::post(|headers: axum::http::HeaderMap| async move {
+                    assert_eq!(headers["x-client"], "custom");
+                    assert_eq!(headers["x-cloud-trace-context"], "trace-id/1;o=1");
+                    Json(serde_json::json!({ "name": "projects/test-project/messages/1" }))
+                }),
+            )
+            .fallback(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                StatusCode::OK
+            });
+        let base_url = spawn_mock_server(app).await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-client", "custom".parse().unwrap());
+        let client = Client::builder().default_headers(headers).build().unwrap();
+        // 另外設定的標頭不會取代自訂 client 的設定
+        let mut extra_headers = HeaderMap::new();
+        extra_headers.insert("x-cloud-trace-context", "trace-id/1;o=1".parse().unwrap());
+        let sender = FCMSender::builder()
+            .project_id("test-project")
+            .access_token("test-token")
+            .client(client)
+            .headers(extra_headers)
+            .timeout(Duration::from_millis(200))
+            .retry(RetryConfig::none())
+            .base_url(base_url.clone())
+            .build()
+            .unwrap();
+
+        let result = sender.send_fcm_message("token", "T", "B", None).await;
+        assert_eq!(result.unwrap(), "projects/test-project/messages/1");
+
+        // 指向會卡住的路徑，應在設定的逾時內失敗
+        let slow = FCMSender::builder()
+            .project_id("slow-project")
+            .timeout(Duration::from_millis(200))
+            .retry(RetryConfig::none())
+            .base_url(base_url)
+            .build()
+            .unwrap();
+        let started = std::time::Instant::now();
+        let result = slow.send_fcm_message("token", "T", "B", None).await;
+        assert!(matches!(result, Err(FcmError::Request(e)) if e.is_timeout()));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        assert!(matches!(
+            FCMSender::builder().access_token("test-token").build(),
+            Err(FcmError::Other(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_error_fails_fast() {
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let requests_clone = requests.clone();
+        let app = Router::new().fallback(move |body: Json<Value>| {
+            let requests = requests_clone.clone();
+            async move {
+                requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                mock_fcm_error_handler(body).await
+            }
+        });
+        let base_url = spawn_mock_server(app).await;
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(base_url);
+
+        let result = sender
+            .send_fcm_message("unregistered", "T", "B", None)
+            .await;
+
+        assert!(matches!(result, Err(FcmError::Unregistered)));
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retry_after_header_and_backoff() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::RETRY_AFTER, "7".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        let config = RetryConfig {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+        };
+        let delay = config.backoff(4);
+        assert!(delay >= Duration::from_millis(150) && delay <= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_sets_validate_only() {
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let recorded = bodies.clone();
+        let app = Router::new().fallback(move |Json(body): Json<Value>| {
+            let recorded = recorded.clone();
+            async move {
+                recorded.lock().unwrap().push(body);
+                Json(serde_json::json!({ "name": "projects/test-project/messages/fake" }))
+            }
+        });
+        let base_url = spawn_mock_server(app).await;
+        let sender = FCMSender::builder()
+            .project_id("test-project")
+            .access_token("test-token")
+            .base_url(base_url)
+            .dry_run(true)
+            .build()
+            .unwrap();
+
+        sender
+            .send_fcm_message("device-token", "T", "B", None)
+            .await
+            .unwrap();
+        sender
+            .clone()
+            .with_dry_run(false)
+            .send_fcm_message("device-token", "T", "B", None)
+            .await
+            .unwrap();
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies[0]["validate_only"], true);
+        assert_eq!(bodies[0]["message"]["token"], "device-token");
+        assert!(bodies[1].get("validate_only").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_find_invalid_repository_tokens() {
+        let app = Router::new().fallback(|Json(body): Json<Value>| async move {
+            assert_eq!(body["validate_only"], true, "驗證時不應真的發送");
+            let token = body["message"]["token"].as_str().unwrap_or_default();
+            if token.starts_with("valid") {
+                Json(serde_json::json!({ "name": "projects/test-project/messages/fake" }))
+                    .into_response()
+            } else {
+                mock_fcm_error_handler(Json(body)).await
+            }
+        });
+        let base_url = spawn_mock_server(app).await;
+        let sender = FCMSender::new("test-project".to_string(), "test-token".to_string())
+            .with_base_url(base_url)
+            .with_retry(RetryConfig::none());
+        let tokens = [
+            "valid-1",
+            "unregistered",
+            "valid-2",
+            "invalid",
+            "mismatch",
+            "quota",
+        ];
+        let repo = TestFullRepository::new(None, tokens.iter().map(|t| t.to_string()).collect());
+
+        let invalid = sender
+            .find_invalid_repository_tokens(&repo, 2)
+            .await
+            .unwrap();
+
+        // quota 只是暫時性錯誤，不應被刪除
+        let expected: HashSet<String> = ["unregistered", "invalid", "mismatch"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(invalid, expected);
+    }
 }
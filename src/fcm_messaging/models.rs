@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FCMMessage<'a> {
+    // 為 true 時 FCM 只驗證訊息（含 token）而不實際發送
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub validate_only: bool,
+    pub message: &'a Message,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Message {
+    #[serde(flatten)]
+    pub target: Target,
+    // 為 None 時是純資料（靜默）訊息，裝置不會顯示通知
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<Notification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub android: Option<AndroidConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apns: Option<ApnsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webpush: Option<WebpushConfig>,
+}
+
+// 發送對象：單一裝置 token、主題或條件表達式，序列化後分別對應 token / topic / condition 欄位
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Target {
+    Token(String),
+    Topic(String),
+    Condition(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Notification {
+    pub title: String,
+    pub body: String,
+    // 大圖通知的圖片網址，所有平台共用
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+// 訊息優先權，未設定時沿用 FCM 預設值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Priority {
+    High,
+    Normal,
+}
+
+// FCM v1 的 android 區塊，ttl 格式為秒數加 "s"，例如 "3600s"
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AndroidConfig {
+    // 相同 collapse_key 的訊息尚未送達時，只保留最新的一則
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapse_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<AndroidNotification>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AndroidNotification {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub click_action: Option<String>,
+    // App 內 drawable 資源名稱，例如 "ic_notification"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    // 圖示顏色，格式為 #rrggbb
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    // 以 App 的字串資源在裝置上翻譯標題與內文，args 依序代入資源中的格式參數；
+    // 與 notification 的 title / body 並用時，裝置找得到資源就以資源為準
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_loc_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub title_loc_args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_loc_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub body_loc_args: Vec<String>,
+}
+
+// FCM v1 的 apns 區塊，headers 為 APNs 的 HTTP 標頭（如 apns-priority）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApnsConfig {
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<ApnsPayload>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApnsPayload {
+    pub aps: Aps,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Aps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<ApsAlert>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badge: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+// aps.alert 字典，鍵名為 APNs 的 kebab-case；內文的在地化鍵在 APNs 中為 loc-key / loc-args
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApsAlert {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_loc_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub title_loc_args: Vec<String>,
+    #[serde(rename = "loc-key", skip_serializing_if = "Option::is_none")]
+    pub body_loc_key: Option<String>,
+    #[serde(rename = "loc-args", default, skip_serializing_if = "Vec::is_empty")]
+    pub body_loc_args: Vec<String>,
+}
+
+// FCM v1 的 webpush 區塊，點擊後開啟的連結放在 fcm_options.link
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebpushConfig {
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<WebpushNotification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fcm_options: Option<WebpushFcmOptions>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebpushNotification {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebpushFcmOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SendResponse {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ErrorBody {
+    #[serde(default)]
+    pub message: String,
+    pub status: Option<String>,
+    #[serde(default)]
+    pub details: Vec<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ErrorDetail {
+    #[serde(rename = "errorCode")]
+    pub error_code: Option<String>,
+}
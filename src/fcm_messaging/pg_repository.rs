@@ -0,0 +1,224 @@
+use super::{FCMTokenRepository, RepositoryError};
+use crate::sqlx::{ident, PgPoolExt, PostgresParam};
+use serde::Deserialize;
+use sqlx::{FromRow, PgPool};
+use std::future::Future;
+
+// 資料表與欄位名稱，查詢時會以 ident 驗證並加上引號
+#[derive(Clone, Debug)]
+pub struct PgFcmTokenConfig {
+    pub tokens_table: String,
+    pub email_column: String,
+    pub token_column: String,
+    pub members_table: String,
+    pub member_group_column: String,
+    pub member_email_column: String,
+}
+
+impl Default for PgFcmTokenConfig {
+    fn default() -> Self {
+        Self {
+            tokens_table: "fcm_tokens".to_string(),
+            email_column: "user_email".to_string(),
+            token_column: "token".to_string(),
+            members_table: "group_members".to_string(),
+            member_group_column: "group_id".to_string(),
+            member_email_column: "user_email".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize)]
+struct TokenRow {
+    token: String,
+}
+
+// 以 PgPool 實作的 FCMTokenRepository
+#[derive(Clone, Debug)]
+pub struct PgFcmTokenRepository {
+    pool: PgPool,
+    config: PgFcmTokenConfig,
+}
+
+impl PgFcmTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_config(pool, PgFcmTokenConfig::default())
+    }
+
+    pub fn with_config(pool: PgPool, config: PgFcmTokenConfig) -> Self {
+        Self { pool, config }
+    }
+
+    fn user_token_query(&self) -> Result<String, RepositoryError> {
+        let c = &self.config;
+        Ok(format!(
+            "SELECT {} AS token FROM {} WHERE {} = $1 LIMIT 1",
+            ident(&c.token_column)?,
+            ident(&c.tokens_table)?,
+            ident(&c.email_column)?
+        ))
+    }
+
+    fn group_tokens_query(&self) -> Result<String, RepositoryError> {
+        let c = &self.config;
+        Ok(format!(
+            "SELECT t.{} AS token FROM {} t JOIN {} m ON m.{} = t.{} WHERE m.{} = $1",
+            ident(&c.token_column)?,
+            ident(&c.tokens_table)?,
+            ident(&c.members_table)?,
+            ident(&c.member_email_column)?,
+            ident(&c.email_column)?,
+            ident(&c.member_group_column)?
+        ))
+    }
+
+    fn remove_token_query(&self) -> Result<String, RepositoryError> {
+        let c = &self.config;
+        Ok(format!(
+            "DELETE FROM {} WHERE {} = $1",
+            ident(&c.tokens_table)?,
+            ident(&c.token_column)?
+        ))
+    }
+
+    fn all_tokens_query(&self) -> Result<String, RepositoryError> {
+        let c = &self.config;
+        Ok(format!(
+            "SELECT {} AS token FROM {}",
+            ident(&c.token_column)?,
+            ident(&c.tokens_table)?
+        ))
+    }
+}
+
+impl FCMTokenRepository for PgFcmTokenRepository {
+    fn get_user_fcm_token(
+        &self,
+        user_email: String,
+    ) -> impl Future<Output = Result<Option<String>, RepositoryError>> + Send {
+        let query = self.user_token_query();
+        let pool = self.pool.clone();
+        async move {
+            let query = query?;
+            let params: Vec<Box<dyn PostgresParam>> = vec![Box::new(user_email)];
+            Ok(pool.fetch_optional_scalar(&query, params).await?)
+        }
+    }
+
+    fn get_group_fcm_tokens(
+        &self,
+        group_id: i32,
+    ) -> impl Future<Output = Result<Vec<String>, RepositoryError>> + Send {
+        let query = self.group_tokens_query();
+        let pool = self.pool.clone();
+        async move {
+            let query = query?;
+            let params: Vec<Box<dyn PostgresParam>> = vec![Box::new(group_id)];
+            let rows: Vec<TokenRow> = pool.fetch(&query, params).await?;
+            Ok(rows.into_iter().map(|row| row.token).collect())
+        }
+    }
+
+    fn get_all_fcm_tokens(
+        &self,
+    ) -> impl Future<Output = Result<Vec<String>, RepositoryError>> + Send {
+        let query = self.all_tokens_query();
+        let pool = self.pool.clone();
+        async move {
+            let query = query?;
+            let rows: Vec<TokenRow> = pool.fetch(&query, Vec::new()).await?;
+            Ok(rows.into_iter().map(|row| row.token).collect())
+        }
+    }
+
+    fn remove_token(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = Result<(), RepositoryError>> + Send {
+        let query = self.remove_token_query();
+        let pool = self.pool.clone();
+        let token = token.to_string();
+        async move {
+            let query = query?;
+            pool.execute(&query, vec![token]).await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::setup_test_db;
+
+    // 只用一條連線，讓 TEMPORARY TABLE 在整個測試中都看得到
+    async fn setup_repository() -> PgFcmTokenRepository {
+        let pool = setup_test_db(1).await;
+
+        for statement in [
+            "CREATE TEMPORARY TABLE device_tokens (email TEXT PRIMARY KEY, fcm_token TEXT NOT NULL)",
+            "CREATE TEMPORARY TABLE team_members (team_id INT NOT NULL, email TEXT NOT NULL)",
+            "INSERT INTO device_tokens VALUES ('alice@example.com', 'token-alice'), ('bob@example.com', 'token-bob'), ('carol@example.com', 'token-carol')",
+            "INSERT INTO team_members VALUES (1, 'alice@example.com'), (1, 'bob@example.com'), (2, 'carol@example.com')",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+
+        PgFcmTokenRepository::with_config(
+            pool,
+            PgFcmTokenConfig {
+                tokens_table: "device_tokens".to_string(),
+                email_column: "email".to_string(),
+                token_column: "fcm_token".to_string(),
+                members_table: "team_members".to_string(),
+                member_group_column: "team_id".to_string(),
+                member_email_column: "email".to_string(),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_user_and_group_tokens() {
+        let repository = setup_repository().await;
+
+        let token = repository
+            .get_user_fcm_token("alice@example.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(token.as_deref(), Some("token-alice"));
+        let missing = repository
+            .get_user_fcm_token("nobody@example.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+
+        let mut group = repository.get_group_fcm_tokens(1).await.unwrap();
+        group.sort();
+        assert_eq!(group, vec!["token-alice", "token-bob"]);
+        assert_eq!(repository.get_all_fcm_tokens().await.unwrap().len(), 3);
+
+        repository.remove_token("token-bob").await.unwrap();
+        assert_eq!(
+            repository.get_group_fcm_tokens(1).await.unwrap(),
+            vec!["token-alice"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_table_name_is_rejected() {
+        let repository = PgFcmTokenRepository::with_config(
+            PgPool::connect_lazy("postgres://localhost/unused").unwrap(),
+            PgFcmTokenConfig {
+                tokens_table: "tokens; DROP TABLE users".to_string(),
+                ..Default::default()
+            },
+        );
+        let result = repository
+            .get_user_fcm_token("alice@example.com".to_string())
+            .await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid SQL identifier"));
+    }
+}
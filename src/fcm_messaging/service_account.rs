@@ -0,0 +1,221 @@
+use super::FcmError;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::{header::HeaderMap, Client};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::sync::Mutex;
+
+const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const JWT_BEARER_GRANT: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+// 提前一分鐘視為過期，避免送出途中 token 失效
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServiceAccount {
+    pub project_id: String,
+    pub client_email: String,
+    pub private_key: String,
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    DEFAULT_TOKEN_URI.to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct AssertionClaims {
+    pub iss: String,
+    pub scope: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+impl ServiceAccount {
+    // 接受 service account 的 JSON 內容或檔案路徑
+    pub fn load(path_or_json: &str) -> Result<Self, FcmError> {
+        let content = if path_or_json.trim_start().starts_with('{') {
+            path_or_json.to_string()
+        } else {
+            std::fs::read_to_string(Path::new(path_or_json)).map_err(|e| {
+                FcmError::Auth(format!(
+                    "cannot read service account {}: {}",
+                    path_or_json, e
+                ))
+            })?
+        };
+        serde_json::from_str(&content)
+            .map_err(|e| FcmError::Auth(format!("invalid service account JSON: {}", e)))
+    }
+
+    // 建立 JWT bearer grant 所需的 assertion，以 service account 的私鑰簽章
+    pub(crate) fn build_assertion(&self, now: DateTime<Utc>) -> Result<String, FcmError> {
+        let claims = AssertionClaims {
+            iss: self.client_email.clone(),
+            scope: FCM_SCOPE.to_string(),
+            aud: self.token_uri.clone(),
+            iat: now.timestamp(),
+            exp: (now + Duration::hours(1)).timestamp(),
+        };
+        let key = EncodingKey::from_rsa_pem(self.private_key.as_bytes())
+            .map_err(|e| FcmError::Auth(format!("invalid private key: {}", e)))?;
+        encode(&Header::new(Algorithm::RS256), &claims, &key)
+            .map_err(|e| FcmError::Auth(format!("cannot sign assertion: {}", e)))
+    }
+}
+
+#[derive(Debug)]
+struct CachedToken {
+    access_token: String,
+    expires_at: DateTime<Utc>,
+}
+
+// 快取 access token 並在過期前自動換發
+#[derive(Debug)]
+pub(crate) struct ServiceAccountTokenProvider {
+    account: ServiceAccount,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl ServiceAccountTokenProvider {
+    pub(crate) fn new(account: ServiceAccount) -> Self {
+        Self {
+            account,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn project_id(&self) -> &str {
+        &self.account.project_id
+    }
+
+    pub(crate) async fn access_token(
+        &self,
+        client: &Client,
+        headers: &HeaderMap,
+    ) -> Result<String, FcmError> {
+        let mut cached = self.cached.lock().await;
+        let now = Utc::now();
+        if let Some(token) = cached.as_ref().filter(|t| t.expires_at > now) {
+            return Ok(token.access_token.clone());
+        }
+
+        let assertion = self.account.build_assertion(now)?;
+        let response = client
+            .post(&self.account.token_uri)
+            .headers(headers.clone())
+            .form(&[("grant_type", JWT_BEARER_GRANT), ("assertion", &assertion)])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(FcmError::Auth(format!(
+                "token exchange failed: {}: {}",
+                status, body
+            )));
+        }
+
+        let token: TokenResponse = response.json().await?;
+        tracing::info!(
+            "已換發新的 FCM access token，有效期 {} 秒",
+            token.expires_in
+        );
+        *cached = Some(CachedToken {
+            access_token: token.access_token.clone(),
+            expires_at: now + Duration::seconds(token.expires_in - EXPIRY_MARGIN_SECS),
+        });
+        Ok(token.access_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{spawn_mock_server, TEST_RSA_PRIVATE_KEY, TEST_RSA_PUBLIC_KEY};
+    use axum::{routing::post, Form, Json, Router};
+    use jsonwebtoken::{decode, DecodingKey, Validation};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn test_account(token_uri: &str) -> ServiceAccount {
+        let json = serde_json::json!({
+            "type": "service_account",
+            "project_id": "test-project",
+            "client_email": "fcm@test-project.iam.gserviceaccount.com",
+            "private_key": TEST_RSA_PRIVATE_KEY,
+            "token_uri": token_uri,
+        });
+        ServiceAccount::load(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_build_assertion_is_verifiable() {
+        let account = test_account(DEFAULT_TOKEN_URI);
+        let assertion = account.build_assertion(Utc::now()).unwrap();
+
+        let key = DecodingKey::from_rsa_pem(TEST_RSA_PUBLIC_KEY.as_bytes()).unwrap();
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[DEFAULT_TOKEN_URI]);
+        let data = decode::<AssertionClaims>(&assertion, &key, &validation)
+            .expect("assertion 應該能以對應公鑰驗證");
+
+        assert_eq!(data.claims.iss, "fcm@test-project.iam.gserviceaccount.com");
+        assert_eq!(data.claims.scope, FCM_SCOPE);
+        assert_eq!(data.claims.exp - data.claims.iat, 3600);
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_json() {
+        let result = ServiceAccount::load("{ not json");
+        assert!(matches!(result, Err(FcmError::Auth(_))));
+    }
+
+    #[tokio::test]
+    async fn test_access_token_is_cached() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let app = Router::new().route(
+            "/token",
+            post(move |Form(form): Form<HashMap<String, String>>| {
+                let calls = calls_clone.clone();
+                async move {
+                    assert_eq!(form["grant_type"], JWT_BEARER_GRANT);
+                    let n = calls.fetch_add(1, Ordering::SeqCst);
+                    Json(serde_json::json!({
+                        "access_token": format!("access-{}", n),
+                        "expires_in": 3600,
+                        "token_type": "Bearer"
+                    }))
+                }
+            }),
+        );
+        let base_url = spawn_mock_server(app).await;
+        let provider =
+            ServiceAccountTokenProvider::new(test_account(&format!("{}/token", base_url)));
+        let client = Client::new();
+
+        let first = provider
+            .access_token(&client, &HeaderMap::new())
+            .await
+            .unwrap();
+        let second = provider
+            .access_token(&client, &HeaderMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(first, "access-0");
+        assert_eq!(second, "access-0");
+        assert_eq!(calls.load(Ordering::SeqCst), 1, "未過期時不應重新換發");
+    }
+}
@@ -0,0 +1,103 @@
+use super::{
+    ApiKeyLocation, FirebaseAuthError, FirebaseAuthService, EMULATOR_API_KEY, SECURE_TOKEN_URL,
+};
+use reqwest::{header::HeaderMap, Client};
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 設定底層 reqwest::Client 的逾時，以及冪等操作在連線失敗 / 逾時時的重試次數
+#[derive(Debug, Clone)]
+pub struct FirebaseAuthServiceBuilder {
+    base_url: String,
+    api_token: String,
+    secure_token_url: String,
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+    max_retries: u32,
+    headers: HeaderMap,
+    api_key_location: ApiKeyLocation,
+}
+
+impl FirebaseAuthServiceBuilder {
+    pub fn new(base_url: impl Into<String>, api_token: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            api_token: api_token.into(),
+            secure_token_url: SECURE_TOKEN_URL.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: None,
+            max_retries: 0,
+            headers: HeaderMap::new(),
+            api_key_location: ApiKeyLocation::default(),
+        }
+    }
+
+    // 指向本機的 Firebase Auth Emulator，例如 "localhost:9099"（未帶 scheme 時使用 http）。
+    // Identity Toolkit 與 Secure Token API 都改走 emulator 上以原網域為前綴的路徑；
+    // emulator 接受任意 API key，因此不需要真正的 key，改送固定的佔位值
+    pub fn emulator(host: impl AsRef<str>) -> Self {
+        let host = host.as_ref().trim_end_matches('/');
+        let origin = if host.starts_with("http://") || host.starts_with("https://") {
+            host.to_string()
+        } else {
+            format!("http://{}", host)
+        };
+        Self::new(
+            format!("{}/identitytoolkit.googleapis.com", origin),
+            EMULATOR_API_KEY,
+        )
+        .secure_token_url(format!("{}/securetoken.googleapis.com", origin))
+    }
+
+    pub fn secure_token_url(mut self, url: impl Into<String>) -> Self {
+        self.secure_token_url = url.into();
+        self
+    }
+
+    // 整個請求（含讀取回應）的逾時
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    // 只套用在 sign-in、refresh、delete 等重送也不會產生副作用的操作
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    // 附加在每個請求上的標頭，例如追蹤用的 X-Cloud-Trace-Context
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    // 預設以 ?key= 傳送，Header 時改用 X-Goog-Api-Key 標頭
+    pub fn api_key_location(mut self, location: ApiKeyLocation) -> Self {
+        self.api_key_location = location;
+        self
+    }
+
+    pub fn build(self) -> Result<FirebaseAuthService, FirebaseAuthError> {
+        let mut client = Client::builder()
+            .timeout(self.timeout)
+            .default_headers(self.headers);
+        if let Some(connect_timeout) = self.connect_timeout {
+            client = client.connect_timeout(connect_timeout);
+        }
+        Ok(FirebaseAuthService {
+            client: client.build()?,
+            base_url: self.base_url,
+            api_token: self.api_token,
+            secure_token_url: self.secure_token_url,
+            max_retries: self.max_retries,
+            api_key_location: self.api_key_location,
+        })
+    }
+}
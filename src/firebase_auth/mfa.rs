@@ -0,0 +1,257 @@
+use super::FirebaseAuthRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// 要註冊的第二驗證因素
+#[derive(Debug, Clone)]
+pub enum MfaFactor {
+    Phone {
+        phone_number: String,
+        recaptcha_token: Option<String>,
+    },
+    Totp,
+}
+
+// POST /v2/accounts/mfaEnrollment:start
+#[derive(Debug, Clone)]
+pub struct StartMfaEnrollmentRequest {
+    pub id_token: String,
+    pub factor: MfaFactor,
+}
+
+impl FirebaseAuthRequest for StartMfaEnrollmentRequest {
+    fn get_endpoint(&self) -> &str {
+        "/v2/accounts/mfaEnrollment:start"
+    }
+
+    fn req_body(&self) -> Value {
+        match &self.factor {
+            MfaFactor::Phone {
+                phone_number,
+                recaptcha_token,
+            } => {
+                let mut info = json!({ "phoneNumber": phone_number });
+                if let Some(token) = recaptcha_token {
+                    info["recaptchaToken"] = json!(token);
+                }
+                json!({ "idToken": self.id_token, "phoneEnrollmentInfo": info })
+            }
+            MfaFactor::Totp => json!({ "idToken": self.id_token, "totpEnrollmentInfo": {} }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhoneSessionInfo {
+    pub session_info: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpSessionInfo {
+    pub session_info: String,
+    pub shared_secret_key: String,
+    #[serde(default)]
+    pub verification_code_length: Option<u32>,
+    #[serde(default)]
+    pub hashing_algorithm: Option<String>,
+    #[serde(default)]
+    pub period_sec: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartMfaEnrollmentResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone_session_info: Option<PhoneSessionInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_session_info: Option<TotpSessionInfo>,
+}
+
+impl StartMfaEnrollmentResponse {
+    // finalize 時必須原樣送回的 session info
+    pub fn session_info(&self) -> Option<&str> {
+        self.phone_session_info
+            .as_ref()
+            .map(|info| info.session_info.as_str())
+            .or_else(|| {
+                self.totp_session_info
+                    .as_ref()
+                    .map(|info| info.session_info.as_str())
+            })
+    }
+}
+
+// POST /v2/accounts/mfaEnrollment:finalize
+#[derive(Debug, Clone)]
+pub struct FinalizeMfaEnrollmentRequest {
+    pub id_token: String,
+    pub display_name: Option<String>,
+    pub session_info: String,
+    pub code: String,
+    pub factor: FinalizeFactor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinalizeFactor {
+    Phone,
+    Totp,
+}
+
+impl FinalizeMfaEnrollmentRequest {
+    // 從 start 的回應帶入 session info，code 為簡訊驗證碼或 TOTP App 產生的驗證碼
+    pub fn from_start(
+        id_token: impl Into<String>,
+        start: &StartMfaEnrollmentResponse,
+        code: impl Into<String>,
+    ) -> Option<Self> {
+        let (session_info, factor) = match (&start.phone_session_info, &start.totp_session_info) {
+            (Some(phone), _) => (phone.session_info.clone(), FinalizeFactor::Phone),
+            (None, Some(totp)) => (totp.session_info.clone(), FinalizeFactor::Totp),
+            (None, None) => return None,
+        };
+        Some(Self {
+            id_token: id_token.into(),
+            display_name: None,
+            session_info,
+            code: code.into(),
+            factor,
+        })
+    }
+
+    pub fn display_name(mut self, name: impl Into<String>) -> Self {
+        self.display_name = Some(name.into());
+        self
+    }
+}
+
+impl FirebaseAuthRequest for FinalizeMfaEnrollmentRequest {
+    fn get_endpoint(&self) -> &str {
+        "/v2/accounts/mfaEnrollment:finalize"
+    }
+
+    fn req_body(&self) -> Value {
+        let mut body = json!({ "idToken": self.id_token });
+        if let Some(name) = &self.display_name {
+            body["displayName"] = json!(name);
+        }
+        match self.factor {
+            FinalizeFactor::Phone => {
+                body["phoneVerificationInfo"] =
+                    json!({ "sessionInfo": self.session_info, "code": self.code });
+            }
+            FinalizeFactor::Totp => {
+                body["totpVerificationInfo"] =
+                    json!({ "sessionInfo": self.session_info, "verificationCode": self.code });
+            }
+        }
+        body
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalizeMfaEnrollmentResponse {
+    pub id_token: String,
+    pub refresh_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfa_enrollment_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::firebase_auth::FirebaseAuthService;
+    use crate::test_support::spawn_mock_server;
+    use axum::{extract::Path, routing::post, Json, Router};
+
+    // 模擬 MFA 端點：start 發出 session info，finalize 必須帶回同一個 session info
+    async fn mock_mfa(Path(endpoint): Path<String>, Json(body): Json<Value>) -> Json<Value> {
+        assert_eq!(body["idToken"], "id-token");
+        match endpoint.as_str() {
+            "mfaEnrollment:start" => {
+                if body.get("totpEnrollmentInfo").is_some() {
+                    Json(json!({
+                        "totpSessionInfo": {
+                            "sharedSecretKey": "SECRET",
+                            "verificationCodeLength": 6,
+                            "hashingAlgorithm": "SHA1",
+                            "periodSec": 30,
+                            "sessionInfo": "totp-session"
+                        }
+                    }))
+                } else {
+                    assert_eq!(body["phoneEnrollmentInfo"]["phoneNumber"], "+886900000000");
+                    Json(json!({ "phoneSessionInfo": { "sessionInfo": "phone-session" } }))
+                }
+            }
+            "mfaEnrollment:finalize" => {
+                assert_eq!(body["totpVerificationInfo"]["sessionInfo"], "totp-session");
+                assert_eq!(body["totpVerificationInfo"]["verificationCode"], "123456");
+                assert_eq!(body["displayName"], "Authenticator");
+                Json(json!({
+                    "idToken": "new-id-token",
+                    "refreshToken": "new-refresh-token",
+                    "mfaEnrollmentId": "enrollment-1"
+                }))
+            }
+            other => panic!("unexpected endpoint {}", other),
+        }
+    }
+
+    async fn test_service() -> FirebaseAuthService {
+        let app = Router::new().route("/v2/accounts/:endpoint", post(mock_mfa));
+        FirebaseAuthService::new(spawn_mock_server(app).await, "test-key")
+    }
+
+    #[tokio::test]
+    async fn test_totp_enrollment_round_trip() {
+        let service = test_service().await;
+        let start: StartMfaEnrollmentResponse = service
+            .request(StartMfaEnrollmentRequest {
+                id_token: "id-token".to_string(),
+                factor: MfaFactor::Totp,
+            })
+            .await
+            .unwrap();
+        assert_eq!(start.session_info(), Some("totp-session"));
+        assert_eq!(
+            start.totp_session_info.as_ref().unwrap().shared_secret_key,
+            "SECRET"
+        );
+
+        let finalize = FinalizeMfaEnrollmentRequest::from_start("id-token", &start, "123456")
+            .unwrap()
+            .display_name("Authenticator");
+        let response: FinalizeMfaEnrollmentResponse = service.request(finalize).await.unwrap();
+        assert_eq!(response.id_token, "new-id-token");
+        assert_eq!(response.mfa_enrollment_id.as_deref(), Some("enrollment-1"));
+    }
+
+    #[tokio::test]
+    async fn test_phone_enrollment_start() {
+        let service = test_service().await;
+        let start: StartMfaEnrollmentResponse = service
+            .request(StartMfaEnrollmentRequest {
+                id_token: "id-token".to_string(),
+                factor: MfaFactor::Phone {
+                    phone_number: "+886900000000".to_string(),
+                    recaptcha_token: None,
+                },
+            })
+            .await
+            .unwrap();
+        assert_eq!(start.session_info(), Some("phone-session"));
+
+        let finalize =
+            FinalizeMfaEnrollmentRequest::from_start("id-token", &start, "654321").unwrap();
+        assert_eq!(
+            finalize.req_body(),
+            json!({
+                "idToken": "id-token",
+                "phoneVerificationInfo": { "sessionInfo": "phone-session", "code": "654321" }
+            })
+        );
+    }
+}
@@ -1,9 +1,164 @@
-use reqwest::{Client, Error};
-use serde::de::DeserializeOwned;
-use std::fmt::Debug;
+use reqwest::{Client, Method, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{self, Debug};
+
+mod builder;
+mod mfa;
+mod models;
+
+pub use builder::FirebaseAuthServiceBuilder;
+pub use mfa::{
+    FinalizeFactor, FinalizeMfaEnrollmentRequest, FinalizeMfaEnrollmentResponse, MfaFactor,
+    PhoneSessionInfo, StartMfaEnrollmentRequest, StartMfaEnrollmentResponse, TotpSessionInfo,
+};
+pub use models::{
+    AccountInfo, CustomTokenResponse, PasswordResetResponse, ProviderUserInfo, RefreshResponse,
+    SignInResponse, SignUpResponse,
+};
+use models::{
+    CustomTokenRequest, IdTokenRequest, LookupResponse, OobCodeRequest, PasswordRequest,
+    RefreshRequest, ResetPasswordRequest, ResetPasswordResponse,
+};
+
+const SECURE_TOKEN_URL: &str = "https://securetoken.googleapis.com";
+const API_KEY_HEADER: &str = "X-Goog-Api-Key";
+// Auth Emulator 不驗證 API key，但請求仍須帶有 key
+const EMULATOR_API_KEY: &str = "fake-api-key";
+
+// API key 的傳送方式；放在標頭可避免 key 出現在代理或存取日誌記錄的 URL 中
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApiKeyLocation {
+    // ?key=<api_token>
+    #[default]
+    Query,
+    // X-Goog-Api-Key: <api_token>
+    Header,
+}
+
+#[derive(Debug)]
+pub enum FirebaseAuthError {
+    EmailNotFound,
+    // id token 有效但查無對應的使用者（例如帳號已刪除）
+    UserNotFound,
+    InvalidPassword,
+    UserDisabled,
+    EmailExists,
+    TooManyAttempts,
+    // 密碼重設連結中的 oob code 已過期或無效（例如已使用過）
+    ExpiredOobCode,
+    InvalidOobCode,
+    // custom token 格式錯誤、已過期，或屬於其他專案
+    InvalidCustomToken,
+    CredentialMismatch,
+    Unknown(String),
+    Request(reqwest::Error),
+}
+
+impl fmt::Display for FirebaseAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirebaseAuthError::EmailNotFound => write!(f, "No user exists with this email"),
+            FirebaseAuthError::UserNotFound => write!(f, "No user exists for this token"),
+            FirebaseAuthError::InvalidPassword => write!(f, "The password is invalid"),
+            FirebaseAuthError::UserDisabled => write!(f, "The user account has been disabled"),
+            FirebaseAuthError::EmailExists => write!(f, "The email is already in use"),
+            FirebaseAuthError::TooManyAttempts => {
+                write!(f, "Too many attempts, please try again later")
+            }
+            FirebaseAuthError::ExpiredOobCode => write!(f, "The action code has expired"),
+            FirebaseAuthError::InvalidOobCode => write!(f, "The action code is invalid"),
+            FirebaseAuthError::InvalidCustomToken => write!(f, "The custom token is invalid"),
+            FirebaseAuthError::CredentialMismatch => {
+                write!(f, "The custom token belongs to a different project")
+            }
+            FirebaseAuthError::Unknown(msg) => write!(f, "Firebase auth error: {}", msg),
+            FirebaseAuthError::Request(e) => write!(f, "Firebase auth request failed: {}", e),
+        }
+    }
+}
+
+impl Error for FirebaseAuthError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FirebaseAuthError::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for FirebaseAuthError {
+    fn from(e: reqwest::Error) -> Self {
+        FirebaseAuthError::Request(e)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    message: String,
+}
+
+impl FirebaseAuthError {
+    // Firebase 的錯誤訊息可能帶有說明，例如 "TOO_MANY_ATTEMPTS_TRY_LATER : Access to this account..."
+    pub(crate) fn from_message(message: &str) -> Self {
+        let code = message.split(':').next().unwrap_or_default().trim();
+        match code {
+            "EMAIL_NOT_FOUND" => FirebaseAuthError::EmailNotFound,
+            "USER_NOT_FOUND" => FirebaseAuthError::UserNotFound,
+            "INVALID_PASSWORD" => FirebaseAuthError::InvalidPassword,
+            "USER_DISABLED" => FirebaseAuthError::UserDisabled,
+            "EMAIL_EXISTS" => FirebaseAuthError::EmailExists,
+            "TOO_MANY_ATTEMPTS_TRY_LATER" => FirebaseAuthError::TooManyAttempts,
+            "EXPIRED_OOB_CODE" => FirebaseAuthError::ExpiredOobCode,
+            "INVALID_OOB_CODE" => FirebaseAuthError::InvalidOobCode,
+            "INVALID_CUSTOM_TOKEN" => FirebaseAuthError::InvalidCustomToken,
+            "CREDENTIAL_MISMATCH" => FirebaseAuthError::CredentialMismatch,
+            _ => FirebaseAuthError::Unknown(message.to_string()),
+        }
+    }
+
+    fn from_response(status: reqwest::StatusCode, body: &str) -> Self {
+        match serde_json::from_str::<ErrorResponse>(body) {
+            Ok(envelope) => Self::from_message(&envelope.error.message),
+            Err(_) => FirebaseAuthError::Unknown(format!("{}: {}", status, body)),
+        }
+    }
+}
+
+// 非 2xx 時解析 Firebase 的錯誤格式，成功時才反序列化成 R
+async fn parse_response<R: DeserializeOwned>(response: Response) -> Result<R, FirebaseAuthError> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(FirebaseAuthError::from_response(status, &body));
+    }
+    Ok(response.json::<R>().await?)
+}
+
 pub trait FirebaseAuthRequest {
     fn get_endpoint(&self) -> &str;
     fn req_body(&self) -> serde_json::Value;
+
+    // GET / HEAD 不會帶 body，其餘方法以 JSON 送出 req_body
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    // 附加在 URL 上的查詢參數，API key 會另外加上
+    fn query_params(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    // 可以安全重送的請求在連線失敗或逾時時依 max_retries 重試，預設只有 GET / HEAD；
+    // 查詢類的 POST 可覆寫為 true
+    fn idempotent(&self) -> bool {
+        matches!(self.method(), Method::GET | Method::HEAD)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -11,30 +166,243 @@ pub struct FirebaseAuthService {
     pub client: Client,
     pub base_url: String,
     pub api_token: String,
+    // 換發 id token 用的 Secure Token API，與 Identity Toolkit 是不同的網域
+    pub secure_token_url: String,
+    // 冪等操作遇到連線失敗或逾時時的重試次數
+    pub max_retries: u32,
+    pub api_key_location: ApiKeyLocation,
 }
 
 impl FirebaseAuthService {
+    // 使用預設設定（10 秒逾時、不重試）
+    pub fn new(base_url: impl Into<String>, api_token: impl Into<String>) -> Self {
+        Self::builder(base_url, api_token)
+            .build()
+            .expect("failed to build reqwest client")
+    }
+
+    // 連到本機 Auth Emulator 的服務，供整合測試使用，見 FirebaseAuthServiceBuilder::emulator
+    pub fn emulator(host: impl AsRef<str>) -> Self {
+        FirebaseAuthServiceBuilder::emulator(host)
+            .build()
+            .expect("failed to build reqwest client")
+    }
+
+    pub fn builder(
+        base_url: impl Into<String>,
+        api_token: impl Into<String>,
+    ) -> FirebaseAuthServiceBuilder {
+        FirebaseAuthServiceBuilder::new(base_url, api_token)
+    }
+
+    pub fn with_secure_token_url(mut self, url: impl Into<String>) -> Self {
+        self.secure_token_url = url.into();
+        self
+    }
+
+    pub async fn sign_up_with_password(
+        &self,
+        email: &str,
+        password: &str,
+    ) -> Result<SignUpResponse, FirebaseAuthError> {
+        let body = PasswordRequest {
+            email,
+            password,
+            return_secure_token: true,
+        };
+        self.post(&self.base_url, "/v1/accounts:signUp", &body, false)
+            .await
+    }
+
+    pub async fn sign_in_with_password(
+        &self,
+        email: &str,
+        password: &str,
+    ) -> Result<SignInResponse, FirebaseAuthError> {
+        let body = PasswordRequest {
+            email,
+            password,
+            return_secure_token: true,
+        };
+        self.post(
+            &self.base_url,
+            "/v1/accounts:signInWithPassword",
+            &body,
+            true,
+        )
+        .await
+    }
+
+    // 以 Admin SDK 簽發的 custom token 換取 id token 與 refresh token
+    pub async fn sign_in_with_custom_token(
+        &self,
+        custom_token: &str,
+    ) -> Result<CustomTokenResponse, FirebaseAuthError> {
+        let body = CustomTokenRequest {
+            token: custom_token,
+            return_secure_token: true,
+        };
+        self.post(
+            &self.base_url,
+            "/v1/accounts:signInWithCustomToken",
+            &body,
+            true,
+        )
+        .await
+    }
+
+    // 以 refresh token 換發新的 id token，走的是 securetoken.googleapis.com
+    // 該端點要求 application/x-www-form-urlencoded，不能像其他端點一樣送 JSON
+    pub async fn refresh_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<RefreshResponse, FirebaseAuthError> {
+        let body = RefreshRequest {
+            grant_type: "refresh_token",
+            refresh_token,
+        };
+        let url = format!("{}/v1/token", self.secure_token_url);
+        let response = self
+            .send_with_retry(true, || {
+                self.with_api_key(self.client.post(&url)).form(&body)
+            })
+            .await?;
+        parse_response(response).await
+    }
+
+    pub async fn send_password_reset_email(
+        &self,
+        email: &str,
+    ) -> Result<PasswordResetResponse, FirebaseAuthError> {
+        let body = OobCodeRequest {
+            request_type: "PASSWORD_RESET",
+            email,
+        };
+        self.post(&self.base_url, "/v1/accounts:sendOobCode", &body, false)
+            .await
+    }
+
+    // 驗證密碼重設信中的 oob code，回傳要重設密碼的帳號 email
+    pub async fn verify_password_reset_code(
+        &self,
+        oob_code: &str,
+    ) -> Result<String, FirebaseAuthError> {
+        let body = ResetPasswordRequest {
+            oob_code,
+            new_password: None,
+        };
+        let response: ResetPasswordResponse = self
+            .post(&self.base_url, "/v1/accounts:resetPassword", &body, true)
+            .await?;
+        Ok(response.email)
+    }
+
+    // 以 oob code 設定新密碼，成功後該 code 即失效
+    pub async fn confirm_password_reset(
+        &self,
+        oob_code: &str,
+        new_password: &str,
+    ) -> Result<(), FirebaseAuthError> {
+        let body = ResetPasswordRequest {
+            oob_code,
+            new_password: Some(new_password),
+        };
+        self.post::<_, ResetPasswordResponse>(
+            &self.base_url,
+            "/v1/accounts:resetPassword",
+            &body,
+            false,
+        )
+        .await?;
+        Ok(())
+    }
+
+    // 以 id token 查詢完整的使用者資料（email_verified、顯示名稱、登入方式等）
+    pub async fn lookup_account(&self, id_token: &str) -> Result<AccountInfo, FirebaseAuthError> {
+        let body = IdTokenRequest { id_token };
+        let response: LookupResponse = self
+            .post(&self.base_url, "/v1/accounts:lookup", &body, true)
+            .await?;
+        response
+            .users
+            .into_iter()
+            .next()
+            .ok_or(FirebaseAuthError::UserNotFound)
+    }
+
+    // 不重試：第一次請求若在刪除後才逾時，重送會得到 USER_NOT_FOUND
+    pub async fn delete_account(&self, id_token: &str) -> Result<(), FirebaseAuthError> {
+        let body = IdTokenRequest { id_token };
+        self.post::<_, serde_json::Value>(&self.base_url, "/v1/accounts:delete", &body, false)
+            .await?;
+        Ok(())
+    }
+
+    async fn post<B: Serialize, R: DeserializeOwned>(
+        &self,
+        base_url: &str,
+        endpoint: &str,
+        body: &B,
+        idempotent: bool,
+    ) -> Result<R, FirebaseAuthError> {
+        let url = format!("{}{}", base_url, endpoint);
+        let response = self
+            .send_with_retry(idempotent, || {
+                self.with_api_key(self.client.post(&url)).json(body)
+            })
+            .await?;
+        parse_response(response).await
+    }
+
+    fn with_api_key(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.api_key_location {
+            ApiKeyLocation::Query => request.query(&[("key", &self.api_token)]),
+            ApiKeyLocation::Header => request.header(API_KEY_HEADER, &self.api_token),
+        }
+    }
+
+    // 冪等的請求在連線失敗或逾時時依 max_retries 重送，其他錯誤直接回傳
+    async fn send_with_retry(
+        &self,
+        idempotent: bool,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<Response, FirebaseAuthError> {
+        let max_retries = if idempotent { self.max_retries } else { 0 };
+        let mut attempt = 0;
+        loop {
+            match request().send().await {
+                Err(e) if attempt < max_retries && (e.is_timeout() || e.is_connect()) => {
+                    attempt += 1;
+                    tracing::warn!("Firebase 請求失敗，進行第 {} 次重試：{}", attempt, e);
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+
     pub async fn request<
         T: FirebaseAuthRequest,
         R: DeserializeOwned + std::fmt::Debug + serde::Serialize,
     >(
         &self,
         req: T,
-    ) -> Result<R, Error> {
-        let url = format!(
-            "{}{}?key={}",
-            self.base_url,
-            &req.get_endpoint(),
-            self.api_token
-        );
+    ) -> Result<R, FirebaseAuthError> {
+        let url = format!("{}{}", self.base_url, &req.get_endpoint());
+        let method = req.method();
+        let body = (!matches!(method, Method::GET | Method::HEAD)).then(|| req.req_body());
+        let query = req.query_params();
         let response = self
-            .client
-            .post(url.clone())
-            .header("Content-Type", "application/json")
-            .json(&req.req_body())
-            .send()
+            .send_with_retry(req.idempotent(), || {
+                let request = self
+                    .with_api_key(self.client.request(method.clone(), &url))
+                    .query(&query);
+                match &body {
+                    Some(body) => request.json(body),
+                    None => request,
+                }
+            })
             .await?;
-        let result = response.json::<R>().await;
+        let result = parse_response::<R>(response).await;
         // 使用 serde_json 的 to_string_pretty() 方法格式化输出
         if let Ok(ref data) = result {
             let pretty_json = serde_json::to_string_pretty(data).unwrap();
@@ -46,3 +414,543 @@ impl FirebaseAuthService {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_mock_server;
+    use axum::{
+        extract::{Path, Query},
+        http::{header, HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::post,
+        Json, Router,
+    };
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // 模擬 Identity Toolkit 與 Secure Token API，所有請求都必須帶上 ?key=
+    async fn mock_firebase(
+        Path(endpoint): Path<String>,
+        Query(query): Query<HashMap<String, String>>,
+        Json(body): Json<Value>,
+    ) -> axum::response::Response {
+        assert_eq!(query.get("key").map(String::as_str), Some("test-key"));
+        match endpoint.as_str() {
+            "accounts:signUp" | "accounts:signInWithPassword" => {
+                assert_eq!(body["returnSecureToken"], true);
+                if body["password"] == "wrong" {
+                    let error = json!({ "error": { "code": 400, "message": "INVALID_PASSWORD" } });
+                    return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+                }
+                Json(json!({
+                    "idToken": "id-token",
+                    "email": body["email"],
+                    "refreshToken": "refresh-token",
+                    "expiresIn": "3600",
+                    "localId": "uid-1",
+                    "registered": endpoint == "accounts:signInWithPassword"
+                }))
+                .into_response()
+            }
+            "accounts:signInWithCustomToken" => {
+                assert_eq!(body["returnSecureToken"], true);
+                let message = match body["token"].as_str() {
+                    Some("custom-token") => {
+                        return Json(json!({
+                            "kind": "identitytoolkit#VerifyCustomTokenResponse",
+                            "idToken": "id-token",
+                            "refreshToken": "refresh-token",
+                            "expiresIn": "3600",
+                            "isNewUser": false
+                        }))
+                        .into_response()
+                    }
+                    Some("other-project-token") => "CREDENTIAL_MISMATCH",
+                    _ => "INVALID_CUSTOM_TOKEN",
+                };
+                let error = json!({ "error": { "code": 400, "message": message } });
+                (StatusCode::BAD_REQUEST, Json(error)).into_response()
+            }
+            "accounts:sendOobCode" => {
+                assert_eq!(body["requestType"], "PASSWORD_RESET");
+                Json(json!({ "email": body["email"] })).into_response()
+            }
+            "accounts:resetPassword" => {
+                let message = match body["oobCode"].as_str() {
+                    Some("valid-code") => {
+                        return Json(json!({
+                            "email": "user@example.com",
+                            "requestType": "PASSWORD_RESET"
+                        }))
+                        .into_response()
+                    }
+                    Some("expired-code") => "EXPIRED_OOB_CODE",
+                    _ => "INVALID_OOB_CODE",
+                };
+                let error = json!({ "error": { "code": 400, "message": message } });
+                (StatusCode::BAD_REQUEST, Json(error)).into_response()
+            }
+            "accounts:lookup" => {
+                if body["idToken"] == "deleted-user-token" {
+                    return Json(json!({ "kind": "identitytoolkit#GetAccountInfoResponse" }))
+                        .into_response();
+                }
+                assert_eq!(body["idToken"], "id-token");
+                Json(json!({
+                    "users": [{
+                        "localId": "uid-1",
+                        "email": "user@example.com",
+                        "emailVerified": true,
+                        "displayName": "Test User",
+                        "providerUserInfo": [{
+                            "providerId": "password",
+                            "federatedId": "user@example.com",
+                            "email": "user@example.com"
+                        }],
+                        "createdAt": "1700000000000",
+                        "lastLoginAt": "1700000500000"
+                    }]
+                }))
+                .into_response()
+            }
+            "accounts:delete" => {
+                assert_eq!(body["idToken"], "id-token");
+                Json(json!({})).into_response()
+            }
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    // 模擬 Secure Token API，只接受 form 編碼的請求
+    async fn mock_secure_token(
+        Query(query): Query<HashMap<String, String>>,
+        headers: HeaderMap,
+        body: String,
+    ) -> axum::response::Response {
+        assert_eq!(query.get("key").map(String::as_str), Some("test-key"));
+        assert_eq!(
+            headers[header::CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(
+            body,
+            "grant_type=refresh_token&refresh_token=refresh%2Ftoken%3D%3D"
+        );
+        Json(json!({
+            "id_token": "new-id-token",
+            "refresh_token": "new-refresh-token",
+            "expires_in": "3600",
+            "token_type": "Bearer",
+            "user_id": "uid-1",
+            "project_id": "1234"
+        }))
+        .into_response()
+    }
+
+    async fn test_service() -> FirebaseAuthService {
+        let identity =
+            spawn_mock_server(Router::new().route("/v1/:endpoint", post(mock_firebase))).await;
+        let secure_token =
+            spawn_mock_server(Router::new().route("/v1/token", post(mock_secure_token))).await;
+        FirebaseAuthService::new(identity, "test-key").with_secure_token_url(secure_token)
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_with_password() {
+        let service = test_service().await;
+        let response = service
+            .sign_up_with_password("user@example.com", "secret")
+            .await
+            .unwrap();
+        assert_eq!(response.id_token, "id-token");
+        assert_eq!(response.email, "user@example.com");
+        assert_eq!(response.local_id, "uid-1");
+    }
+
+    #[tokio::test]
+    async fn test_sign_in_with_password() {
+        let service = test_service().await;
+        let response = service
+            .sign_in_with_password("user@example.com", "secret")
+            .await
+            .unwrap();
+        assert_eq!(response.refresh_token, "refresh-token");
+        assert!(response.registered);
+
+        let error = service
+            .sign_in_with_password("user@example.com", "wrong")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, FirebaseAuthError::InvalidPassword));
+    }
+
+    #[tokio::test]
+    async fn test_hung_endpoint_times_out_and_retries_idempotent_calls() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits_clone = hits.clone();
+        let app = Router::new().fallback(move || {
+            let hits = hits_clone.clone();
+            async move {
+                hits.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                StatusCode::OK
+            }
+        });
+        let service = FirebaseAuthService::builder(spawn_mock_server(app).await, "test-key")
+            .timeout(Duration::from_millis(200))
+            .connect_timeout(Duration::from_secs(1))
+            .max_retries(2)
+            .build()
+            .unwrap();
+
+        let started = std::time::Instant::now();
+        let error = service
+            .sign_in_with_password("user@example.com", "secret")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, FirebaseAuthError::Request(e) if e.is_timeout()),
+            "應該是逾時錯誤，實際為 {:?}",
+            error
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(hits.load(Ordering::SeqCst), 3, "冪等操作應重試 2 次");
+
+        // 非冪等的註冊與刪除帳號不應重試
+        hits.store(0, Ordering::SeqCst);
+        assert!(service
+            .sign_up_with_password("user@example.com", "secret")
+            .await
+            .is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        hits.store(0, Ordering::SeqCst);
+        assert!(service.delete_account("id-token").await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // 通用的 request 依 FirebaseAuthRequest::idempotent 決定是否重試
+        struct LookupByIdRequest;
+        impl FirebaseAuthRequest for LookupByIdRequest {
+            fn get_endpoint(&self) -> &str {
+                "/v1/projects/demo/accounts:lookup"
+            }
+            fn req_body(&self) -> Value {
+                json!({})
+            }
+            fn method(&self) -> Method {
+                Method::GET
+            }
+        }
+        hits.store(0, Ordering::SeqCst);
+        assert!(service
+            .request::<_, Value>(LookupByIdRequest)
+            .await
+            .is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_custom_headers_are_sent() {
+        let app = Router::new().fallback(|headers: axum::http::HeaderMap| async move {
+            assert_eq!(headers["x-cloud-trace-context"], "trace-id/1;o=1");
+            Json(json!({}))
+        });
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("X-Cloud-Trace-Context", "trace-id/1;o=1".parse().unwrap());
+        let service = FirebaseAuthService::builder(spawn_mock_server(app).await, "test-key")
+            .headers(headers)
+            .build()
+            .unwrap();
+
+        service.delete_account("id-token").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_api_key_sent_in_header() {
+        let app = Router::new().fallback(
+            |uri: axum::http::Uri, headers: axum::http::HeaderMap| async move {
+                assert_eq!(headers["x-goog-api-key"], "test-key");
+                assert!(
+                    !uri.query().unwrap_or_default().contains("key="),
+                    "標頭模式下 URL 不應帶 key：{}",
+                    uri
+                );
+                Json(json!({ "users": [{ "localId": "uid-1" }] }))
+            },
+        );
+        let service = FirebaseAuthService::builder(spawn_mock_server(app).await, "test-key")
+            .api_key_location(ApiKeyLocation::Header)
+            .build()
+            .unwrap();
+
+        struct LookupRequest;
+        impl FirebaseAuthRequest for LookupRequest {
+            fn get_endpoint(&self) -> &str {
+                "/v1/accounts:lookup"
+            }
+            fn req_body(&self) -> Value {
+                json!({ "idToken": "id-token" })
+            }
+        }
+
+        let response: Value = service.request(LookupRequest).await.unwrap();
+        assert_eq!(response["users"][0]["localId"], "uid-1");
+        let account = service.lookup_account("id-token").await.unwrap();
+        assert_eq!(account.local_id, "uid-1");
+    }
+
+    // emulator 以原網域作為路徑前綴，兩個 API 共用同一個 host
+    #[tokio::test]
+    async fn test_emulator_routes_requests_to_local_host() {
+        let app = Router::new()
+            .route(
+                "/identitytoolkit.googleapis.com/v1/accounts:signInWithPassword",
+                post(
+                    |Query(query): Query<HashMap<String, String>>,
+                     Json(body): Json<Value>| async move {
+                        assert_eq!(query["key"], EMULATOR_API_KEY);
+                        assert_eq!(
+                            body,
+                            json!({
+                                "email": "user@example.com",
+                                "password": "secret",
+                                "returnSecureToken": true
+                            })
+                        );
+                        Json(json!({
+                            "idToken": "id-token",
+                            "email": "user@example.com",
+                            "refreshToken": "refresh-token",
+                            "expiresIn": "3600",
+                            "localId": "uid-1",
+                            "registered": true
+                        }))
+                    },
+                ),
+            )
+            .route(
+                "/securetoken.googleapis.com/v1/token",
+                post(|Query(query): Query<HashMap<String, String>>, body: String| async move {
+                    assert_eq!(query["key"], EMULATOR_API_KEY);
+                    assert_eq!(body, "grant_type=refresh_token&refresh_token=refresh-token");
+                    Json(json!({
+                        "id_token": "new-id-token",
+                        "refresh_token": "new-refresh-token",
+                        "expires_in": "3600",
+                        "token_type": "Bearer",
+                        "user_id": "uid-1",
+                        "project_id": "demo-project"
+                    }))
+                }),
+            );
+        let url = spawn_mock_server(app).await;
+        let host = url.trim_start_matches("http://");
+        let service = FirebaseAuthService::emulator(host);
+        assert_eq!(
+            service.base_url,
+            format!("http://{}/identitytoolkit.googleapis.com", host)
+        );
+
+        let signed_in = service
+            .sign_in_with_password("user@example.com", "secret")
+            .await
+            .unwrap();
+        assert_eq!(signed_in.local_id, "uid-1");
+        let refreshed = service
+            .refresh_token(&signed_in.refresh_token)
+            .await
+            .unwrap();
+        assert_eq!(refreshed.id_token, "new-id-token");
+    }
+
+    #[test]
+    fn test_error_message_mapping() {
+        let from = FirebaseAuthError::from_message;
+        use FirebaseAuthError::*;
+        assert!(matches!(from("EMAIL_NOT_FOUND"), EmailNotFound));
+        assert!(matches!(from("USER_NOT_FOUND"), UserNotFound));
+        assert!(matches!(from("INVALID_PASSWORD"), InvalidPassword));
+        assert!(matches!(from("USER_DISABLED"), UserDisabled));
+        assert!(matches!(from("EMAIL_EXISTS"), EmailExists));
+        assert!(matches!(from("EXPIRED_OOB_CODE"), ExpiredOobCode));
+        assert!(matches!(from("INVALID_OOB_CODE"), InvalidOobCode));
+        assert!(matches!(from("INVALID_CUSTOM_TOKEN"), InvalidCustomToken));
+        assert!(matches!(from("CREDENTIAL_MISMATCH"), CredentialMismatch));
+        assert!(matches!(
+            from("TOO_MANY_ATTEMPTS_TRY_LATER : Access to this account has been temporarily disabled"),
+            TooManyAttempts
+        ));
+
+        assert!(matches!(
+            FirebaseAuthError::from_message("WEAK_PASSWORD : Password should be at least 6 characters"),
+            FirebaseAuthError::Unknown(msg) if msg.starts_with("WEAK_PASSWORD")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_request_with_get_method_and_query_params() {
+        let app = Router::new().route(
+            "/v1/projects/demo/accounts:lookup",
+            axum::routing::get(
+                |Query(query): Query<HashMap<String, String>>, body: String| async move {
+                    assert_eq!(query["key"], "test-key");
+                    assert_eq!(query["localId"], "uid-1");
+                    assert!(body.is_empty(), "GET 請求不應帶 body");
+                    Json(json!({ "users": [{ "localId": "uid-1" }] }))
+                },
+            ),
+        );
+        let service = FirebaseAuthService::new(spawn_mock_server(app).await, "test-key");
+
+        struct LookupByIdRequest;
+        impl FirebaseAuthRequest for LookupByIdRequest {
+            fn get_endpoint(&self) -> &str {
+                "/v1/projects/demo/accounts:lookup"
+            }
+            fn req_body(&self) -> Value {
+                json!({})
+            }
+            fn method(&self) -> Method {
+                Method::GET
+            }
+            fn query_params(&self) -> Vec<(String, String)> {
+                vec![("localId".to_string(), "uid-1".to_string())]
+            }
+        }
+
+        let response: Value = service.request(LookupByIdRequest).await.unwrap();
+        assert_eq!(response["users"][0]["localId"], "uid-1");
+    }
+
+    #[tokio::test]
+    async fn test_request_returns_typed_error_on_failure() {
+        let app = Router::new().fallback(|| async {
+            let error = json!({ "error": { "code": 400, "message": "EMAIL_NOT_FOUND" } });
+            (StatusCode::BAD_REQUEST, Json(error))
+        });
+        let service = FirebaseAuthService::new(spawn_mock_server(app).await, "test-key");
+
+        struct LookupRequest;
+        impl FirebaseAuthRequest for LookupRequest {
+            fn get_endpoint(&self) -> &str {
+                "/v1/accounts:signInWithPassword"
+            }
+            fn req_body(&self) -> Value {
+                json!({})
+            }
+        }
+
+        let result = service.request::<_, Value>(LookupRequest).await;
+        assert!(matches!(result, Err(FirebaseAuthError::EmailNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_posts_form_to_secure_token_url() {
+        let secure_token =
+            spawn_mock_server(Router::new().route("/v1/token", post(mock_secure_token))).await;
+        // Identity Toolkit 網址故意指向不存在的服務，確保 refresh 不會打到那裡
+        let service = FirebaseAuthService::new("http://127.0.0.1:1", "test-key")
+            .with_secure_token_url(secure_token);
+
+        // 特殊字元必須經過 form 編碼
+        let response = service.refresh_token("refresh/token==").await.unwrap();
+        assert_eq!(response.id_token, "new-id-token");
+        assert_eq!(response.refresh_token, "new-refresh-token");
+        assert_eq!(response.expires_in, "3600");
+    }
+
+    #[tokio::test]
+    async fn test_send_password_reset_email() {
+        let service = test_service().await;
+        let response = service
+            .send_password_reset_email("user@example.com")
+            .await
+            .unwrap();
+        assert_eq!(response.email, "user@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_with_valid_code() {
+        let service = test_service().await;
+        let email = service
+            .verify_password_reset_code("valid-code")
+            .await
+            .unwrap();
+        assert_eq!(email, "user@example.com");
+
+        service
+            .confirm_password_reset("valid-code", "new-secret")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_with_expired_code() {
+        let service = test_service().await;
+        let error = service
+            .verify_password_reset_code("expired-code")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, FirebaseAuthError::ExpiredOobCode));
+
+        let error = service
+            .confirm_password_reset("expired-code", "new-secret")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, FirebaseAuthError::ExpiredOobCode));
+    }
+
+    #[tokio::test]
+    async fn test_sign_in_with_custom_token() {
+        let service = test_service().await;
+        let response = service
+            .sign_in_with_custom_token("custom-token")
+            .await
+            .unwrap();
+        assert_eq!(response.id_token, "id-token");
+        assert_eq!(response.refresh_token, "refresh-token");
+        assert_eq!(response.expires_in, "3600");
+        assert!(!response.is_new_user);
+
+        let error = service
+            .sign_in_with_custom_token("garbage")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, FirebaseAuthError::InvalidCustomToken));
+
+        let error = service
+            .sign_in_with_custom_token("other-project-token")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, FirebaseAuthError::CredentialMismatch));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_account() {
+        let service = test_service().await;
+        let account = service.lookup_account("id-token").await.unwrap();
+        assert_eq!(account.local_id, "uid-1");
+        assert_eq!(account.email.as_deref(), Some("user@example.com"));
+        assert!(account.email_verified);
+        assert_eq!(account.display_name.as_deref(), Some("Test User"));
+        assert!(!account.disabled);
+        assert_eq!(account.provider_user_info.len(), 1);
+        assert_eq!(account.provider_user_info[0].provider_id, "password");
+        assert_eq!(account.created_at.as_deref(), Some("1700000000000"));
+
+        let error = service
+            .lookup_account("deleted-user-token")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, FirebaseAuthError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_account() {
+        let service = test_service().await;
+        assert!(service.delete_account("id-token").await.is_ok());
+    }
+}
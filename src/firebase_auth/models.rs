@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PasswordRequest<'a> {
+    pub email: &'a str,
+    pub password: &'a str,
+    pub return_secure_token: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RefreshRequest<'a> {
+    pub grant_type: &'a str,
+    pub refresh_token: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OobCodeRequest<'a> {
+    pub request_type: &'a str,
+    pub email: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IdTokenRequest<'a> {
+    pub id_token: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CustomTokenRequest<'a> {
+    pub token: &'a str,
+    pub return_secure_token: bool,
+}
+
+// 不帶 new_password 時只驗證 oob code，不會變更密碼
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ResetPasswordRequest<'a> {
+    pub oob_code: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_password: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ResetPasswordResponse {
+    #[serde(default)]
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignUpResponse {
+    pub id_token: String,
+    #[serde(default)]
+    pub email: String,
+    pub refresh_token: String,
+    pub expires_in: String,
+    pub local_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignInResponse {
+    pub id_token: String,
+    pub email: String,
+    pub refresh_token: String,
+    pub expires_in: String,
+    pub local_id: String,
+    #[serde(default)]
+    pub registered: bool,
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomTokenResponse {
+    pub id_token: String,
+    pub refresh_token: String,
+    pub expires_in: String,
+    #[serde(default)]
+    pub is_new_user: bool,
+}
+
+// Secure Token API 的回應欄位是 snake_case，與 Identity Toolkit 不同
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshResponse {
+    pub id_token: String,
+    pub refresh_token: String,
+    pub expires_in: String,
+    #[serde(default)]
+    pub token_type: String,
+    #[serde(default)]
+    pub user_id: String,
+    #[serde(default)]
+    pub project_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordResetResponse {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LookupResponse {
+    #[serde(default)]
+    pub users: Vec<AccountInfo>,
+}
+
+// accounts:lookup 回傳的使用者資料，時間欄位為毫秒時間戳字串
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInfo {
+    pub local_id: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub photo_url: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default)]
+    pub provider_user_info: Vec<ProviderUserInfo>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub last_login_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderUserInfo {
+    pub provider_id: String,
+    #[serde(default)]
+    pub federated_id: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
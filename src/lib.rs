@@ -1,5 +1,12 @@
+pub mod clock;
+pub mod error;
 pub mod fcm_messaging;
 pub mod firebase_auth;
 pub mod scheduler;
 pub mod sqlx;
 pub mod utilty;
+
+pub use error::SdkError;
+
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,149 @@
+use super::{JobCallback, JobFuture};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+// 任務的觸發方式：cron 表達式（含秒），或固定間隔
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    Cron(String),
+    Interval(Duration),
+}
+
+// 未命名任務在 metrics 與事件中的名稱
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Cron(expr) => f.write_str(expr),
+            Trigger::Interval(interval) => write!(f, "every {:?}", interval),
+        }
+    }
+}
+
+// 任務失敗後的重試策略，第 n 次重試前等待 base_delay * 2^n，並以 max_delay 為上限
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobRetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for JobRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl JobRetryPolicy {
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+// 每次觸發時套用的執行選項，add_task_with_timeout / add_task_with_jitter 也是透過它實作
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunOptions {
+    pub timeout: Option<Duration>,
+    // 失敗（panic）或逾時後依 policy 重試，重試仍屬於同一次觸發
+    pub retry: Option<JobRetryPolicy>,
+    // 上一次觸發還在執行時略過這次觸發
+    pub no_overlap: bool,
+    pub jitter: Option<Duration>,
+}
+
+// 以鏈式呼叫組出任務，最後以 run 給定 callback 並交給 Scheduler::add_job：
+//
+// let spec = JobBuilder::cron("0 */5 * * * *")
+//     .name("sync")
+//     .timeout(Duration::from_secs(60))
+//     .no_overlap()
+//     .run(|| async { sync().await });
+// scheduler.add_job(spec).await?;
+#[derive(Debug, Clone)]
+pub struct JobBuilder {
+    trigger: Trigger,
+    name: Option<String>,
+    options: RunOptions,
+}
+
+impl JobBuilder {
+    pub fn cron(expr: impl Into<String>) -> Self {
+        Self::with_trigger(Trigger::Cron(expr.into()))
+    }
+
+    // 每隔 interval 觸發一次，第一次觸發在註冊後約一個 interval
+    pub fn interval(interval: Duration) -> Self {
+        Self::with_trigger(Trigger::Interval(interval))
+    }
+
+    fn with_trigger(trigger: Trigger) -> Self {
+        Self {
+            trigger,
+            name: None,
+            options: RunOptions::default(),
+        }
+    }
+
+    // 具名的 cron 任務會出現在 export_definitions 中，且名稱不可重複
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    pub fn retry(mut self, policy: JobRetryPolicy) -> Self {
+        self.options.retry = Some(policy);
+        self
+    }
+
+    pub fn no_overlap(mut self) -> Self {
+        self.options.no_overlap = true;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.options.jitter = Some(jitter);
+        self
+    }
+
+    pub fn run<F, Fut>(self, task: F) -> JobSpec
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        JobSpec {
+            trigger: self.trigger,
+            name: self.name,
+            options: self.options,
+            callback: Arc::new(move || -> JobFuture { Box::pin(task()) }),
+        }
+    }
+}
+
+// JobBuilder::run 的結果，由 Scheduler::add_job 註冊
+pub struct JobSpec {
+    pub(crate) trigger: Trigger,
+    pub(crate) name: Option<String>,
+    pub(crate) options: RunOptions,
+    pub(crate) callback: JobCallback,
+}
+
+impl JobSpec {
+    pub fn trigger(&self) -> &Trigger {
+        &self.trigger
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
@@ -0,0 +1,47 @@
+use super::SchedulerError;
+use serde::Deserialize;
+use std::path::Path;
+
+// 設定檔格式（JSON 或 TOML，依副檔名判斷）：
+//
+// [[jobs]]
+// name = "cleanup"
+// cron = "0 0 3 * * *"
+// enabled = true
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchedulerConfig {
+    #[serde(default)]
+    pub jobs: Vec<JobConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JobConfig {
+    pub name: String,
+    pub cron: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl SchedulerConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SchedulerError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            SchedulerError::InvalidDefinitions(format!("cannot read {}: {}", path.display(), e))
+        })?;
+        let is_toml = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        let parsed = if is_toml {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        };
+        parsed.map_err(|e| {
+            SchedulerError::InvalidDefinitions(format!("cannot parse {}: {}", path.display(), e))
+        })
+    }
+}
@@ -0,0 +1,167 @@
+use chrono::{DateTime, LocalResult, NaiveDate, TimeZone, Utc};
+
+// after 之後（不含）下一次 tz 當地時間 hour:minute 對應的 UTC 時刻。
+// 當地時間不存在時往後找第一個存在的時間，出現兩次時取較早的一次
+pub(crate) fn next_daily_at<Tz: TimeZone>(
+    after: DateTime<Utc>,
+    hour: u32,
+    minute: u32,
+    tz: &Tz,
+) -> Option<DateTime<Utc>> {
+    let mut date = after.with_timezone(tz).date_naive();
+    loop {
+        if let Some(at) = local_occurrence(date, hour, minute, tz) {
+            if at > after {
+                return Some(at);
+            }
+        }
+        date = date.succ_opt()?;
+    }
+}
+
+fn local_occurrence<Tz: TimeZone>(
+    date: NaiveDate,
+    hour: u32,
+    minute: u32,
+    tz: &Tz,
+) -> Option<DateTime<Utc>> {
+    let mut local = date.and_hms_opt(hour, minute, 0)?;
+    // 跳過的時段最長不超過一天，逐分鐘往後找第一個存在的當地時間
+    for _ in 0..=24 * 60 {
+        match tz.from_local_datetime(&local) {
+            LocalResult::Single(at) => return Some(at.with_timezone(&Utc)),
+            LocalResult::Ambiguous(earliest, _) => return Some(earliest.with_timezone(&Utc)),
+            LocalResult::None => local += chrono::Duration::minutes(1),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, TokioClock};
+    use crate::scheduler::{Scheduler, SchedulerError};
+    use chrono::{FixedOffset, NaiveDateTime};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    // 模擬歐洲中部時間的 2024 年：03-31 01:00Z 起 +2（當地 02:00 跳到 03:00），
+    // 10-27 01:00Z 起回到 +1（當地 03:00 退回 02:00）
+    #[derive(Debug, Clone, Copy)]
+    struct TestDst;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn hours(h: i32) -> FixedOffset {
+        FixedOffset::east_opt(h * 3600).unwrap()
+    }
+
+    impl TimeZone for TestDst {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            TestDst
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // 較大的 offset 對應較早的 UTC 時刻，放在前面
+            let valid: Vec<FixedOffset> = [hours(2), hours(1)]
+                .into_iter()
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+                .collect();
+            match valid[..] {
+                [] => LocalResult::None,
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest, ..] => LocalResult::Ambiguous(earliest, latest),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, at: &NaiveDateTime) -> FixedOffset {
+            let summer =
+                utc("2024-03-31T01:00:00Z").naive_utc()..utc("2024-10-27T01:00:00Z").naive_utc();
+            if summer.contains(at) {
+                hours(2)
+            } else {
+                hours(1)
+            }
+        }
+    }
+
+    #[test]
+    fn test_spring_forward_runs_at_first_time_after_gap() {
+        let next = |after: &str| next_daily_at(utc(after), 2, 30, &TestDst).unwrap();
+        assert_eq!(next("2024-03-30T00:00:00Z"), utc("2024-03-30T01:30:00Z"));
+        // 03-31 當地沒有 02:30，改在 03:00（+2）執行
+        assert_eq!(next("2024-03-30T01:30:00Z"), utc("2024-03-31T01:00:00Z"));
+        assert_eq!(next("2024-03-31T01:00:00Z"), utc("2024-04-01T00:30:00Z"));
+    }
+
+    #[test]
+    fn test_fall_back_runs_once_on_first_occurrence() {
+        let next = |after: &str| next_daily_at(utc(after), 2, 30, &TestDst).unwrap();
+        // 10-27 當地 02:30 出現兩次（00:30Z 與 01:30Z），只取第一次
+        assert_eq!(next("2024-10-26T12:00:00Z"), utc("2024-10-27T00:30:00Z"));
+        assert_eq!(next("2024-10-27T00:30:00Z"), utc("2024-10-28T01:30:00Z"));
+        assert_eq!(next("2024-10-27T01:00:00Z"), utc("2024-10-28T01:30:00Z"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_daily_task_follows_local_time_across_dst() {
+        let clock = Arc::new(TokioClock::starting_at(utc("2024-03-29T12:00:00Z")));
+        let mut scheduler = Scheduler::new_with_clock(clock.clone()).await.unwrap();
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let fired_clone = fired.clone();
+        scheduler
+            .add_daily_at(2, 30, TestDst, move || {
+                let fired = fired_clone.clone();
+                let clock = clock.clone();
+                async move {
+                    fired.lock().unwrap().push(clock.now().timestamp());
+                }
+            })
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(4 * 24 * 3600)).await;
+        scheduler.stop().await.unwrap();
+
+        let expected: Vec<i64> = [
+            "2024-03-30T01:30:00Z",
+            "2024-03-31T01:00:00Z",
+            "2024-04-01T00:30:00Z",
+            "2024-04-02T00:30:00Z",
+        ]
+        .iter()
+        .map(|at| utc(at).timestamp())
+        .collect();
+        assert_eq!(*fired.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_daily_at_rejects_invalid_time() {
+        let scheduler = Scheduler::new().await.unwrap();
+        let err = scheduler
+            .add_daily_at(24, 0, Utc, || async {})
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SchedulerError>(),
+            Some(SchedulerError::InvalidTime {
+                hour: 24,
+                minute: 0
+            })
+        ));
+    }
+}
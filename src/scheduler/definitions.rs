@@ -0,0 +1,46 @@
+use super::{JobCallback, JobFuture};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+// 可攜式的任務定義，只包含名稱與 cron 表達式，callback 於匯入時依名稱從 JobRegistry 取得
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobDefinition {
+    pub name: String,
+    pub cron_expr: String,
+}
+
+// 任務名稱對應到實際執行的 callback
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    callbacks: HashMap<String, JobCallback>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<F, Fut>(&mut self, name: impl Into<String>, task: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let callback: JobCallback = Arc::new(move || Box::pin(task()) as JobFuture);
+        self.callbacks.insert(name.into(), callback);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<JobCallback> {
+        self.callbacks.get(name).cloned()
+    }
+}
+
+impl std::fmt::Debug for JobRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobRegistry")
+            .field("jobs", &self.callbacks.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+const DEFAULT_BROADCAST_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchedulerEvent {
+    Started { job: String },
+    Completed { job: String, duration: Duration },
+    Failed { job: String, error: String },
+    // 執行超過 timeout 被中止，與 Failed 分開以便區分卡住與出錯
+    TimedOut { job: String, timeout: Duration },
+}
+
+// 排程事件的發送端，支援兩種訂閱方式：
+// - subscribe()：broadcast，訂閱者太慢時會遺失舊事件（收到 Lagged），但不會拖慢任務
+// - subscribe_lossless()：每個訂閱者一條有界 mpsc，佇列滿時任務會等待（backpressure），保證不遺失
+// 同一個任務的事件一定依 Started -> Completed/Failed 的順序送達；不同任務同時觸發時彼此的事件可能交錯
+#[derive(Clone, Debug)]
+pub(crate) struct EventBus {
+    broadcast: broadcast::Sender<SchedulerEvent>,
+    lossless: Arc<Mutex<Vec<mpsc::Sender<SchedulerEvent>>>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_BROADCAST_CAPACITY)
+    }
+}
+
+impl EventBus {
+    pub(crate) fn new(broadcast_capacity: usize) -> Self {
+        let (broadcast, _) = broadcast::channel(broadcast_capacity);
+        Self {
+            broadcast,
+            lossless: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<SchedulerEvent> {
+        self.broadcast.subscribe()
+    }
+
+    pub(crate) fn subscribe_lossless(&self, capacity: usize) -> mpsc::Receiver<SchedulerEvent> {
+        let (tx, rx) = mpsc::channel(capacity);
+        self.lossless.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub(crate) async fn emit(&self, event: SchedulerEvent) {
+        // 沒有 broadcast 訂閱者時 send 會回傳錯誤，可以忽略
+        let _ = self.broadcast.send(event.clone());
+
+        let senders = self.lossless.lock().unwrap().clone();
+        let mut has_closed = false;
+        for sender in senders {
+            if sender.send(event.clone()).await.is_err() {
+                has_closed = true;
+            }
+        }
+        if has_closed {
+            self.lossless
+                .lock()
+                .unwrap()
+                .retain(|sender| !sender.is_closed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started(n: usize) -> SchedulerEvent {
+        SchedulerEvent::Started {
+            job: format!("job-{}", n),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lossless_subscriber_never_drops_events() {
+        let bus = EventBus::new(1);
+        let mut lossless = bus.subscribe_lossless(2);
+        let mut lossy = bus.subscribe();
+
+        // 慢速訂閱者：每收到一個事件就停頓一下
+        let consumer = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Some(event) = lossless.recv().await {
+                tokio::time::sleep(Duration::from_millis(2)).await;
+                received.push(event);
+            }
+            received
+        });
+
+        for n in 0..50 {
+            bus.emit(started(n)).await;
+        }
+        drop(bus);
+
+        let received = consumer.await.unwrap();
+        assert_eq!(received.len(), 50, "lossless 模式不應遺失任何事件");
+        assert_eq!(received, (0..50).map(started).collect::<Vec<_>>());
+
+        // 對照組：容量為 1 的 broadcast 訂閱者會落後並遺失事件
+        assert!(matches!(
+            lossy.recv().await,
+            Err(broadcast::error::RecvError::Lagged(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_closed_lossless_subscriber_is_removed() {
+        let bus = EventBus::default();
+        let receiver = bus.subscribe_lossless(1);
+        drop(receiver);
+
+        bus.emit(started(0)).await;
+        assert!(bus.lossless.lock().unwrap().is_empty());
+    }
+}
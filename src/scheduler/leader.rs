@@ -0,0 +1,225 @@
+use super::{JobId, PgAdvisoryLock, Scheduler, SchedulerEvent};
+use async_trait::async_trait;
+use sqlx::{pool::PoolConnection, PgPool, Postgres};
+use std::error::Error;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+
+// 判斷目前這個實例是否為 leader，多個副本中只有 leader 會執行任務內容
+#[async_trait]
+pub trait LeaderElector: Send + Sync {
+    async fn is_leader(&self) -> Result<bool, Box<dyn Error + Send + Sync>>;
+}
+
+// 以 Postgres session 層級的 advisory lock 選出 leader
+// 與 PgAdvisoryLock 共用上鎖邏輯，取得鎖的連線會一直保留，直到連線中斷或 elector 被 drop，其他副本才有機會接手
+#[derive(Debug)]
+pub struct PgAdvisoryLockElector {
+    lock: PgAdvisoryLock,
+    key: i64,
+    connection: Mutex<Option<PoolConnection<Postgres>>>,
+}
+
+impl PgAdvisoryLockElector {
+    pub fn new(pool: PgPool, key: i64) -> Self {
+        Self {
+            lock: PgAdvisoryLock::new(pool),
+            key,
+            connection: Mutex::new(None),
+        }
+    }
+}
+
+impl Drop for PgAdvisoryLockElector {
+    fn drop(&mut self) {
+        if let Some(conn) = self.connection.get_mut().take() {
+            PgAdvisoryLock::close_session(conn);
+        }
+    }
+}
+
+#[async_trait]
+impl LeaderElector for PgAdvisoryLockElector {
+    async fn is_leader(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let mut connection = self.connection.lock().await;
+
+        // 已持有鎖：確認連線仍然有效，連線斷掉時鎖也會被 Postgres 釋放
+        if let Some(conn) = connection.as_mut() {
+            if sqlx::query("SELECT 1").execute(&mut **conn).await.is_ok() {
+                return Ok(true);
+            }
+            tracing::warn!("持有 advisory lock 的連線已失效，重新競選 leader");
+            if let Some(conn) = connection.take() {
+                PgAdvisoryLock::close_session(conn);
+            }
+        }
+
+        let Some(conn) = self.lock.lock_session(self.key).await? else {
+            return Ok(false);
+        };
+        tracing::info!("取得 advisory lock {}，成為 leader", self.key);
+        *connection = Some(conn);
+        Ok(true)
+    }
+}
+
+// 只在 leader 上執行任務內容的 Scheduler；非 leader 仍會觸發排程，但會略過任務本體
+pub struct LeaderScheduler {
+    scheduler: Scheduler,
+    elector: Arc<dyn LeaderElector>,
+}
+
+impl LeaderScheduler {
+    pub fn new(scheduler: Scheduler, elector: Arc<dyn LeaderElector>) -> Self {
+        Self { scheduler, elector }
+    }
+
+    pub fn inner(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SchedulerEvent> {
+        self.scheduler.subscribe()
+    }
+
+    pub async fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        self.scheduler.start().await
+    }
+
+    pub async fn stop(&mut self) -> Result<(), Box<dyn Error>> {
+        self.scheduler.stop().await
+    }
+
+    pub async fn add_task<F, Fut>(&self, cron_expr: &str, task: F) -> Result<JobId, Box<dyn Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.scheduler
+            .add_task(cron_expr, leader_only(self.elector.clone(), task))
+            .await
+    }
+
+    pub async fn add_named_task<F, Fut>(
+        &self,
+        name: &str,
+        cron_expr: &str,
+        task: F,
+    ) -> Result<JobId, Box<dyn Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.scheduler
+            .add_named_task(name, cron_expr, leader_only(self.elector.clone(), task))
+            .await
+    }
+}
+
+fn leader_only<F, Fut>(
+    elector: Arc<dyn LeaderElector>,
+    task: F,
+) -> impl Fn() -> std::pin::Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync + Clone + 'static
+where
+    F: Fn() -> Fut + Send + Sync + 'static + Clone,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    move || {
+        let elector = elector.clone();
+        let task = task.clone();
+        Box::pin(async move {
+            match elector.is_leader().await {
+                Ok(true) => task().await,
+                Ok(false) => tracing::debug!("非 leader，略過本次任務"),
+                Err(e) => tracing::warn!("無法確認 leader 身分，略過本次任務：{}", e),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::setup_test_db;
+    use std::time::Duration;
+
+    // 測試用：多個實例共用同一個鎖，第一個詢問的實例成為 leader
+    #[derive(Debug)]
+    struct SharedLockElector {
+        holder: Arc<std::sync::Mutex<Option<&'static str>>>,
+        instance: &'static str,
+    }
+
+    #[async_trait]
+    impl LeaderElector for SharedLockElector {
+        async fn is_leader(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+            let mut holder = self.holder.lock().unwrap();
+            Ok(*holder.get_or_insert(self.instance) == self.instance)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_leader_executes() {
+        let holder = Arc::new(std::sync::Mutex::new(Some("replica-a")));
+        let runs = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let mut replicas = Vec::new();
+        for instance in ["replica-a", "replica-b"] {
+            let elector = Arc::new(SharedLockElector {
+                holder: holder.clone(),
+                instance,
+            });
+            let scheduler = LeaderScheduler::new(Scheduler::new().await.unwrap(), elector);
+            let runs = runs.clone();
+            scheduler
+                .add_task("* * * * * *", move || {
+                    let runs = runs.clone();
+                    async move {
+                        runs.lock().unwrap().push(instance);
+                    }
+                })
+                .await
+                .unwrap();
+            replicas.push(scheduler);
+        }
+
+        for scheduler in replicas.iter_mut() {
+            scheduler.start().await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        for scheduler in replicas.iter_mut() {
+            scheduler.stop().await.unwrap();
+        }
+
+        let runs = runs.lock().unwrap();
+        assert!(!runs.is_empty(), "leader 應該至少執行一次");
+        assert!(runs.iter().all(|instance| *instance == "replica-a"));
+    }
+
+    #[tokio::test]
+    async fn test_pg_advisory_lock_elects_single_leader() {
+        // pool 很小，連線幾乎一定會被重複使用：若 leader 的連線被還回 pool 而非關閉，鎖會殘留在該 session 上
+        let pool = setup_test_db(2).await;
+        let key = 5_460_001;
+
+        let first = PgAdvisoryLockElector::new(pool.clone(), key);
+        let second = PgAdvisoryLockElector::new(pool.clone(), key);
+        assert!(first.is_leader().await.unwrap());
+        assert!(!second.is_leader().await.unwrap());
+        // leader 重複詢問仍然是 leader
+        assert!(first.is_leader().await.unwrap());
+
+        // leader 下線（連線關閉）後，其他實例可以接手
+        drop(first);
+        let mut took_over = false;
+        for _ in 0..40 {
+            if second.is_leader().await.unwrap() {
+                took_over = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(took_over, "leader 下線後應該由另一個實例接手");
+    }
+}
@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use sqlx::{pool::PoolConnection, PgPool, Postgres};
+use std::time::Duration;
+
+// 跨實例的任務鎖：多個副本在同一次觸發時只有取得鎖的實例會執行任務
+// ttl 應短於排程間隔、長於各實例間的時鐘誤差，避免同一次觸發被執行兩次
+#[async_trait]
+pub trait DistributedLock: Send + Sync {
+    async fn try_acquire(&self, job: &str, ttl: Duration) -> bool;
+}
+
+// 以 Postgres session 層級的 advisory lock 實作，鎖的 key 由任務名稱雜湊而來
+// 取得鎖後會占用一條連線直到 ttl 結束才解鎖並歸還，pool 大小需預留這些連線
+#[derive(Debug, Clone)]
+pub struct PgAdvisoryLock {
+    pool: PgPool,
+}
+
+impl PgAdvisoryLock {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // 取得一條連線並嘗試以 key 上鎖，成功時回傳持有鎖的連線；鎖跟著這條連線，
+    // 直到 unlock_session 或 close_session 為止
+    pub(crate) async fn lock_session(
+        &self,
+        key: i64,
+    ) -> Result<Option<PoolConnection<Postgres>>, sqlx::Error> {
+        let mut conn = self.pool.acquire().await?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(key)
+            .fetch_one(&mut *conn)
+            .await?;
+        Ok(acquired.then_some(conn))
+    }
+
+    // 解鎖後把連線還回 pool；解鎖失敗時不能把仍持有鎖的連線還回 pool，直接關閉讓 Postgres 釋放
+    pub(crate) async fn unlock_session(mut conn: PoolConnection<Postgres>, key: i64) {
+        let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(key)
+            .execute(&mut *conn)
+            .await;
+        if unlocked.is_err() {
+            Self::close_session(conn);
+        }
+    }
+
+    // 持有鎖的連線不能還回 pool（鎖會跟著連線被重複使用），直接關閉讓 Postgres 釋放鎖
+    pub(crate) fn close_session(conn: PoolConnection<Postgres>) {
+        drop(conn.detach());
+    }
+}
+
+#[async_trait]
+impl DistributedLock for PgAdvisoryLock {
+    async fn try_acquire(&self, job: &str, ttl: Duration) -> bool {
+        let key = lock_key(job);
+        match self.lock_session(key).await {
+            Ok(Some(conn)) => {
+                tokio::spawn(async move {
+                    tokio::time::sleep(ttl).await;
+                    Self::unlock_session(conn, key).await;
+                });
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                tracing::warn!("競爭任務 {} 的鎖失敗：{}", job, e);
+                false
+            }
+        }
+    }
+}
+
+// FNV-1a：不同版本、不同機器上的實例必須算出相同的 key，不能使用 DefaultHasher
+fn lock_key(job: &str) -> i64 {
+    let hash = job.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    hash as i64
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failure,
+    // 超過 add_task_with_timeout 設定的時間而被中止
+    Timeout,
+}
+
+// 每次任務執行結束後呼叫，可實作成 Prometheus 等監控系統的 counter / histogram
+pub trait MetricsRecorder: Send + Sync {
+    fn record_run(&self, job: &str, duration: Duration, outcome: Outcome);
+}
+
+// 預設的 recorder，不做任何事
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetrics;
+
+impl MetricsRecorder for NoopMetrics {
+    fn record_run(&self, _job: &str, _duration: Duration, _outcome: Outcome) {}
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct JobMetrics {
+    pub runs: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    pub total_duration: Duration,
+    pub last_duration: Option<Duration>,
+}
+
+// 將統計保存在記憶體中，主要供測試或簡單的 /metrics 端點使用
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    jobs: Mutex<HashMap<String, JobMetrics>>,
+}
+
+impl InMemoryMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, job: &str) -> Option<JobMetrics> {
+        self.jobs.lock().unwrap().get(job).cloned()
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, JobMetrics> {
+        self.jobs.lock().unwrap().clone()
+    }
+}
+
+impl MetricsRecorder for InMemoryMetrics {
+    fn record_run(&self, job: &str, duration: Duration, outcome: Outcome) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let metrics = jobs.entry(job.to_string()).or_default();
+        metrics.runs += 1;
+        match outcome {
+            Outcome::Success => metrics.successes += 1,
+            Outcome::Failure => metrics.failures += 1,
+            Outcome::Timeout => metrics.timeouts += 1,
+        }
+        metrics.total_duration += duration;
+        metrics.last_duration = Some(duration);
+    }
+}
+
+// Scheduler::stats 的結果，適合簡單的狀態端點；started_at 為 None 表示尚未啟動或已停止
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedulerStats {
+    pub jobs_registered: usize,
+    pub total_runs: u64,
+    pub total_errors: u64,
+    pub total_timeouts: u64,
+    pub started_at: Option<Instant>,
+}
+
+// 不論使用哪個 MetricsRecorder，Scheduler 本身都會維護的累計數字
+#[derive(Debug, Default)]
+pub(crate) struct StatsCounters {
+    runs: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    started_at: Mutex<Option<Instant>>,
+}
+
+impl StatsCounters {
+    pub(crate) fn record(&self, outcome: Outcome) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        match outcome {
+            Outcome::Success => {}
+            Outcome::Failure => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            Outcome::Timeout => {
+                self.timeouts.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub(crate) fn set_started(&self, started_at: Option<Instant>) {
+        *self.started_at.lock().unwrap() = started_at;
+    }
+
+    pub(crate) fn snapshot(&self, jobs_registered: usize) -> SchedulerStats {
+        SchedulerStats {
+            jobs_registered,
+            total_runs: self.runs.load(Ordering::Relaxed),
+            total_errors: self.errors.load(Ordering::Relaxed),
+            total_timeouts: self.timeouts.load(Ordering::Relaxed),
+            started_at: *self.started_at.lock().unwrap(),
+        }
+    }
+}
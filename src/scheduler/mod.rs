@@ -1,103 +1,1541 @@
+use crate::clock::{Clock, RealClock};
+use futures::FutureExt;
+use rand::Rng;
+use serde_json::Value;
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
 use std::pin::Pin;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
 use tokio_cron_scheduler::{Job, JobScheduler};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+mod builder;
+mod config;
+mod daily;
+mod definitions;
+mod events;
+mod leader;
+mod lock;
+mod metrics;
+
+use builder::RunOptions;
+pub use builder::{JobBuilder, JobRetryPolicy, JobSpec, Trigger};
+pub use config::{JobConfig, SchedulerConfig};
+pub use definitions::{JobDefinition, JobRegistry};
+use events::EventBus;
+pub use events::SchedulerEvent;
+pub use leader::{LeaderElector, LeaderScheduler, PgAdvisoryLockElector};
+pub use lock::{DistributedLock, PgAdvisoryLock};
+use metrics::StatsCounters;
+pub use metrics::{
+    InMemoryMetrics, JobMetrics, MetricsRecorder, NoopMetrics, Outcome, SchedulerStats,
+};
+
+#[derive(Debug)]
+pub enum SchedulerError {
+    InvalidCron { expr: String, reason: String },
+    DuplicateJob(String),
+    UnknownJob(String),
+    InvalidDefinitions(String),
+    JobNotFound(JobId),
+    // 設定檔中有問題的任務：(任務名稱, 原因)
+    InvalidJobs(Vec<(String, String)>),
+    // 間隔為 0 的 Trigger::Interval
+    InvalidInterval(Duration),
+    // add_daily_at 的時間超出 00:00 ~ 23:59
+    InvalidTime { hour: u32, minute: u32 },
+}
+
+impl fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedulerError::InvalidCron { expr, reason } => {
+                write!(f, "Invalid cron expression '{}': {}", expr, reason)
+            }
+            SchedulerError::DuplicateJob(name) => write!(f, "Job '{}' already exists", name),
+            SchedulerError::UnknownJob(name) => {
+                write!(f, "No callback registered for job '{}'", name)
+            }
+            SchedulerError::InvalidDefinitions(reason) => {
+                write!(f, "Invalid job definitions: {}", reason)
+            }
+            SchedulerError::JobNotFound(id) => write!(f, "No job with id {}", id),
+            SchedulerError::InvalidJobs(jobs) => {
+                let details: Vec<String> = jobs
+                    .iter()
+                    .map(|(name, reason)| format!("'{}': {}", name, reason))
+                    .collect();
+                write!(f, "Invalid jobs in config: {}", details.join("; "))
+            }
+            SchedulerError::InvalidInterval(interval) => {
+                write!(f, "Invalid job interval: {:?}", interval)
+            }
+            SchedulerError::InvalidTime { hour, minute } => {
+                write!(f, "Invalid time of day: {:02}:{:02}", hour, minute)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchedulerError {}
+
+// tokio-cron-scheduler 指派給每個任務的 id
+pub type JobId = uuid::Uuid;
 pub type JobFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
-pub type JobCallback = Box<dyn Fn() -> JobFuture + Send + Sync>;
+pub type JobCallback = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+pub struct CronJob {
+    pub cron_expr: String,
+    pub callback: JobCallback,
+}
+
+impl CronJob {
+    pub fn new<F>(cron_expr: impl Into<String>, callback: F) -> Self
+    where
+        F: Fn() -> JobFuture + Send + Sync + 'static,
+    {
+        Self {
+            cron_expr: cron_expr.into(),
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+pub struct Scheduler {
+    scheduler: JobScheduler,
+    is_running: Arc<AtomicBool>, // 新增狀態控制
+    events: EventBus,
+    // 具名任務的定義（名稱 -> cron 表達式），用於匯出 / 匯入
+    definitions: Mutex<BTreeMap<String, String>>,
+    // 以 JobBuilder::interval 註冊的具名任務，只用於檢查名稱重複
+    interval_jobs: Mutex<BTreeSet<String>>,
+    // 每個任務各自的暫停旗標，與全域的 is_running 互相獨立
+    paused: Mutex<HashMap<JobId, Arc<AtomicBool>>>,
+    metrics: Arc<dyn MetricsRecorder>,
+    stats: Arc<StatsCounters>,
+    cancellation: CancellationToken,
+    // 執行中的任務，取消後用來等待它們結束
+    in_flight: TaskTracker,
+    // 設定時改以此時間來源觸發任務，不經過 tokio-cron-scheduler
+    clock: Option<Arc<dyn Clock>>,
+    // add_daily_at 與 clock 觸發的任務各自的迴圈，取消或 drop 排程器時中止
+    drivers: Arc<Mutex<Vec<tokio::task::JoinHandle<()>>>>,
+}
+
+impl Scheduler {
+    pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::new_with_metrics(Arc::new(NoopMetrics)).await
+    }
+
+    // 每次任務執行結束後以 recorder 記錄耗時與成功 / 失敗
+    pub async fn new_with_metrics(
+        metrics: Arc<dyn MetricsRecorder>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let scheduler = JobScheduler::new().await?;
+        Ok(Self {
+            scheduler,
+            is_running: Arc::new(AtomicBool::new(false)),
+            events: EventBus::default(),
+            definitions: Mutex::new(BTreeMap::new()),
+            interval_jobs: Mutex::new(BTreeSet::new()),
+            paused: Mutex::new(HashMap::new()),
+            metrics,
+            stats: Arc::new(StatsCounters::default()),
+            cancellation: CancellationToken::new(),
+            in_flight: TaskTracker::new(),
+            clock: None,
+            drivers: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    // 以指定的時間來源觸發任務。測試中搭配 TokioClock 與 tokio::time::pause，
+    // 可以推進虛擬時間並斷言確切的觸發次數；未指定時（new）以系統時間由 tokio-cron-scheduler 觸發
+    pub async fn new_with_clock(clock: Arc<dyn Clock>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut scheduler = Self::new().await?;
+        scheduler.clock = Some(clock);
+        Ok(scheduler)
+    }
+
+    // 與應用程式共用的 CancellationToken：token 取消後不再開始新的任務，
+    // 等待執行中的任務結束後自動關閉排程器，不需要另外呼叫 stop
+    pub async fn new_with_cancellation(
+        token: CancellationToken,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut scheduler = Self::new().await?;
+        scheduler.cancellation = token.clone();
+
+        let is_running = scheduler.is_running.clone();
+        let stats = scheduler.stats.clone();
+        let in_flight = scheduler.in_flight.clone();
+        let drivers = scheduler.drivers.clone();
+        let mut job_scheduler = scheduler.scheduler.clone();
+        tokio::spawn(async move {
+            token.cancelled().await;
+            is_running.store(false, Ordering::SeqCst);
+            stats.set_started(None);
+            for driver in drivers.lock().unwrap().drain(..) {
+                driver.abort();
+            }
+            in_flight.close();
+            in_flight.wait().await;
+            if let Err(e) = job_scheduler.shutdown().await {
+                tracing::error!("取消後關閉排程器失敗：{}", e);
+            }
+        });
+        Ok(scheduler)
+    }
+
+    // 從 JSON / TOML 設定檔建立排程器，callback 依任務名稱從 callbacks 取得
+    // 會先檢查所有啟用中的任務，任何一個 cron 無效或缺少 callback 就回傳列出所有問題任務的錯誤
+    pub async fn from_config(
+        path: impl AsRef<Path>,
+        callbacks: &HashMap<String, JobCallback>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = SchedulerConfig::load(path)?;
+        let jobs: Vec<JobConfig> = config.jobs.into_iter().filter(|job| job.enabled).collect();
+
+        let mut problems = Vec::new();
+        for job in &jobs {
+            if let Err(e) = cron::Schedule::from_str(&job.cron) {
+                problems.push((
+                    job.name.clone(),
+                    format!("invalid cron expression '{}': {}", job.cron, e),
+                ));
+            } else if !callbacks.contains_key(&job.name) {
+                problems.push((job.name.clone(), "no callback registered".to_string()));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(Box::new(SchedulerError::InvalidJobs(problems)));
+        }
+
+        let scheduler = Self::new().await?;
+        for job in jobs {
+            let callback = callbacks[&job.name].clone();
+            scheduler
+                .add_named_task(&job.name, &job.cron, move || callback())
+                .await?;
+        }
+        Ok(scheduler)
+    }
+
+    // 一般訂閱：訂閱者跟不上時會遺失較舊的事件（recv 回傳 Lagged），不影響任務執行
+    pub fn subscribe(&self) -> broadcast::Receiver<SchedulerEvent> {
+        self.events.subscribe()
+    }
+
+    // 不遺失事件的訂閱：佇列滿時任務會等到訂閱者消化完才繼續，適合需要完整記錄 Failed 的監控端
+    // 注意：訂閱者太慢會拖慢任務，收到的 Receiver 不再使用時應 drop 掉
+    pub fn subscribe_lossless(&self, capacity: usize) -> mpsc::Receiver<SchedulerEvent> {
+        self.events.subscribe_lossless(capacity)
+    }
+
+    pub async fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.is_running.store(true, Ordering::SeqCst);
+        if self.clock.is_none() {
+            self.scheduler.start().await?;
+        }
+        self.stats.set_started(Some(Instant::now()));
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.is_running.store(false, Ordering::SeqCst);
+        self.stats.set_started(None);
+        // 等待一小段時間確保所有任務都看到停止信號
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // drivers 保留下來，停止期間的觸發由 is_running 略過，再次 start 後照常執行
+        self.scheduler.shutdown().await?;
+        Ok(())
+    }
+
+    pub async fn add_task<F, Fut>(
+        &self,
+        cron_expr: &str,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.schedule(cron_expr.to_string(), cron_expr, task).await
+    }
+
+    // 多副本部署時只讓取得 lock 的實例執行任務，其他實例在同一次觸發時略過
+    pub async fn add_task_exclusive<F, Fut>(
+        &self,
+        name: &str,
+        cron_expr: &str,
+        lock: Arc<dyn DistributedLock>,
+        ttl: Duration,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let job = name.to_string();
+        self.add_named_task(name, cron_expr, move || {
+            let lock = lock.clone();
+            let task = task.clone();
+            let job = job.clone();
+            async move {
+                if lock.try_acquire(&job, ttl).await {
+                    task().await;
+                } else {
+                    tracing::debug!("任務 {} 已由其他實例執行，略過本次觸發", job);
+                }
+            }
+        })
+        .await
+    }
+
+    // 每次觸發先等待 [0, jitter) 的隨機時間再執行，避免大量共用同一 cron 的任務同時打向外部服務。
+    // jitter 必須小於觸發間隔，否則延遲後的執行可能與下一次觸發重疊；
+    // 事件與 metrics 記錄的執行時間會包含這段延遲
+    pub async fn add_task_with_jitter<F, Fut>(
+        &self,
+        cron_expr: &str,
+        jitter: Duration,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let options = RunOptions {
+            jitter: Some(jitter),
+            ..Default::default()
+        };
+        self.schedule_with(
+            cron_expr.to_string(),
+            cron_trigger(cron_expr),
+            options,
+            task,
+        )
+        .await
+    }
+
+    // 每次執行最多 timeout，超過時中止該次執行（drop 掉 future），記錄為 Outcome::Timeout
+    // 並發出 TimedOut 事件，下一次觸發照常執行。
+    // 中止只發生在 await 點，卡在同步程式碼中的任務無法被中止
+    pub async fn add_task_with_timeout<F, Fut>(
+        &self,
+        cron_expr: &str,
+        timeout: Duration,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let options = RunOptions {
+            timeout: Some(timeout),
+            ..Default::default()
+        };
+        self.schedule_with(
+            cron_expr.to_string(),
+            cron_trigger(cron_expr),
+            options,
+            task,
+        )
+        .await
+    }
+
+    // 每天在 tz 的當地時間 hour:minute 執行一次。每次觸發後才依當地日曆計算下一次的 UTC 時刻，
+    // 而不是轉成固定的 cron，因此日光節約時間切換前後都會落在正確的當地時間：
+    // - 當天不存在該時間（春季撥快，例如 02:30 被跳過）時，改在跳過時段後第一個存在的時間執行（03:00）
+    // - 當天出現兩次（秋季撥慢）時，只在第一次出現時執行，不會重複
+    // 任務名稱為 "daily at HH:MM"
+    pub async fn add_daily_at<Tz, F, Fut>(
+        &self,
+        hour: u32,
+        minute: u32,
+        tz: Tz,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        Tz: chrono::TimeZone + Send + Sync + 'static,
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        if hour >= 24 || minute >= 60 {
+            return Err(Box::new(SchedulerError::InvalidTime { hour, minute }));
+        }
+        let job_name = format!("daily at {:02}:{:02}", hour, minute);
+        let (paused, run) = self.job_runner(job_name, RunOptions::default(), task);
+        let clock = self.clock.clone().unwrap_or_else(|| Arc::new(RealClock));
+        let driver = tokio::spawn(async move {
+            let mut last = clock.now();
+            loop {
+                // 時鐘若略早於上次的觸發時刻醒來，仍以上次觸發時刻為基準，避免同一天執行兩次
+                let now = clock.now().max(last);
+                let Some(next) = daily::next_daily_at(now, hour, minute, &tz) else {
+                    break;
+                };
+                clock.sleep((next - now).to_std().unwrap_or_default()).await;
+                tokio::spawn(run());
+                last = next;
+            }
+        });
+        self.drivers.lock().unwrap().push(driver);
+        let id = uuid::Uuid::new_v4();
+        self.paused.lock().unwrap().insert(id, paused);
+        Ok(id)
+    }
+
+    // 註冊以 JobBuilder 組出的任務。未命名的任務以 cron 表達式或間隔作為 metrics / 事件中的名稱；
+    // 具名的 interval 任務同樣不可重名，但不會出現在 export_definitions 中（定義只能描述 cron）
+    pub async fn add_job(&self, spec: JobSpec) -> Result<JobId, Box<dyn std::error::Error>> {
+        let JobSpec {
+            trigger,
+            name,
+            options,
+            callback,
+        } = spec;
+        if let Some(name) = &name {
+            let cron_expr = match &trigger {
+                Trigger::Cron(expr) => Some(expr.as_str()),
+                Trigger::Interval(_) => None,
+            };
+            self.reserve_name(name, cron_expr)?;
+        }
+
+        let job_name = name.clone().unwrap_or_else(|| trigger.to_string());
+        let result = self
+            .schedule_with(job_name, trigger, options, move || callback())
+            .await;
+        if let (Err(_), Some(name)) = (&result, &name) {
+            self.release_name(name);
+        }
+        result
+    }
+
+    // 在 await 排程之前先佔用名稱，同時註冊同名任務時只有一個會成功；排程失敗時以 release_name 釋放
+    fn reserve_name(&self, name: &str, cron_expr: Option<&str>) -> Result<(), SchedulerError> {
+        let mut definitions = self.definitions.lock().unwrap();
+        let mut interval_jobs = self.interval_jobs.lock().unwrap();
+        if definitions.contains_key(name) || interval_jobs.contains(name) {
+            return Err(SchedulerError::DuplicateJob(name.to_string()));
+        }
+        match cron_expr {
+            Some(cron_expr) => {
+                definitions.insert(name.to_string(), cron_expr.to_string());
+            }
+            None => {
+                interval_jobs.insert(name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn release_name(&self, name: &str) {
+        self.definitions.lock().unwrap().remove(name);
+        self.interval_jobs.lock().unwrap().remove(name);
+    }
+
+    // 同步或 CPU 密集的任務（例如呼叫同步函式庫、壓縮檔案）請用這個方法：
+    // task 會在 spawn_blocking 的執行緒中執行，不會卡住 async runtime；
+    // 只有 await I/O 的一般任務仍使用 add_task 即可
+    pub async fn add_blocking_task<F>(
+        &self,
+        cron_expr: &str,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let task = Arc::new(task);
+        self.add_task(cron_expr, move || {
+            let task = task.clone();
+            async move {
+                if let Err(e) = tokio::task::spawn_blocking(move || task()).await {
+                    // 把 panic 交回 schedule，讓它照常發出 Failed 事件
+                    if e.is_panic() {
+                        std::panic::resume_unwind(e.into_panic());
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    pub async fn add_cron_job(&self, job: CronJob) -> Result<JobId, Box<dyn std::error::Error>> {
+        let callback = job.callback;
+        self.schedule(job.cron_expr.clone(), &job.cron_expr, move || callback())
+            .await
+    }
+
+    // 一次註冊多個 CronJob；會先檢查所有 cron 表達式，有任何一個無效就不註冊任何任務
+    pub async fn register_jobs(
+        &self,
+        jobs: Vec<CronJob>,
+    ) -> Result<Vec<JobId>, Box<dyn std::error::Error>> {
+        for job in &jobs {
+            cron::Schedule::from_str(&job.cron_expr).map_err(|e| SchedulerError::InvalidCron {
+                expr: job.cron_expr.clone(),
+                reason: e.to_string(),
+            })?;
+        }
+
+        let mut ids = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            ids.push(self.add_cron_job(job).await?);
+        }
+        Ok(ids)
+    }
+
+    // 加入具名任務，具名任務會出現在 export_definitions 的結果中
+    pub async fn add_named_task<F, Fut>(
+        &self,
+        name: &str,
+        cron_expr: &str,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.reserve_name(name, Some(cron_expr))?;
+        let result = self.schedule(name.to_string(), cron_expr, task).await;
+        if result.is_err() {
+            self.release_name(name);
+        }
+        result
+    }
+
+    // 暫停的任務仍保留排程，只是觸發時直接略過，resume_task 後恢復執行
+    pub fn pause_task(&self, id: JobId) -> Result<(), SchedulerError> {
+        self.set_paused(id, true)
+    }
+
+    pub fn resume_task(&self, id: JobId) -> Result<(), SchedulerError> {
+        self.set_paused(id, false)
+    }
+
+    // 已註冊的任務數與啟動以來（含先前的啟動）所有任務的累計執行 / 失敗次數
+    pub fn stats(&self) -> SchedulerStats {
+        self.stats.snapshot(self.paused.lock().unwrap().len())
+    }
+
+    pub fn is_paused(&self, id: JobId) -> bool {
+        self.paused
+            .lock()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|paused| paused.load(Ordering::SeqCst))
+    }
+
+    fn set_paused(&self, id: JobId, paused: bool) -> Result<(), SchedulerError> {
+        let flags = self.paused.lock().unwrap();
+        let flag = flags.get(&id).ok_or(SchedulerError::JobNotFound(id))?;
+        flag.store(paused, Ordering::SeqCst);
+        Ok(())
+    }
+
+    // 匯出所有具名任務的定義，格式為 [{"name": ..., "cron_expr": ...}]，依名稱排序
+    pub fn export_definitions(&self) -> Value {
+        serde_json::to_value(self.definitions()).unwrap_or_default()
+    }
+
+    pub fn definitions(&self) -> Vec<JobDefinition> {
+        self.definitions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, cron_expr)| JobDefinition {
+                name: name.clone(),
+                cron_expr: cron_expr.clone(),
+            })
+            .collect()
+    }
+
+    // 匯入任務定義並依名稱從 registry 綁定 callback；會先檢查全部定義，有任何錯誤就不加入任何任務
+    pub async fn import_definitions(
+        &self,
+        value: Value,
+        registry: &JobRegistry,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let definitions: Vec<JobDefinition> = serde_json::from_value(value)
+            .map_err(|e| SchedulerError::InvalidDefinitions(e.to_string()))?;
+
+        let mut jobs = Vec::with_capacity(definitions.len());
+        for definition in definitions {
+            cron::Schedule::from_str(&definition.cron_expr).map_err(|e| {
+                SchedulerError::InvalidCron {
+                    expr: definition.cron_expr.clone(),
+                    reason: e.to_string(),
+                }
+            })?;
+            let callback = registry
+                .get(&definition.name)
+                .ok_or_else(|| SchedulerError::UnknownJob(definition.name.clone()))?;
+            jobs.push((definition, callback));
+        }
+
+        for (definition, callback) in jobs {
+            self.add_named_task(&definition.name, &definition.cron_expr, move || callback())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn schedule<F, Fut>(
+        &self,
+        job_name: String,
+        cron_expr: &str,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.schedule_with(
+            job_name,
+            cron_trigger(cron_expr),
+            RunOptions::default(),
+            task,
+        )
+        .await
+    }
+
+    async fn schedule_with<F, Fut>(
+        &self,
+        job_name: String,
+        trigger: Trigger,
+        options: RunOptions,
+        task: F,
+    ) -> Result<JobId, Box<dyn std::error::Error>>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        if trigger == Trigger::Interval(Duration::ZERO) {
+            return Err(Box::new(SchedulerError::InvalidInterval(Duration::ZERO)));
+        }
+        let (paused, run) = self.job_runner(job_name, options, task);
+
+        let id = match (&self.clock, trigger) {
+            (Some(clock), trigger) => self.spawn_clock_driver(&trigger, clock.clone(), run)?,
+            (None, Trigger::Cron(expr)) => {
+                let job = Job::new_async(expr.as_str(), move |_, _| run())?;
+                self.scheduler.add(job).await?
+            }
+            (None, Trigger::Interval(interval)) => {
+                let job = Job::new_repeated_async(interval, move |_, _| run())?;
+                self.scheduler.add(job).await?
+            }
+        };
+        self.paused.lock().unwrap().insert(id, paused);
+        Ok(id)
+    }
+
+    // 包裝 task 成每次觸發時執行的 closure（狀態檢查、事件、metrics、逾時與重試），
+    // 連同該任務的暫停旗標一起回傳，由呼叫端決定如何觸發
+    fn job_runner<F, Fut>(
+        &self,
+        job_name: String,
+        options: RunOptions,
+        task: F,
+    ) -> (
+        Arc<AtomicBool>,
+        impl Fn() -> JobFuture + Send + Sync + 'static,
+    )
+    where
+        F: Fn() -> Fut + Send + Sync + 'static + Clone,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let is_running = self.is_running.clone();
+        let paused = Arc::new(AtomicBool::new(false));
+        let events = self.events.clone();
+        let metrics = self.metrics.clone();
+        let stats = self.stats.clone();
+        let cancellation = self.cancellation.clone();
+        let in_flight = self.in_flight.clone();
+        let options = Arc::new(options);
+        let executing = Arc::new(AtomicBool::new(false));
+
+        let job_paused = paused.clone();
+        let run = move || -> JobFuture {
+            let is_running = is_running.clone();
+            let paused = job_paused.clone();
+            let task = task.clone(); // 如果 F 不能 clone，需要用 Arc 包裝
+            let events = events.clone();
+            let metrics = metrics.clone();
+            let stats = stats.clone();
+            let job = job_name.clone();
+            let cancellation = cancellation.clone();
+            let in_flight = in_flight.clone();
+            let options = options.clone();
+            let executing = executing.clone();
+            Box::pin(async move {
+                // 先登記再檢查取消，確保取消時等待的清單不會漏掉剛通過檢查的任務
+                let _in_flight = in_flight.token();
+                if !is_running.load(Ordering::SeqCst)
+                    || paused.load(Ordering::SeqCst)
+                    || cancellation.is_cancelled()
+                {
+                    return;
+                }
+                let _executing = if options.no_overlap {
+                    if executing.swap(true, Ordering::SeqCst) {
+                        tracing::debug!("任務 {} 上一次執行尚未結束，略過本次觸發", job);
+                        return;
+                    }
+                    Some(ExecutingGuard(executing))
+                } else {
+                    None
+                };
+                events
+                    .emit(SchedulerEvent::Started { job: job.clone() })
+                    .await;
+                let started_at = Instant::now();
+                if let Some(jitter) = options.jitter {
+                    tokio::time::sleep(jitter_delay(jitter)).await;
+                }
+                let mut attempt = 0;
+                let result = loop {
+                    // 在這裡攔下 panic，避免它穿過 job future 讓排程器的任務被中止而沒有任何紀錄
+                    let execution = AssertUnwindSafe(task()).catch_unwind();
+                    let result = match options.timeout {
+                        Some(limit) => tokio::time::timeout(limit, execution).await,
+                        None => Ok(execution.await),
+                    };
+                    match &options.retry {
+                        Some(policy)
+                            if attempt < policy.max_retries && !matches!(result, Ok(Ok(()))) =>
+                        {
+                            let delay = policy.delay_for(attempt);
+                            attempt += 1;
+                            tracing::warn!(
+                                "任務 {} 執行失敗，{:?} 後進行第 {} 次重試",
+                                job,
+                                delay,
+                                attempt
+                            );
+                            tokio::time::sleep(delay).await;
+                        }
+                        _ => break result,
+                    }
+                };
+                let duration = started_at.elapsed();
+                match result {
+                    Ok(Ok(())) => {
+                        stats.record(Outcome::Success);
+                        metrics.record_run(&job, duration, Outcome::Success);
+                        events
+                            .emit(SchedulerEvent::Completed { job, duration })
+                            .await
+                    }
+                    Ok(Err(panic)) => {
+                        let error = panic_message(panic.as_ref());
+                        tracing::error!("任務 {} 執行時 panic：{}", job, error);
+                        stats.record(Outcome::Failure);
+                        metrics.record_run(&job, duration, Outcome::Failure);
+                        events.emit(SchedulerEvent::Failed { job, error }).await
+                    }
+                    Err(_) => {
+                        let timeout = options.timeout.unwrap_or_default();
+                        tracing::warn!("任務 {} 執行超過 {:?}，已中止", job, timeout);
+                        stats.record(Outcome::Timeout);
+                        metrics.record_run(&job, duration, Outcome::Timeout);
+                        events.emit(SchedulerEvent::TimedOut { job, timeout }).await
+                    }
+                }
+            })
+        };
+        (paused, run)
+    }
+
+    // 依 clock 計算下一次觸發並等待，取代 tokio-cron-scheduler 以系統時間觸發的做法；
+    // 是否執行仍由 run 內的 is_running / 暫停 / 取消檢查決定
+    fn spawn_clock_driver(
+        &self,
+        trigger: &Trigger,
+        clock: Arc<dyn Clock>,
+        run: impl Fn() -> JobFuture + Send + Sync + 'static,
+    ) -> Result<JobId, SchedulerError> {
+        let driver = match trigger {
+            Trigger::Cron(expr) => {
+                let schedule =
+                    cron::Schedule::from_str(expr).map_err(|e| SchedulerError::InvalidCron {
+                        expr: expr.clone(),
+                        reason: e.to_string(),
+                    })?;
+                tokio::spawn(async move {
+                    loop {
+                        let now = clock.now();
+                        let Some(next) = schedule.after(&now).next() else {
+                            break;
+                        };
+                        clock.sleep((next - now).to_std().unwrap_or_default()).await;
+                        tokio::spawn(run());
+                    }
+                })
+            }
+            Trigger::Interval(interval) => {
+                let interval = *interval;
+                tokio::spawn(async move {
+                    loop {
+                        clock.sleep(interval).await;
+                        tokio::spawn(run());
+                    }
+                })
+            }
+        };
+        self.drivers.lock().unwrap().push(driver);
+        Ok(uuid::Uuid::new_v4())
+    }
+}
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        for driver in self.drivers.lock().unwrap().drain(..) {
+            driver.abort();
+        }
+    }
+}
+
+fn cron_trigger(cron_expr: &str) -> Trigger {
+    Trigger::Cron(cron_expr.to_string())
+}
+
+// no_overlap 任務執行期間持有，結束（含逾時或停止時被 drop）後清除旗標
+struct ExecutingGuard(Arc<AtomicBool>);
+
+impl Drop for ExecutingGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+fn jitter_delay(jitter: Duration) -> Duration {
+    if jitter.is_zero() {
+        return Duration::ZERO;
+    }
+    let nanos = u64::try_from(jitter.as_nanos()).unwrap_or(u64::MAX);
+    Duration::from_nanos(rand::thread_rng().gen_range(0..nanos))
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "task panicked".to_string()
+    }
+}
+
+// 將 cron 表達式（秒 分 時 日 月 週 [年]）轉換成易讀的英文描述，例如 "every day at 09:00"
+pub fn describe_cron(expr: &str) -> Result<String, SchedulerError> {
+    cron::Schedule::from_str(expr).map_err(|e| SchedulerError::InvalidCron {
+        expr: expr.to_string(),
+        reason: e.to_string(),
+    })?;
+
+    // cron 也接受 @daily 等簡寫，對應的欄位與 cron crate 的展開一致
+    match expr.trim() {
+        "@yearly" => return Ok("every year on January 1 at 00:00".to_string()),
+        "@monthly" => return Ok("every month on day 1 at 00:00".to_string()),
+        "@weekly" => return Ok("every week on Sunday at 00:00".to_string()),
+        "@daily" => return Ok("every day at 00:00".to_string()),
+        "@hourly" => return Ok("every hour".to_string()),
+        _ => {}
+    }
+
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    if fields.len() < 6 {
+        return Err(SchedulerError::InvalidCron {
+            expr: expr.to_string(),
+            reason: format!("expected at least 6 fields, got {}", fields.len()),
+        });
+    }
+    let (sec, min, hour, dom, month, dow) = (
+        fields[0], fields[1], fields[2], fields[3], fields[4], fields[5],
+    );
+
+    let every_day = is_any(dom) && is_any(month) && is_any(dow);
+    let time = describe_time(sec, min, hour);
+    let mut parts = Vec::new();
+    match time {
+        TimeDescription::At(at) if every_day => parts.push(format!("every day at {}", at)),
+        TimeDescription::At(at) => parts.push(format!("at {}", at)),
+        TimeDescription::Every(every) => parts.push(every),
+    }
+    if !is_any(dow) {
+        parts.push(format!("on {}", describe_list(dow, day_of_week_name)));
+    }
+    if !is_any(dom) {
+        parts.push(format!("on day {} of the month", dom));
+    }
+    if !is_any(month) {
+        parts.push(format!("in {}", describe_list(month, month_name)));
+    }
+    if let Some(year) = fields.get(6).filter(|y| !is_any(y)) {
+        parts.push(format!("in {}", year));
+    }
+
+    Ok(parts.join(" "))
+}
+
+enum TimeDescription {
+    At(String),
+    Every(String),
+}
+
+fn describe_time(sec: &str, min: &str, hour: &str) -> TimeDescription {
+    let (s, m, h) = (sec.parse::<u32>(), min.parse::<u32>(), hour.parse::<u32>());
+    if let (Ok(s), Ok(m), Ok(h)) = (&s, &m, &h) {
+        return if *s == 0 {
+            TimeDescription::At(format!("{:02}:{:02}", h, m))
+        } else {
+            TimeDescription::At(format!("{:02}:{:02}:{:02}", h, m, s))
+        };
+    }
+
+    let description = match (sec, min, hour) {
+        ("*", "*", "*") => "every second".to_string(),
+        (_, "*", "*") if step(sec).is_some() => format!("every {} seconds", step(sec).unwrap()),
+        ("0", "*", "*") => "every minute".to_string(),
+        (_, _, "*") if s.is_ok() && step(min).is_some() => {
+            format!("every {} minutes", step(min).unwrap())
+        }
+        ("0", "0", "*") => "every hour".to_string(),
+        (_, _, "*") if s.is_ok() && m.is_ok() => {
+            format!("every hour at minute {}", m.unwrap())
+        }
+        ("0", "0", _) if step(hour).is_some() => format!("every {} hours", step(hour).unwrap()),
+        _ => format!("at second {}, minute {}, hour {}", sec, min, hour),
+    };
+    TimeDescription::Every(description)
+}
+
+fn is_any(field: &str) -> bool {
+    field == "*" || field == "?"
+}
+
+fn step(field: &str) -> Option<u32> {
+    field.strip_prefix("*/").and_then(|n| n.parse().ok())
+}
+
+fn describe_list(field: &str, name: fn(&str) -> String) -> String {
+    field
+        .split(',')
+        .map(|item| match item.split_once('-') {
+            Some((from, to)) => format!("{} through {}", name(from), name(to)),
+            None => name(item),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn day_of_week_name(day: &str) -> String {
+    const NAMES: [&str; 7] = [
+        "Sunday",
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+    ];
+    // cron crate 以 1 代表星期日
+    if let Ok(n) = day.parse::<usize>() {
+        return NAMES.get(n.wrapping_sub(1)).unwrap_or(&day).to_string();
+    }
+    NAMES
+        .iter()
+        .find(|name| name[..3].eq_ignore_ascii_case(&day[..day.len().min(3)]))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| day.to_string())
+}
+
+fn month_name(month: &str) -> String {
+    const NAMES: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+    if let Ok(n) = month.parse::<usize>() {
+        return NAMES.get(n.wrapping_sub(1)).unwrap_or(&month).to_string();
+    }
+    NAMES
+        .iter()
+        .find(|name| name[..3].eq_ignore_ascii_case(&month[..month.len().min(3)]))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| month.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TokioClock;
+    use crate::test_support::setup_test_db;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    // 測試建立排程器
+    #[tokio::test]
+    async fn test_scheduler_creation() {
+        let scheduler = Scheduler::new().await;
+        assert!(scheduler.is_ok(), "應該能夠成功建立排程器");
+    }
+
+    // 測試任務 panic 時 lossless 訂閱者會收到 Failed
+    #[tokio::test]
+    async fn test_failed_event_delivered_to_lossless_subscriber() {
+        let mut scheduler = Scheduler::new().await.unwrap();
+        let mut events = scheduler.subscribe_lossless(1);
+
+        scheduler
+            .add_task("* * * * * *", || async {
+                panic!("任務失敗");
+            })
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        let first = tokio::time::timeout(Duration::from_secs(3), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        // 故意慢一點才讀下一個事件，確保 Failed 不會因此遺失
+        sleep(Duration::from_millis(300)).await;
+        let second = tokio::time::timeout(Duration::from_secs(3), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        scheduler.stop().await.unwrap();
+
+        assert!(matches!(first, SchedulerEvent::Started { .. }));
+        assert!(matches!(second, SchedulerEvent::Failed { .. }));
+    }
+
+    // 測試暫停後計數停止增加，恢復後繼續增加
+    #[tokio::test]
+    async fn test_pause_and_resume_task() {
+        let mut scheduler = Scheduler::new().await.unwrap();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counter_clone = counter.clone();
+        let id = scheduler
+            .add_task("* * * * * *", move || {
+                let counter = counter_clone.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_millis(2100)).await;
+        assert!(counter.load(Ordering::SeqCst) > 0, "任務應該已經執行過");
+
+        scheduler.pause_task(id).unwrap();
+        assert!(scheduler.is_paused(id));
+        // 等待已觸發的執行結束後再記錄
+        sleep(Duration::from_millis(200)).await;
+        let frozen = counter.load(Ordering::SeqCst);
+        sleep(Duration::from_millis(2100)).await;
+        assert_eq!(counter.load(Ordering::SeqCst), frozen, "暫停期間不應執行");
+
+        scheduler.resume_task(id).unwrap();
+        assert!(!scheduler.is_paused(id));
+        sleep(Duration::from_millis(2100)).await;
+        scheduler.stop().await.unwrap();
+        assert!(counter.load(Ordering::SeqCst) > frozen, "恢復後應繼續執行");
+
+        assert!(matches!(
+            scheduler.pause_task(JobId::new_v4()),
+            Err(SchedulerError::JobNotFound(_))
+        ));
+    }
+
+    // 測試執行次數與耗時會記錄到 MetricsRecorder
+    #[tokio::test]
+    async fn test_metrics_recorded_for_each_run() {
+        let metrics = Arc::new(InMemoryMetrics::new());
+        let mut scheduler = Scheduler::new_with_metrics(metrics.clone()).await.unwrap();
+        scheduler
+            .add_named_task("tick", "* * * * * *", || async {
+                sleep(Duration::from_millis(10)).await;
+            })
+            .await
+            .unwrap();
+        scheduler
+            .add_named_task("boom", "* * * * * *", || async {
+                panic!("任務失敗");
+            })
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_millis(3100)).await;
+        scheduler.stop().await.unwrap();
+
+        let tick = metrics.get("tick").expect("應該有 tick 的統計");
+        assert!(tick.runs >= 2, "實際執行次數: {}", tick.runs);
+        assert_eq!(tick.successes, tick.runs);
+        assert_eq!(tick.failures, 0);
+        assert!(tick.last_duration.unwrap() >= Duration::from_millis(10));
+        assert!(tick.total_duration >= Duration::from_millis(10) * tick.runs as u32);
+
+        let boom = metrics.get("boom").expect("應該有 boom 的統計");
+        assert!(boom.runs >= 2);
+        assert_eq!(boom.failures, boom.runs);
+    }
+
+    // 測試任務定義經過 JSON 匯出再匯入後保持一致
+    #[tokio::test]
+    async fn test_definitions_round_trip_through_json() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut registry = JobRegistry::new();
+        for name in ["cleanup", "report"] {
+            let counter = counter.clone();
+            registry.register(name, move || {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+
+        let original = Scheduler::new().await.unwrap();
+        original
+            .add_named_task("report", "0 0 9 * * Mon", || async {})
+            .await
+            .unwrap();
+        original
+            .add_named_task("cleanup", "* * * * * *", || async {})
+            .await
+            .unwrap();
+        // 未具名的任務無法重新綁定，不會被匯出
+        original.add_task("0 0 * * * *", || async {}).await.unwrap();
+
+        let json = serde_json::to_string(&original.export_definitions()).unwrap();
+        let mut reloaded = Scheduler::new().await.unwrap();
+        reloaded
+            .import_definitions(serde_json::from_str(&json).unwrap(), &registry)
+            .await
+            .unwrap();
+
+        assert_eq!(reloaded.definitions(), original.definitions());
+        assert_eq!(
+            reloaded.definitions(),
+            vec![
+                JobDefinition {
+                    name: "cleanup".to_string(),
+                    cron_expr: "* * * * * *".to_string(),
+                },
+                JobDefinition {
+                    name: "report".to_string(),
+                    cron_expr: "0 0 9 * * Mon".to_string(),
+                },
+            ]
+        );
+
+        // 重新綁定的 callback 確實會被執行
+        reloaded.start().await.unwrap();
+        sleep(Duration::from_millis(1500)).await;
+        reloaded.stop().await.unwrap();
+        assert!(counter.load(Ordering::SeqCst) > 0);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_unknown_job() {
+        let scheduler = Scheduler::new().await.unwrap();
+        let definitions = serde_json::json!([{ "name": "missing", "cron_expr": "* * * * * *" }]);
+
+        let err = scheduler
+            .import_definitions(definitions, &JobRegistry::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "No callback registered for job 'missing'");
+        assert!(scheduler.definitions().is_empty());
+    }
+
+    fn noop_callbacks(names: &[&str]) -> HashMap<String, JobCallback> {
+        names
+            .iter()
+            .map(|name| {
+                let callback: JobCallback = Arc::new(|| Box::pin(async {}) as JobFuture);
+                (name.to_string(), callback)
+            })
+            .collect()
+    }
+
+    // 測試從 TOML 設定檔載入兩個任務，停用的任務不會註冊
+    #[tokio::test]
+    async fn test_from_config_registers_jobs() {
+        let dir = std::env::temp_dir().join(format!("scheduler-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("jobs.toml");
+        std::fs::write(
+            &path,
+            r#"
+[[jobs]]
+name = "cleanup"
+cron = "0 0 3 * * *"
+
+[[jobs]]
+name = "report"
+cron = "0 0 9 * * Mon"
+enabled = true
+
+[[jobs]]
+name = "disabled"
+cron = "not a cron"
+enabled = false
+"#,
+        )
+        .unwrap();
+
+        let scheduler = Scheduler::from_config(&path, &noop_callbacks(&["cleanup", "report"]))
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            scheduler.definitions(),
+            vec![
+                JobDefinition {
+                    name: "cleanup".to_string(),
+                    cron_expr: "0 0 3 * * *".to_string(),
+                },
+                JobDefinition {
+                    name: "report".to_string(),
+                    cron_expr: "0 0 9 * * Mon".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_from_config_reports_invalid_jobs_by_name() {
+        let path = std::env::temp_dir().join(format!("scheduler-{}.json", uuid::Uuid::new_v4()));
+        let config = serde_json::json!({
+            "jobs": [
+                { "name": "ok", "cron": "* * * * * *" },
+                { "name": "broken", "cron": "every minute" },
+                { "name": "orphan", "cron": "* * * * * *" }
+            ]
+        });
+        std::fs::write(&path, config.to_string()).unwrap();
+
+        let err = Scheduler::from_config(&path, &noop_callbacks(&["ok", "broken"]))
+            .await
+            .err()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let message = err.to_string();
+        assert!(message.contains("'broken': invalid cron expression 'every minute'"));
+        assert!(message.contains("'orphan': no callback registered"));
+        assert!(!message.contains("'ok'"));
+    }
+
+    // 測試以 CronJob 清單一次註冊多個任務
+    #[tokio::test]
+    async fn test_register_cron_jobs() {
+        let first = Arc::new(AtomicUsize::new(0));
+        let second = Arc::new(AtomicUsize::new(0));
+        let counting_job = |counter: Arc<AtomicUsize>| {
+            CronJob::new("* * * * * *", move || {
+                let counter = counter.clone();
+                Box::pin(async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }) as JobFuture
+            })
+        };
+
+        let mut scheduler = Scheduler::new().await.unwrap();
+        let ids = scheduler
+            .register_jobs(vec![
+                counting_job(first.clone()),
+                counting_job(second.clone()),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
 
-pub struct CronJob {
-    pub cron_expr: String,
-    pub callback: JobCallback,
-}
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_millis(1500)).await;
+        scheduler.stop().await.unwrap();
 
-impl CronJob {
-    pub fn new<F>(cron_expr: impl Into<String>, callback: F) -> Self
-    where
-        F: Fn() -> JobFuture + Send + Sync + 'static,
-    {
-        Self {
-            cron_expr: cron_expr.into(),
-            callback: Box::new(callback),
-        }
+        assert!(first.load(Ordering::SeqCst) > 0, "第一個任務應該執行");
+        assert!(second.load(Ordering::SeqCst) > 0, "第二個任務應該執行");
     }
-}
 
-pub struct Scheduler {
-    scheduler: JobScheduler,
-    is_running: Arc<AtomicBool>, // 新增狀態控制
-}
+    // 測試兩個共用 Postgres advisory lock 的排程器，每次觸發只有一個實例執行
+    #[tokio::test]
+    async fn test_exclusive_task_runs_once_per_tick() {
+        let job = format!("exclusive-{}", uuid::Uuid::new_v4());
+        let runs = Arc::new(Mutex::new(Vec::new()));
 
-impl Scheduler {
-    pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let scheduler = JobScheduler::new().await?;
-        Ok(Self {
-            scheduler,
-            is_running: Arc::new(AtomicBool::new(false)),
-        })
+        let mut replicas = Vec::new();
+        for replica in ["replica-a", "replica-b"] {
+            // 每個副本使用各自的連線池，模擬不同的服務實例
+            let pool = setup_test_db(10).await;
+            let lock: Arc<dyn DistributedLock> = Arc::new(PgAdvisoryLock::new(pool));
+            let scheduler = Scheduler::new().await.unwrap();
+            let runs = runs.clone();
+            scheduler
+                .add_task_exclusive(
+                    &job,
+                    "* * * * * *",
+                    lock,
+                    Duration::from_millis(700),
+                    move || {
+                        let runs = runs.clone();
+                        async move {
+                            runs.lock()
+                                .unwrap()
+                                .push((chrono::Utc::now().timestamp(), replica));
+                        }
+                    },
+                )
+                .await
+                .unwrap();
+            replicas.push(scheduler);
+        }
+
+        for scheduler in replicas.iter_mut() {
+            scheduler.start().await.unwrap();
+        }
+        sleep(Duration::from_millis(3500)).await;
+        for scheduler in replicas.iter_mut() {
+            scheduler.stop().await.unwrap();
+        }
+
+        let runs = runs.lock().unwrap();
+        assert!(runs.len() >= 2, "任務應該執行多次，實際為 {:?}", runs);
+        let ticks: std::collections::HashSet<i64> = runs.iter().map(|(tick, _)| *tick).collect();
+        assert_eq!(
+            ticks.len(),
+            runs.len(),
+            "同一次觸發不應執行兩次：{:?}",
+            runs
+        );
     }
 
-    pub async fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.is_running.store(true, Ordering::SeqCst);
-        self.scheduler.start().await?;
-        Ok(())
+    // 同一 cron 的一般任務作為觸發時間的基準，加上 jitter 的任務應該在其之後才執行
+    #[tokio::test]
+    async fn test_jittered_task_runs_after_tick() {
+        let plain_runs = Arc::new(Mutex::new(Vec::new()));
+        let jittered_runs = Arc::new(Mutex::new(Vec::new()));
+
+        let mut scheduler = Scheduler::new().await.unwrap();
+        let runs = plain_runs.clone();
+        scheduler
+            .add_task("* * * * * *", move || {
+                let runs = runs.clone();
+                async move { runs.lock().unwrap().push(Instant::now()) }
+            })
+            .await
+            .unwrap();
+        let runs = jittered_runs.clone();
+        scheduler
+            .add_task_with_jitter("* * * * * *", Duration::from_millis(400), move || {
+                let runs = runs.clone();
+                async move { runs.lock().unwrap().push(Instant::now()) }
+            })
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_millis(3500)).await;
+        scheduler.stop().await.unwrap();
+
+        let plain_runs = plain_runs.lock().unwrap();
+        let jittered_runs = jittered_runs.lock().unwrap();
+        assert!(!jittered_runs.is_empty(), "加上 jitter 的任務仍應執行");
+        let delays: Vec<Duration> = jittered_runs
+            .iter()
+            .filter_map(|run| {
+                plain_runs
+                    .iter()
+                    .filter(|tick| *tick <= run)
+                    .max()
+                    .map(|tick| run.duration_since(*tick))
+            })
+            .collect();
+        assert!(
+            delays
+                .iter()
+                .all(|delay| *delay < Duration::from_millis(500)),
+            "延遲不應超過 jitter：{:?}",
+            delays
+        );
+        assert!(
+            delays
+                .iter()
+                .any(|delay| *delay >= Duration::from_millis(20)),
+            "任務不應在觸發的當下立即執行：{:?}",
+            delays
+        );
     }
 
-    pub async fn stop(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.is_running.store(false, Ordering::SeqCst);
-        // 等待一小段時間確保所有任務都看到停止信號
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        self.scheduler.shutdown().await?;
-        Ok(())
+    #[test]
+    fn test_jitter_delay_range() {
+        assert_eq!(jitter_delay(Duration::ZERO), Duration::ZERO);
+        let jitter = Duration::from_millis(10);
+        assert!((0..100).all(|_| jitter_delay(jitter) < jitter));
     }
 
-    pub async fn add_task<F, Fut>(
-        &self,
-        cron_expr: &str,
-        task: F,
-    ) -> Result<(), Box<dyn std::error::Error>>
-    where
-        F: Fn() -> Fut + Send + Sync + 'static + Clone,
-        Fut: std::future::Future<Output = ()> + Send + 'static,
-    {
-        let is_running = self.is_running.clone();
+    // 取消 token 後不再觸發新的任務，執行中的任務會先跑完
+    #[tokio::test]
+    async fn test_cancellation_token_stops_scheduler() {
+        let token = CancellationToken::new();
+        let started = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(AtomicUsize::new(0));
 
-        let job = Job::new_async(cron_expr, move |_, _| {
-            let is_running = is_running.clone();
-            let task = task.clone(); // 如果 F 不能 clone，需要用 Arc 包裝
-            Box::pin(async move {
-                if is_running.load(Ordering::SeqCst) {
-                    task().await;
+        let mut scheduler = Scheduler::new_with_cancellation(token.clone())
+            .await
+            .unwrap();
+        let (started_clone, finished_clone) = (started.clone(), finished.clone());
+        scheduler
+            .add_task("* * * * * *", move || {
+                let started = started_clone.clone();
+                let finished = finished_clone.clone();
+                async move {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    sleep(Duration::from_millis(300)).await;
+                    finished.fetch_add(1, Ordering::SeqCst);
                 }
             })
-        })?;
+            .await
+            .unwrap();
 
-        self.scheduler.add(job).await?;
-        Ok(())
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_millis(2500)).await;
+        assert!(started.load(Ordering::SeqCst) > 0, "取消前任務應該執行");
+
+        token.cancel();
+        sleep(Duration::from_millis(500)).await;
+        let started_at_cancel = started.load(Ordering::SeqCst);
+        assert_eq!(
+            finished.load(Ordering::SeqCst),
+            started_at_cancel,
+            "執行中的任務應該跑完"
+        );
+        assert_eq!(scheduler.stats().started_at, None);
+
+        sleep(Duration::from_millis(2000)).await;
+        assert_eq!(
+            started.load(Ordering::SeqCst),
+            started_at_cancel,
+            "取消後不應再觸發任務"
+        );
     }
-}
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::Arc;
-    use std::time::Duration;
-    use tokio::time::sleep;
 
-    // 測試建立排程器
     #[tokio::test]
-    async fn test_scheduler_creation() {
-        let scheduler = Scheduler::new().await;
-        assert!(scheduler.is_ok(), "應該能夠成功建立排程器");
+    async fn test_stats_count_runs_and_errors() {
+        let mut scheduler = Scheduler::new().await.unwrap();
+        assert_eq!(
+            scheduler.stats(),
+            SchedulerStats {
+                jobs_registered: 0,
+                total_runs: 0,
+                total_errors: 0,
+                total_timeouts: 0,
+                started_at: None,
+            }
+        );
+
+        scheduler
+            .add_task("* * * * * *", || async {})
+            .await
+            .unwrap();
+        scheduler
+            .add_task("* * * * * *", || async { panic!("boom") })
+            .await
+            .unwrap();
+        scheduler.start().await.unwrap();
+        assert!(scheduler.stats().started_at.is_some());
+        sleep(Duration::from_millis(1500)).await;
+        scheduler.stop().await.unwrap();
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.jobs_registered, 2);
+        assert!(stats.total_runs > 0);
+        assert!(stats.total_errors > 0 && stats.total_errors < stats.total_runs);
+        assert_eq!(stats.started_at, None);
+    }
+
+    // 測試阻塞任務在 spawn_blocking 中執行，不會卡住單執行緒的 runtime
+    #[tokio::test]
+    async fn test_blocking_task_does_not_block_runtime() {
+        let blocking_runs = Arc::new(AtomicUsize::new(0));
+        let ticks = Arc::new(AtomicUsize::new(0));
+
+        let mut scheduler = Scheduler::new().await.unwrap();
+        let runs = blocking_runs.clone();
+        scheduler
+            .add_blocking_task("* * * * * *", move || {
+                std::thread::sleep(Duration::from_millis(1500));
+                runs.fetch_add(1, Ordering::SeqCst);
+            })
+            .await
+            .unwrap();
+
+        let ticker_ticks = ticks.clone();
+        let ticker = tokio::spawn(async move {
+            loop {
+                sleep(Duration::from_millis(50)).await;
+                ticker_ticks.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_millis(3000)).await;
+        scheduler.stop().await.unwrap();
+        ticker.abort();
+
+        assert!(blocking_runs.load(Ordering::SeqCst) > 0, "阻塞任務應該執行");
+        assert!(
+            ticks.load(Ordering::SeqCst) >= 40,
+            "阻塞任務執行期間其他任務應該持續推進"
+        );
     }
 
-    // 測試基本的任務執行
     #[tokio::test]
+    async fn test_register_jobs_rejects_invalid_cron() {
+        let scheduler = Scheduler::new().await.unwrap();
+        let jobs = vec![
+            CronJob::new("* * * * * *", || Box::pin(async {}) as JobFuture),
+            CronJob::new("not a cron", || Box::pin(async {}) as JobFuture),
+        ];
+        let err = scheduler.register_jobs(jobs).await.unwrap_err();
+        assert!(err.to_string().contains("not a cron"));
+    }
+
+    // 測試基本的任務執行（虛擬時間，不需真的等待）
+    #[tokio::test(start_paused = true)]
     async fn test_basic_task_execution() {
         let counter = Arc::new(AtomicUsize::new(0));
         let counter_clone = counter.clone();
-        let mut scheduler = Scheduler::new().await.unwrap();
+        let mut scheduler = virtual_scheduler().await;
 
         scheduler
             .add_task("* * * * * *", move || {
@@ -115,7 +1553,272 @@ mod tests {
 
         let final_count = counter.load(Ordering::SeqCst);
         println!("最終執行次數: {}", final_count);
-        assert!(final_count > 0, "任務應該至少執行一次");
+        assert_eq!(final_count, 2, "每秒任務在 2 秒內應該執行兩次");
+    }
+
+    // 起點設在某一秒的正中間，每秒的觸發點都落在 x.5 秒，不會與測試的等待時間重疊
+    async fn virtual_scheduler() -> Scheduler {
+        let start = chrono::DateTime::from_timestamp_millis(1_700_000_000_500).unwrap();
+        Scheduler::new_with_clock(Arc::new(TokioClock::starting_at(start)))
+            .await
+            .unwrap()
+    }
+
+    // token 取消後 add_daily_at 與 clock 觸發的任務不再觸發，它們的迴圈也已中止
+    #[tokio::test(start_paused = true)]
+    async fn test_cancellation_stops_daily_and_clock_jobs() {
+        let token = CancellationToken::new();
+        let mut scheduler = Scheduler::new_with_cancellation(token.clone())
+            .await
+            .unwrap();
+        scheduler.clock = Some(Arc::new(TokioClock::starting_at(
+            chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        )));
+        let daily = Arc::new(AtomicUsize::new(0));
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let daily_clone = daily.clone();
+        scheduler
+            .add_daily_at(0, 0, chrono::Utc, move || {
+                let daily = daily_clone.clone();
+                async move {
+                    daily.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .await
+            .unwrap();
+        let ticks_clone = ticks.clone();
+        scheduler
+            .add_job(
+                JobBuilder::interval(Duration::from_secs(3600)).run(move || {
+                    let ticks = ticks_clone.clone();
+                    async move {
+                        ticks.fetch_add(1, Ordering::SeqCst);
+                    }
+                }),
+            )
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_secs(24 * 3600 + 60)).await;
+        assert_eq!(daily.load(Ordering::SeqCst), 1);
+        assert_eq!(ticks.load(Ordering::SeqCst), 24);
+
+        token.cancel();
+        sleep(Duration::from_secs(3 * 24 * 3600)).await;
+        assert_eq!(daily.load(Ordering::SeqCst), 1);
+        assert_eq!(ticks.load(Ordering::SeqCst), 24);
+        assert!(scheduler.drivers.lock().unwrap().is_empty());
+    }
+
+    // stop 之後再 start，clock 觸發與 add_daily_at 的任務都恢復執行
+    #[tokio::test(start_paused = true)]
+    async fn test_clock_and_daily_jobs_resume_after_restart() {
+        let mut scheduler = Scheduler::new_with_clock(Arc::new(TokioClock::starting_at(
+            chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        )))
+        .await
+        .unwrap();
+        let daily = Arc::new(AtomicUsize::new(0));
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let daily_clone = daily.clone();
+        scheduler
+            .add_daily_at(0, 0, chrono::Utc, move || {
+                let daily = daily_clone.clone();
+                async move {
+                    daily.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .await
+            .unwrap();
+        let ticks_clone = ticks.clone();
+        scheduler
+            .add_job(
+                JobBuilder::interval(Duration::from_secs(3600)).run(move || {
+                    let ticks = ticks_clone.clone();
+                    async move {
+                        ticks.fetch_add(1, Ordering::SeqCst);
+                    }
+                }),
+            )
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_secs(24 * 3600 + 60)).await;
+        scheduler.stop().await.unwrap();
+        assert_eq!(daily.load(Ordering::SeqCst), 1);
+        assert_eq!(ticks.load(Ordering::SeqCst), 24);
+
+        // 停止期間的觸發都被略過
+        sleep(Duration::from_secs(24 * 3600)).await;
+        assert_eq!(daily.load(Ordering::SeqCst), 1);
+        assert_eq!(ticks.load(Ordering::SeqCst), 24);
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_secs(24 * 3600)).await;
+        scheduler.stop().await.unwrap();
+        assert_eq!(daily.load(Ordering::SeqCst), 2);
+        assert_eq!(ticks.load(Ordering::SeqCst), 48);
+    }
+
+    // 時間暫停時 sleep 會直接推進虛擬時間，途中的每個觸發點都會依序執行
+    #[tokio::test(start_paused = true)]
+    async fn test_virtual_clock_fires_exactly_once_per_second() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut scheduler = virtual_scheduler().await;
+        let counter_clone = counter.clone();
+        scheduler
+            .add_task("* * * * * *", move || {
+                let counter = counter_clone.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_secs(5)).await;
+        assert_eq!(counter.load(Ordering::SeqCst), 5);
+
+        scheduler.stop().await.unwrap();
+        sleep(Duration::from_secs(5)).await;
+        assert_eq!(counter.load(Ordering::SeqCst), 5, "停止後不應再觸發");
+    }
+
+    // 卡住的任務每次都在 timeout 時被中止，不影響下一次觸發
+    #[tokio::test(start_paused = true)]
+    async fn test_task_timeout_recorded_separately_from_errors() {
+        let finished = Arc::new(AtomicUsize::new(0));
+        let mut scheduler = virtual_scheduler().await;
+        let mut events = scheduler.subscribe_lossless(16);
+        let finished_clone = finished.clone();
+        scheduler
+            .add_task_with_timeout("* * * * * *", Duration::from_millis(200), move || {
+                let finished = finished_clone.clone();
+                async move {
+                    sleep(Duration::from_secs(10)).await;
+                    finished.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_secs(3)).await;
+        scheduler.stop().await.unwrap();
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.total_runs, 3);
+        assert_eq!(stats.total_timeouts, 3);
+        assert_eq!(stats.total_errors, 0);
+        assert_eq!(finished.load(Ordering::SeqCst), 0, "逾時的任務不應執行完畢");
+
+        let mut timed_out = 0;
+        while let Ok(event) = events.try_recv() {
+            if let SchedulerEvent::TimedOut { timeout, .. } = event {
+                assert_eq!(timeout, Duration::from_millis(200));
+                timed_out += 1;
+            }
+        }
+        assert_eq!(timed_out, 3);
+    }
+
+    // 每次執行 1.5 秒、每秒觸發：no_overlap 會略過執行期間的觸發，5 秒內只在第 1、3、5 次觸發時執行
+    #[tokio::test(start_paused = true)]
+    async fn test_job_builder_named_no_overlap_jittered() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let mut scheduler = virtual_scheduler().await;
+        let started_clone = started.clone();
+        let spec = JobBuilder::cron("* * * * * *")
+            .name("report")
+            .no_overlap()
+            .jitter(Duration::from_millis(200))
+            .run(move || {
+                let started = started_clone.clone();
+                async move {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    sleep(Duration::from_millis(1500)).await;
+                }
+            });
+        assert_eq!(spec.name(), Some("report"));
+        scheduler.add_job(spec).await.unwrap();
+        assert_eq!(scheduler.stats().jobs_registered, 1);
+        assert_eq!(scheduler.definitions()[0].name, "report");
+
+        let duplicate = JobBuilder::cron("* * * * * *")
+            .name("report")
+            .run(|| async {});
+        assert!(scheduler.add_job(duplicate).await.is_err());
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_secs(5)).await;
+        scheduler.stop().await.unwrap();
+
+        assert_eq!(started.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_add_job_reserves_name_before_scheduling() {
+        let scheduler = Scheduler::new().await.unwrap();
+        let named = || {
+            JobBuilder::cron("* * * * * *")
+                .name("report")
+                .run(|| async {})
+        };
+        let (first, second) = tokio::join!(scheduler.add_job(named()), scheduler.add_job(named()));
+        assert!(first.is_ok() != second.is_ok(), "同名任務只能註冊一個");
+        assert_eq!(scheduler.stats().jobs_registered, 1);
+
+        // 排程失敗時釋放名稱，之後仍可用同一個名稱註冊
+        let invalid = JobBuilder::cron("not a cron")
+            .name("cleanup")
+            .run(|| async {});
+        assert!(scheduler.add_job(invalid).await.is_err());
+        assert!(scheduler.definitions().iter().all(|d| d.name != "cleanup"));
+        let valid = JobBuilder::cron("* * * * * *")
+            .name("cleanup")
+            .run(|| async {});
+        scheduler.add_job(valid).await.unwrap();
+    }
+
+    // 固定間隔的任務失敗後依 JobRetryPolicy 在同一次觸發內重試
+    #[tokio::test(start_paused = true)]
+    async fn test_job_builder_interval_with_retry() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let mut scheduler = virtual_scheduler().await;
+        let attempts_clone = attempts.clone();
+        let spec = JobBuilder::interval(Duration::from_secs(1))
+            .retry(JobRetryPolicy {
+                max_retries: 3,
+                base_delay: Duration::from_millis(50),
+                max_delay: Duration::from_secs(1),
+            })
+            .run(move || {
+                let attempts = attempts_clone.clone();
+                async move {
+                    // 每次觸發的第一次嘗試都失敗
+                    if attempts.fetch_add(1, Ordering::SeqCst) & 1 == 0 {
+                        panic!("暫時性錯誤");
+                    }
+                }
+            });
+        assert_eq!(spec.trigger(), &Trigger::Interval(Duration::from_secs(1)));
+        scheduler.add_job(spec).await.unwrap();
+        assert!(scheduler
+            .add_job(JobBuilder::interval(Duration::ZERO).run(|| async {}))
+            .await
+            .is_err());
+
+        scheduler.start().await.unwrap();
+        sleep(Duration::from_millis(3500)).await;
+        scheduler.stop().await.unwrap();
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.total_runs, 3);
+        assert_eq!(stats.total_errors, 0);
+        assert_eq!(attempts.load(Ordering::SeqCst), 6);
     }
 
     // 測試多個任務的並行執行
@@ -252,4 +1955,87 @@ mod tests {
         let result = scheduler.stop().await;
         assert!(result.is_ok(), "即使任務出錯，排程器也應該能正常停止");
     }
+
+    // 測試任務 panic 後排程器仍持續運作，之後的觸發照常執行
+    #[tokio::test]
+    async fn test_panicking_task_does_not_stop_scheduler() {
+        let mut scheduler = Scheduler::new().await.unwrap();
+        let mut events = scheduler.subscribe_lossless(16);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_clone = runs.clone();
+        scheduler
+            .add_named_task("flaky", "* * * * * *", move || {
+                let runs = runs_clone.clone();
+                async move {
+                    // 第一次執行 panic，之後正常完成
+                    if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                        panic!("第一次執行失敗");
+                    }
+                }
+            })
+            .await
+            .unwrap();
+
+        scheduler.start().await.unwrap();
+        let mut outcomes = Vec::new();
+        while outcomes.len() < 2 {
+            let event = tokio::time::timeout(Duration::from_secs(3), events.recv())
+                .await
+                .expect("排程器應該持續觸發任務")
+                .unwrap();
+            match event {
+                SchedulerEvent::Failed { error, .. } => outcomes.push(Err(error)),
+                SchedulerEvent::Completed { .. } => outcomes.push(Ok(())),
+                SchedulerEvent::Started { .. } | SchedulerEvent::TimedOut { .. } => {}
+            }
+        }
+        scheduler.stop().await.unwrap();
+
+        assert_eq!(outcomes[0], Err("第一次執行失敗".to_string()));
+        assert_eq!(outcomes[1], Ok(()));
+    }
+
+    // 測試 cron 表達式的描述
+    #[test]
+    fn test_describe_cron_common_expressions() {
+        let cases = vec![
+            ("* * * * * *", "every second"),
+            ("*/5 * * * * *", "every 5 seconds"),
+            ("0 * * * * *", "every minute"),
+            ("0 */15 * * * *", "every 15 minutes"),
+            ("0 0 * * * *", "every hour"),
+            ("0 0 9 * * *", "every day at 09:00"),
+            ("0 30 9 * * Mon-Fri", "at 09:30 on Monday through Friday"),
+            ("0 0 0 1 * *", "at 00:00 on day 1 of the month"),
+            ("0 0 12 * Jan,Jul *", "at 12:00 in January, July"),
+        ];
+
+        for (expr, expected) in cases {
+            assert_eq!(describe_cron(expr).unwrap(), expected, "表達式: {}", expr);
+        }
+    }
+
+    #[test]
+    fn test_describe_cron_shorthands() {
+        let cases = vec![
+            ("@yearly", "every year on January 1 at 00:00"),
+            ("@monthly", "every month on day 1 at 00:00"),
+            ("@weekly", "every week on Sunday at 00:00"),
+            ("@daily", "every day at 00:00"),
+            ("@hourly", "every hour"),
+        ];
+
+        for (expr, expected) in cases {
+            assert_eq!(describe_cron(expr).unwrap(), expected, "表達式: {}", expr);
+        }
+    }
+
+    #[test]
+    fn test_describe_cron_invalid_expression() {
+        let result = describe_cron("invalid");
+        assert!(
+            matches!(result, Err(SchedulerError::InvalidCron { ref expr, .. }) if expr == "invalid"),
+            "無效的 cron 表達式應該返回錯誤"
+        );
+    }
 }
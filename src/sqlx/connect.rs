@@ -0,0 +1,120 @@
+use super::RetryPolicy;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{Error, PgPool};
+
+// 啟動時資料庫可能尚未就緒（例如與服務同時啟動的容器），連線失敗時依 retry 的延遲重試，
+// 直到成功或用完重試次數；認證失敗等不會自行恢復的錯誤直接回傳。
+// 每次嘗試最多等待 options 的 acquire_timeout，需要快速重試時請一併調低
+pub async fn connect_pool_with_retry(
+    options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+    retry: &RetryPolicy,
+) -> Result<PgPool, Error> {
+    let mut attempt = 0;
+    loop {
+        match options.clone().connect_with(connect_options.clone()).await {
+            Ok(pool) => return Ok(pool),
+            Err(e) if attempt < retry.max_retries && is_connect_retryable(&e) => {
+                let delay = retry.delay_for(attempt);
+                tracing::warn!(
+                    "無法連線到資料庫（第 {} 次）：{}，{:?} 後重試",
+                    attempt + 1,
+                    e,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// 連線被拒、逾時，或資料庫仍在啟動（57P03 cannot_connect_now、08xxx 連線錯誤）時才重試
+fn is_connect_retryable(err: &Error) -> bool {
+    match err {
+        Error::Io(_) | Error::PoolTimedOut => true,
+        Error::Database(db_err) => db_err
+            .code()
+            .is_some_and(|code| code == "57P03" || code.starts_with("08")),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlx::PgPoolExt;
+    use crate::test_support::database_url;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn quick_retry(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_millis(200),
+        }
+    }
+
+    fn quick_options() -> PgPoolOptions {
+        PgPoolOptions::new().acquire_timeout(Duration::from_millis(200))
+    }
+
+    // 先佔用一個 port 再釋放，之後才在該 port 開啟轉送到資料庫的 proxy，模擬晚一步就緒的資料庫
+    #[tokio::test]
+    async fn test_connect_retries_until_database_is_reachable() {
+        let target = PgConnectOptions::from_str(&database_url()).unwrap();
+        let upstream = format!("{}:{}", target.get_host(), target.get_port());
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(400)).await;
+            let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+            loop {
+                let (mut inbound, _) = listener.accept().await.unwrap();
+                let upstream = upstream.clone();
+                tokio::spawn(async move {
+                    let mut outbound = TcpStream::connect(upstream).await.unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                });
+            }
+        });
+
+        let pool = connect_pool_with_retry(
+            quick_options(),
+            target.clone().host("127.0.0.1").port(port),
+            &quick_retry(10),
+        )
+        .await
+        .expect("資料庫就緒後應該連線成功");
+        assert!(pool.ping().await.is_ok());
+
+        pool.warmup(3).await.unwrap();
+        assert!(pool.size() >= 3);
+    }
+
+    #[tokio::test]
+    async fn test_connect_gives_up_after_retries() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let connect_options = PgConnectOptions::from_str(&database_url())
+            .unwrap()
+            .host("127.0.0.1")
+            .port(port);
+
+        let result =
+            connect_pool_with_retry(quick_options(), connect_options, &quick_retry(2)).await;
+        assert!(result.as_ref().is_err_and(is_connect_retryable));
+    }
+}
@@ -0,0 +1,76 @@
+use sqlx::postgres::PgDatabaseError;
+use sqlx::Error;
+use std::fmt;
+
+// 依 SQLSTATE 區分的約束違反，讓呼叫端不必自行比對錯誤碼字串；
+// 其他錯誤原樣包在 Database 中
+#[derive(Debug)]
+pub enum PgExtError {
+    // 23505，例如重複的 email
+    UniqueViolation { constraint: Option<String> },
+    // 23503
+    ForeignKeyViolation { constraint: Option<String> },
+    // 23502，column 為違反 NOT NULL 的欄位
+    NotNull { column: Option<String> },
+    // 23514
+    CheckViolation { constraint: Option<String> },
+    Database(Error),
+}
+
+impl PgExtError {
+    // 違反的約束名稱；NotNull 沒有約束名稱，回傳欄位名稱
+    pub fn constraint(&self) -> Option<&str> {
+        match self {
+            PgExtError::UniqueViolation { constraint }
+            | PgExtError::ForeignKeyViolation { constraint }
+            | PgExtError::CheckViolation { constraint } => constraint.as_deref(),
+            PgExtError::NotNull { column } => column.as_deref(),
+            PgExtError::Database(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for PgExtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.constraint().unwrap_or("unknown");
+        match self {
+            PgExtError::UniqueViolation { .. } => write!(f, "Unique constraint violated: {}", name),
+            PgExtError::ForeignKeyViolation { .. } => {
+                write!(f, "Foreign key constraint violated: {}", name)
+            }
+            PgExtError::NotNull { .. } => write!(f, "Null value in non-null column: {}", name),
+            PgExtError::CheckViolation { .. } => write!(f, "Check constraint violated: {}", name),
+            PgExtError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PgExtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PgExtError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for PgExtError {
+    fn from(err: Error) -> Self {
+        let Error::Database(db_err) = &err else {
+            return PgExtError::Database(err);
+        };
+        let constraint = db_err.constraint().map(str::to_string);
+        match db_err.code().as_deref() {
+            Some("23505") => PgExtError::UniqueViolation { constraint },
+            Some("23503") => PgExtError::ForeignKeyViolation { constraint },
+            Some("23514") => PgExtError::CheckViolation { constraint },
+            Some("23502") => PgExtError::NotNull {
+                column: db_err
+                    .try_downcast_ref::<PgDatabaseError>()
+                    .and_then(|e| e.column())
+                    .map(str::to_string),
+            },
+            _ => PgExtError::Database(err),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use futures::stream::{self, BoxStream, StreamExt};
+use sqlx::postgres::{PgListener, PgNotification};
+use sqlx::{Error, PgPool};
+
+// Postgres LISTEN / NOTIFY 的訂閱
+#[async_trait::async_trait]
+pub trait PgListenExt {
+    async fn listen(&self, channels: &[&str]) -> Result<BoxStream<'static, PgNotification>, Error>;
+}
+
+#[async_trait::async_trait]
+impl PgListenExt for PgPool {
+    // 監聽的連線中斷時，PgListener 會自動重新連線並重新 LISTEN 所有頻道，
+    // 但斷線期間送出的通知會遺失；無法重新連線（例如 pool 已關閉）時 stream 直接結束
+    async fn listen(&self, channels: &[&str]) -> Result<BoxStream<'static, PgNotification>, Error> {
+        let mut listener = PgListener::connect_with(self).await?;
+        listener.listen_all(channels.iter().copied()).await?;
+        tracing::info!("開始監聽頻道: {:?}", channels);
+
+        Ok(stream::unfold(listener, |mut listener| async move {
+            match listener.recv().await {
+                Ok(notification) => Some((notification, listener)),
+                Err(e) => {
+                    tracing::warn!("LISTEN 連線失敗，停止接收通知：{}", e);
+                    None
+                }
+            }
+        })
+        .boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::setup_test_db;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_listen_receives_notify_from_another_connection() {
+        let pool = setup_test_db(10).await;
+        let channel = format!("test_listen_{}", uuid::Uuid::new_v4().simple());
+
+        let mut notifications = pool.listen(&[channel.as_str()]).await.unwrap();
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(&channel)
+            .bind("order:42")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let notification = tokio::time::timeout(Duration::from_secs(5), notifications.next())
+            .await
+            .expect("應該在逾時前收到通知")
+            .expect("stream 不應結束");
+        assert_eq!(notification.channel(), channel);
+        assert_eq!(notification.payload(), "order:42");
+    }
+}
@@ -0,0 +1,291 @@
+use sqlx::{Error, PgPool};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+// 已套用的 migration 記錄在這張表，version 為檔名開頭的數字
+const MIGRATIONS_TABLE: &str = "_migrations";
+
+// 目錄中的一個 migration 檔，檔名格式為 <version>_<name>.sql，例如 0001_create_users.sql
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub name: String,
+    pub path: PathBuf,
+    pub sql: String,
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    // 不符合 <version>_<name>.sql 的 .sql 檔
+    InvalidFileName(PathBuf),
+    // 兩個檔案使用相同的 version
+    DuplicateVersion(i64),
+    // 執行失敗的 migration 會整個 rollback，version 為 None 表示失敗在讀取 / 建立記錄表
+    Database {
+        version: Option<i64>,
+        source: Error,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Io { path, source } => {
+                write!(f, "Failed to read {}: {}", path.display(), source)
+            }
+            MigrationError::InvalidFileName(path) => write!(
+                f,
+                "Invalid migration file name '{}', expected <version>_<name>.sql",
+                path.display()
+            ),
+            MigrationError::DuplicateVersion(version) => {
+                write!(f, "Duplicate migration version {}", version)
+            }
+            MigrationError::Database {
+                version: Some(version),
+                source,
+            } => write!(f, "Migration {} failed: {}", version, source),
+            MigrationError::Database {
+                version: None,
+                source,
+            } => write!(f, "Migration bookkeeping failed: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MigrationError::Io { source, .. } => Some(source),
+            MigrationError::Database { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for MigrationError {
+    fn from(source: Error) -> Self {
+        MigrationError::Database {
+            version: None,
+            source,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PgMigrateExt {
+    // 依 version 由小到大套用 dir 中尚未套用的 migration，每個檔案各自在一個交易中執行並記錄到
+    // _migrations；某個檔案失敗時它的變更會 rollback，之前已套用的保留。回傳本次套用的 migration
+    async fn run_migrations(&self, dir: &Path) -> Result<Vec<Migration>, MigrationError>;
+
+    // dry-run：只列出尚未套用的 migration，不建立記錄表也不執行任何 SQL
+    async fn pending_migrations(&self, dir: &Path) -> Result<Vec<Migration>, MigrationError>;
+}
+
+#[async_trait::async_trait]
+impl PgMigrateExt for PgPool {
+    async fn run_migrations(&self, dir: &Path) -> Result<Vec<Migration>, MigrationError> {
+        let migrations = read_migrations(dir)?;
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                version BIGINT PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+            MIGRATIONS_TABLE
+        ))
+        .execute(self)
+        .await?;
+
+        let mut applied = Vec::new();
+        for migration in migrations {
+            if apply(self, &migration)
+                .await
+                .map_err(|source| MigrationError::Database {
+                    version: Some(migration.version),
+                    source,
+                })?
+            {
+                info!("已套用 migration {}_{}", migration.version, migration.name);
+                applied.push(migration);
+            }
+        }
+        Ok(applied)
+    }
+
+    async fn pending_migrations(&self, dir: &Path) -> Result<Vec<Migration>, MigrationError> {
+        let migrations = read_migrations(dir)?;
+        let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+            .bind(MIGRATIONS_TABLE)
+            .fetch_one(self)
+            .await?;
+        let applied: Vec<i64> = if exists {
+            sqlx::query_scalar(&format!("SELECT version FROM {}", MIGRATIONS_TABLE))
+                .fetch_all(self)
+                .await?
+        } else {
+            Vec::new()
+        };
+        Ok(migrations
+            .into_iter()
+            .filter(|m| !applied.contains(&m.version))
+            .collect())
+    }
+}
+
+// 在交易中執行單一 migration；已被套用（例如另一個實例同時啟動）時回傳 false
+async fn apply(pool: &PgPool, migration: &Migration) -> Result<bool, Error> {
+    let mut tx = pool.begin().await?;
+    // 多個實例同時執行時依序套用，避免重複執行同一個檔案
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(MIGRATIONS_TABLE)
+        .execute(&mut *tx)
+        .await?;
+    let already_applied: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS (SELECT 1 FROM {} WHERE version = $1)",
+        MIGRATIONS_TABLE
+    ))
+    .bind(migration.version)
+    .fetch_one(&mut *tx)
+    .await?;
+    if already_applied {
+        return Ok(false);
+    }
+    // 不帶參數時走 simple query protocol，一個檔案可以包含多個陳述式
+    sqlx::Executor::execute(&mut *tx, migration.sql.as_str()).await?;
+    sqlx::query(&format!(
+        "INSERT INTO {} (version, name) VALUES ($1, $2)",
+        MIGRATIONS_TABLE
+    ))
+    .bind(migration.version)
+    .bind(&migration.name)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+// 讀取 dir 中所有 .sql 檔並依 version 排序，其他副檔名的檔案忽略
+fn read_migrations(dir: &Path) -> Result<Vec<Migration>, MigrationError> {
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| MigrationError::Io { path, source }
+    };
+    let mut migrations = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io_error(dir))? {
+        let path = entry.map_err(io_error(dir))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("sql") {
+            continue;
+        }
+        let (version, name) =
+            parse_file_name(&path).ok_or_else(|| MigrationError::InvalidFileName(path.clone()))?;
+        let sql = std::fs::read_to_string(&path).map_err(io_error(&path))?;
+        migrations.push(Migration {
+            version,
+            name,
+            path,
+            sql,
+        });
+    }
+    migrations.sort_by_key(|m| m.version);
+    if let Some(pair) = migrations.windows(2).find(|w| w[0].version == w[1].version) {
+        return Err(MigrationError::DuplicateVersion(pair[0].version));
+    }
+    Ok(migrations)
+}
+
+fn parse_file_name(path: &Path) -> Option<(i64, String)> {
+    let stem = path.file_stem()?.to_str()?;
+    let (version, name) = stem.split_once('_')?;
+    if name.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((version.parse().ok()?, name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::setup_test_db;
+
+    #[test]
+    fn test_parse_file_name() {
+        let parse = |name: &str| parse_file_name(Path::new(name));
+        assert_eq!(
+            parse("0001_create_users.sql"),
+            Some((1, "create_users".into()))
+        );
+        assert_eq!(
+            parse("20240101_add_index.sql"),
+            Some((20240101, "add_index".into()))
+        );
+        assert_eq!(parse("create_users.sql"), None);
+        assert_eq!(parse("0001.sql"), None);
+        assert_eq!(parse("v1_users.sql"), None);
+    }
+
+    #[tokio::test]
+    async fn test_migrations_applied_in_order_and_only_once() {
+        // 記錄表與 migration 建立的表都放在獨立的 schema，測試結束後整個刪除
+        let schema = format!("test_migrate_{}", uuid::Uuid::new_v4().simple());
+        let pool = setup_test_db(1).await;
+        sqlx::Executor::execute(
+            &pool,
+            format!("CREATE SCHEMA {0}; SET search_path TO {0}", schema).as_str(),
+        )
+        .await
+        .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("migrations-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("0001_create_users.sql"),
+            "CREATE TABLE users (id SERIAL PRIMARY KEY, email TEXT NOT NULL);",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("0002_add_name.sql"),
+            "ALTER TABLE users ADD COLUMN name TEXT;
+             INSERT INTO users (email, name) VALUES ('a@example.com', 'Alice');",
+        )
+        .unwrap();
+        std::fs::write(dir.join("README.md"), "not a migration").unwrap();
+
+        let pending = pool.pending_migrations(&dir).await.unwrap();
+        assert_eq!(
+            pending.iter().map(|m| m.version).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let applied = pool.run_migrations(&dir).await.unwrap();
+        assert_eq!(
+            applied.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+            vec!["create_users", "add_name"]
+        );
+        let name: String = sqlx::query_scalar("SELECT name FROM users")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(name, "Alice");
+
+        // 再執行一次不應重複套用
+        assert!(pool.run_migrations(&dir).await.unwrap().is_empty());
+        assert!(pool.pending_migrations(&dir).await.unwrap().is_empty());
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", schema))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
@@ -1,19 +1,167 @@
+use futures::stream::{BoxStream, StreamExt};
 use serde::de::DeserializeOwned;
 use sqlx::{
-    postgres::{PgQueryResult, PgRow},
+    encode::IsNull,
+    postgres::{PgArgumentBuffer, PgArguments, PgQueryResult, PgRow, PgTypeInfo},
     prelude::FromRow,
-    Error, PgPool, Postgres,
+    query::Query,
+    Arguments, Error, PgPool, Postgres, Row, Transaction,
 };
 use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::io::AsyncRead;
 use tracing::{info, instrument};
 
+mod connect;
+mod error;
+mod listen;
+mod migrate;
+mod prepared;
+
+pub use connect::connect_pool_with_retry;
+pub use error::PgExtError;
+pub use listen::PgListenExt;
+pub use migrate::{Migration, MigrationError, PgMigrateExt};
+pub use prepared::PreparedQuery;
+
+pub type TxFuture<'c, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'c>>;
+
+// 序列化失敗 / 死結時的重試策略，延遲以 base_delay 指數成長並以 max_delay 為上限
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+// SQLSTATE 40001 (serialization_failure) 與 40P01 (deadlock_detected) 可以安全地重試
+pub fn is_retryable_error(err: &Error) -> bool {
+    match err {
+        Error::Database(db_err) => matches!(db_err.code().as_deref(), Some("40001" | "40P01")),
+        _ => false,
+    }
+}
+
+// COPY 的資料格式：Csv 以逗號分隔、雙引號包住特殊值；Text 為 Postgres 預設的 tab 分隔、反斜線跳脫格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyFormat {
+    Csv,
+    Text,
+}
+
+impl CopyFormat {
+    fn options(&self) -> &'static str {
+        match self {
+            CopyFormat::Csv => "FORMAT csv",
+            CopyFormat::Text => "FORMAT text",
+        }
+    }
+
+    // 將一列資料編碼成此格式的一行（含結尾換行），None 代表 NULL
+    pub fn encode_row(&self, fields: &[Option<&str>]) -> String {
+        let (delimiter, encode): (&str, fn(&str) -> String) = match self {
+            CopyFormat::Csv => (",", encode_csv_field),
+            CopyFormat::Text => ("\t", encode_text_field),
+        };
+        let mut line = fields
+            .iter()
+            .map(|field| match field {
+                Some(value) => encode(value),
+                None if *self == CopyFormat::Text => "\\N".to_string(),
+                None => String::new(),
+            })
+            .collect::<Vec<_>>()
+            .join(delimiter);
+        line.push('\n');
+        line
+    }
+}
+
+// CSV 中未加引號的空值代表 NULL，因此空字串也必須加引號；單獨一行的 \. 會被視為資料結尾
+fn encode_csv_field(value: &str) -> String {
+    let needs_quotes = value.is_empty() || value == "\\." || value.contains([',', '"', '\n', '\r']);
+    if needs_quotes {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn encode_text_field(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => encoded.push_str("\\\\"),
+            '\t' => encoded.push_str("\\t"),
+            '\n' => encoded.push_str("\\n"),
+            '\r' => encoded.push_str("\\r"),
+            _ => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+// 連線池目前的狀態，適合直接序列化後由 /health 路由回傳
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct PoolHealth {
+    // 連線池允許的最大連線數
+    pub size: u32,
+    // 目前閒置的連線數
+    pub idle: usize,
+    // 目前已建立的連線數（閒置 + 使用中）
+    pub num_connections: u32,
+}
+
+// 分頁查詢的結果：目前這一頁的資料與符合條件的總筆數
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountedRows<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+}
+
 #[async_trait::async_trait]
 pub trait PgPoolExt {
+    // 保留回傳 sqlx::Error，既有呼叫端以 ? 將它轉成自己的錯誤型別，改變回傳型別會讓它們無法編譯；
+    // 需要區分約束違反（例如重複的 email 回傳 409）時改用 execute_checked
     async fn execute<'a, T>(&self, query: &'a str, params: T) -> Result<PgQueryResult, Error>
     where
         T: Send + Sync + IntoIterator + 'a,
         T::Item: 'a + Send + Sync + sqlx::Encode<'a, Postgres> + sqlx::Type<Postgres>;
 
+    // execute 的型別化版本，約束違反會轉成 PgExtError 的對應變體
+    async fn execute_checked(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<PgQueryResult, PgExtError>;
+
+    async fn execute_returning<T>(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Vec<T>, PgExtError>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin;
+
     async fn fetch<T>(
         &self,
         query: &str,
@@ -21,16 +169,108 @@ pub trait PgPoolExt {
     ) -> Result<Vec<T>, Error>
     where
         T: for<'r> FromRow<'r, PgRow> + DeserializeOwned + Send + Unpin;
+
+    async fn fetch_optional_scalar<T>(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Option<T>, Error>
+    where
+        T: for<'r> sqlx::Decode<'r, Postgres> + sqlx::Type<Postgres> + Send + Unpin;
+
+    fn fetch_stream<'a, T>(
+        &'a self,
+        query: &'a str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> BoxStream<'a, Result<T, Error>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin + 'a;
+
+    async fn fetch_json(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Vec<serde_json::Value>, Error>;
+
+    async fn fetch_json_column<T>(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: DeserializeOwned + Send + Unpin;
+
+    async fn fetch_where<T>(
+        &self,
+        table: &str,
+        where_builder: &WhereBuilder,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin;
+
+    async fn count_where(&self, table: &str, where_builder: &WhereBuilder) -> Result<i64, Error>;
+
+    async fn fetch_with_count<T>(
+        &self,
+        data_query: &str,
+        count_query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<CountedRows<T>, Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin;
+
+    async fn serializable_transaction<T, F>(&self, policy: &RetryPolicy, f: F) -> Result<T, Error>
+    where
+        T: Send,
+        F: for<'c> FnMut(&'c mut Transaction<'static, Postgres>) -> TxFuture<'c, T> + Send;
+
+    async fn transaction_with_retry<T, F>(&self, policy: &RetryPolicy, f: F) -> Result<T, Error>
+    where
+        T: Send,
+        F: for<'c> FnMut(&'c mut Transaction<'static, Postgres>) -> TxFuture<'c, T> + Send;
+
+    async fn execute_with_retry(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+        policy: &RetryPolicy,
+    ) -> Result<PgQueryResult, Error>;
+
+    async fn copy_in<R>(
+        &self,
+        table: &str,
+        columns: &[&str],
+        format: CopyFormat,
+        reader: R,
+    ) -> Result<u64, Error>
+    where
+        R: AsyncRead + Unpin + Send;
+
+    async fn copy_in_csv<R>(&self, table: &str, columns: &[&str], reader: R) -> Result<u64, Error>
+    where
+        R: AsyncRead + Unpin + Send;
+
+    async fn prepare_query(&self, query: &str) -> Result<PreparedQuery, Error>;
+
+    async fn ping(&self) -> Result<Duration, Error>;
+
+    // 預先建立 n 條連線（不超過 max_connections），讓第一批請求不必負擔連線握手
+    async fn warmup(&self, n: u32) -> Result<(), Error>;
+
+    fn health_status(&self) -> PoolHealth;
 }
 
 #[async_trait::async_trait]
 impl PgPoolExt for PgPool {
+    // 錯誤為原始的 sqlx::Error，需要型別化的約束錯誤時使用 execute_checked
     #[instrument(skip(self, query, params), fields(query = %query))]
     async fn execute<'a, T>(&self, query: &'a str, params: T) -> Result<PgQueryResult, Error>
     where
         T: Send + Sync + IntoIterator + 'a,
         T::Item: 'a + Send + Sync + sqlx::Encode<'a, Postgres> + sqlx::Type<Postgres>,
     {
+        let params: Vec<T::Item> = params.into_iter().collect();
+        check_param_count(query, params.len())?;
         let mut q = sqlx::query(query);
         for param in params {
             q = q.bind(param);
@@ -40,53 +280,945 @@ impl PgPoolExt for PgPool {
             Ok(pg_result) => info!("Query執行成功，影響了 {} 行", pg_result.rows_affected()),
             Err(e) => info!("Query執行失敗：{:?}", e),
         }
-        result
+        result
+    }
+
+    // 與 execute 相同，但唯一 / 外鍵 / NOT NULL / CHECK 約束違反會轉成 PgExtError 的對應變體，
+    // 例如 handler 可以對 UniqueViolation 回傳 409
+    #[instrument(skip(self, query, params), fields(query = %query))]
+    async fn execute_checked(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<PgQueryResult, PgExtError> {
+        check_param_count(query, params.len())?;
+        let mut sqlx_query = sqlx::query(query);
+        for param in params.iter() {
+            sqlx_query = param.bind_to_query(sqlx_query);
+        }
+        Ok(sqlx_query.execute(self).await?)
+    }
+
+    // INSERT / UPDATE ... RETURNING，錯誤處理與 execute_checked 相同
+    #[instrument(skip(self, query, params), fields(query = %query))]
+    async fn execute_returning<T>(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Vec<T>, PgExtError>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        check_param_count(query, params.len())?;
+        let mut sqlx_query = sqlx::query(query);
+        for param in params.iter() {
+            sqlx_query = param.bind_to_query(sqlx_query);
+        }
+        let rows = sqlx_query.fetch_all(self).await?;
+        Ok(rows
+            .iter()
+            .map(T::from_row)
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    #[instrument(skip(self, query, params), fields(query = %query))]
+    async fn fetch<T>(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + DeserializeOwned + Send + Unpin,
+    {
+        info!("執行查詢: {}", query);
+        let param_count = params.len();
+        check_param_count(query, param_count)?;
+
+        let mut sqlx_query = sqlx::query(query);
+        for param in params.iter() {
+            sqlx_query = param.bind_to_query(sqlx_query);
+        }
+
+        info!("綁定了 {} 個參數", param_count);
+
+        let rows = sqlx_query.fetch_all(self).await?;
+        rows.into_iter()
+            .map(|row| T::from_row(&row))
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    // 取第一列的第一個欄位；沒有資料列或值為 NULL 時都回傳 None（例如空表上的 SELECT max(...)）
+    #[instrument(skip(self, query, params), fields(query = %query))]
+    async fn fetch_optional_scalar<T>(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Option<T>, Error>
+    where
+        T: for<'r> sqlx::Decode<'r, Postgres> + sqlx::Type<Postgres> + Send + Unpin,
+    {
+        check_param_count(query, params.len())?;
+        let mut sqlx_query = sqlx::query(query);
+        for param in params.iter() {
+            sqlx_query = param.bind_to_query(sqlx_query);
+        }
+        match sqlx_query.fetch_optional(self).await? {
+            Some(row) => row.try_get::<Option<T>, _>(0),
+            None => Ok(None),
+        }
+    }
+
+    // 逐列讀取結果而不先收集成 Vec，適合匯出大量資料
+    // 回傳的 stream 借用了 pool 與 query，兩者都必須比 stream 活得久；
+    // stream 消耗完或被 drop 之前會一直佔用一條連線
+    fn fetch_stream<'a, T>(
+        &'a self,
+        query: &'a str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> BoxStream<'a, Result<T, Error>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin + 'a,
+    {
+        if let Err(e) = check_param_count(query, params.len()) {
+            return futures::stream::once(async move { Err(e) }).boxed();
+        }
+        let mut arguments = PgArguments::default();
+        for param in &params {
+            param.add_to_arguments(&mut arguments);
+        }
+        sqlx::query_with(query, arguments)
+            .fetch(self)
+            .map(|row| row.and_then(|row| T::from_row(&row)))
+            .boxed()
+    }
+
+    // 查詢結構在編譯期未知時使用（例如管理介面的臨時查詢），每一列轉成以欄位名稱為 key 的 JSON 物件
+    // 支援整數、浮點數、布林、文字、timestamp / date、uuid 與 json / jsonb，自訂 enum 會轉成字串；
+    // 其他型別（numeric、interval 等）的二進位格式無法直接轉成文字，會是 null，需要時請在 SQL 中轉型（例如 amount::text）
+    #[instrument(skip(self, query, params), fields(query = %query))]
+    async fn fetch_json(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Vec<serde_json::Value>, Error> {
+        check_param_count(query, params.len())?;
+        let mut sqlx_query = sqlx::query(query);
+        for param in params.iter() {
+            sqlx_query = param.bind_to_query(sqlx_query);
+        }
+        let rows = sqlx_query.fetch_all(self).await?;
+        rows.iter().map(row_to_json).collect()
+    }
+
+    // 將每一列的第一個 json / jsonb 欄位反序列化成 T，例如 SELECT profile FROM users。
+    // 需要同時讀取其他欄位時改用 fetch，並在 FromRow 結構中以 sqlx::types::Json<T> 宣告該欄位：
+    //
+    // #[derive(FromRow, Deserialize)]
+    // struct User { id: i32, profile: Json<Profile> }
+    //
+    // 直接宣告為 Profile 時 sqlx 會把它當成 Postgres 的複合型別解碼而失敗
+    #[instrument(skip(self, query, params), fields(query = %query))]
+    async fn fetch_json_column<T>(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: DeserializeOwned + Send + Unpin,
+    {
+        check_param_count(query, params.len())?;
+        let mut sqlx_query = sqlx::query(query);
+        for param in params.iter() {
+            sqlx_query = param.bind_to_query(sqlx_query);
+        }
+        let rows = sqlx_query.fetch_all(self).await?;
+        rows.iter()
+            .map(|row| row.try_get::<sqlx::types::Json<T>, _>(0).map(|json| json.0))
+            .collect()
+    }
+
+    // SELECT * FROM table WHERE ...，條件與參數來自 where_builder
+    #[instrument(skip(self, where_builder))]
+    async fn fetch_where<T>(
+        &self,
+        table: &str,
+        where_builder: &WhereBuilder,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let query = format!(
+            "SELECT * FROM {}{}",
+            ident(table).map_err(|e| Error::Configuration(Box::new(e)))?,
+            where_builder.where_clause()
+        );
+        info!("執行查詢: {}", query);
+        let rows = where_builder
+            .bind(sqlx::query(&query))
+            .fetch_all(self)
+            .await?;
+        rows.iter().map(T::from_row).collect()
+    }
+
+    // 與 fetch_where 使用相同的條件計算筆數，確保分頁的總數與列表一致
+    #[instrument(skip(self, where_builder))]
+    async fn count_where(&self, table: &str, where_builder: &WhereBuilder) -> Result<i64, Error> {
+        let query = format!(
+            "SELECT COUNT(*) FROM {}{}",
+            ident(table).map_err(|e| Error::Configuration(Box::new(e)))?,
+            where_builder.where_clause()
+        );
+        info!("執行查詢: {}", query);
+        let row = where_builder
+            .bind(sqlx::query(&query))
+            .fetch_one(self)
+            .await?;
+        row.try_get(0)
+    }
+
+    // 兩個查詢共用 params；count_query 只會綁定它用到的前 N 個參數，
+    // 因此 LIMIT / OFFSET 這類只屬於 data_query 的參數要放在最後
+    #[instrument(
+        skip(self, data_query, count_query, params),
+        fields(data_query = %data_query, count_query = %count_query)
+    )]
+    async fn fetch_with_count<T>(
+        &self,
+        data_query: &str,
+        count_query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<CountedRows<T>, Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let mut data = sqlx::query(data_query);
+        for param in &params {
+            data = param.bind_to_query(data);
+        }
+        let items = data
+            .fetch_all(self)
+            .await?
+            .iter()
+            .map(T::from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut count = sqlx::query(count_query);
+        for param in params.iter().take(max_placeholder(count_query)) {
+            count = param.bind_to_query(count);
+        }
+        let total = count.fetch_one(self).await?.try_get(0)?;
+
+        Ok(CountedRows { items, total })
+    }
+
+    // 以 SERIALIZABLE 隔離等級執行整個交易，遇到 40001/40P01 時依 policy 重跑整個 closure
+    async fn serializable_transaction<T, F>(&self, policy: &RetryPolicy, f: F) -> Result<T, Error>
+    where
+        T: Send,
+        F: for<'c> FnMut(&'c mut Transaction<'static, Postgres>) -> TxFuture<'c, T> + Send,
+    {
+        run_transaction(self, true, policy, f).await
+    }
+
+    // 以預設隔離等級執行交易，同樣在 40001/40P01 時重跑整個 closure（高併發下的死結）
+    async fn transaction_with_retry<T, F>(&self, policy: &RetryPolicy, f: F) -> Result<T, Error>
+    where
+        T: Send,
+        F: for<'c> FnMut(&'c mut Transaction<'static, Postgres>) -> TxFuture<'c, T> + Send,
+    {
+        run_transaction(self, false, policy, f).await
+    }
+
+    // 單一語句遇到 40001/40P01 時依 policy 重新執行，其他錯誤直接回傳
+    #[instrument(skip(self, query, params, policy), fields(query = %query))]
+    async fn execute_with_retry(
+        &self,
+        query: &str,
+        params: Vec<Box<dyn PostgresParam>>,
+        policy: &RetryPolicy,
+    ) -> Result<PgQueryResult, Error> {
+        check_param_count(query, params.len())?;
+        let mut attempt = 0;
+        loop {
+            let mut sqlx_query = sqlx::query(query);
+            for param in &params {
+                sqlx_query = param.bind_to_query(sqlx_query);
+            }
+            match sqlx_query.execute(self).await {
+                Err(e) if attempt < policy.max_retries && is_retryable_error(&e) => {
+                    let delay = policy.delay_for(attempt);
+                    attempt += 1;
+                    info!(
+                        "語句執行失敗，{:?} 後進行第 {} 次重試：{}",
+                        delay, attempt, e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // 以 COPY ... FROM STDIN 串流匯入，大量資料時比多列 INSERT 快得多；回傳匯入的列數
+    // 讀取失敗時會中止 COPY，已送出的資料不會寫入
+    #[instrument(skip(self, reader))]
+    async fn copy_in<R>(
+        &self,
+        table: &str,
+        columns: &[&str],
+        format: CopyFormat,
+        reader: R,
+    ) -> Result<u64, Error>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        let to_config_error = |e: BuildError| Error::Configuration(Box::new(e));
+        let columns = columns
+            .iter()
+            .map(|column| ident(column))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(to_config_error)?;
+        let statement = format!(
+            "COPY {} ({}) FROM STDIN WITH ({})",
+            ident(table).map_err(to_config_error)?,
+            columns.join(", "),
+            format.options()
+        );
+        info!("執行查詢: {}", statement);
+
+        let mut conn = self.acquire().await?;
+        let mut copy = conn.copy_in_raw(&statement).await?;
+        if let Err(e) = copy.read_from(reader).await.map(|_| ()) {
+            copy.abort(e.to_string()).await?;
+            return Err(e);
+        }
+        let rows = copy.finish().await?;
+        info!("COPY 匯入了 {} 行", rows);
+        Ok(rows)
+    }
+
+    async fn copy_in_csv<R>(&self, table: &str, columns: &[&str], reader: R) -> Result<u64, Error>
+    where
+        R: AsyncRead + Unpin + Send,
+    {
+        self.copy_in(table, columns, CopyFormat::Csv, reader).await
+    }
+
+    // 先在一條連線上 prepare，語法錯誤會在這裡回報；取名避免與 sqlx::Executor::prepare 衝突
+    #[instrument(skip(self))]
+    async fn prepare_query(&self, query: &str) -> Result<PreparedQuery, Error> {
+        sqlx::Executor::prepare(self, query).await?;
+        Ok(PreparedQuery::new(self.clone(), query))
+    }
+
+    // 執行 SELECT 1 並回傳來回時間，供 liveness / readiness 檢查使用
+    #[instrument(skip(self))]
+    async fn ping(&self) -> Result<Duration, Error> {
+        let started = std::time::Instant::now();
+        sqlx::query("SELECT 1").execute(self).await?;
+        Ok(started.elapsed())
+    }
+
+    #[instrument(skip(self))]
+    async fn warmup(&self, n: u32) -> Result<(), Error> {
+        let n = n.min(self.options().get_max_connections());
+        // 同時持有 n 條連線，才會真的建立 n 條而不是重複使用同一條閒置連線
+        let connections = futures::future::try_join_all((0..n).map(|_| self.acquire())).await?;
+        info!("已預先建立 {} 條資料庫連線", connections.len());
+        Ok(())
+    }
+
+    fn health_status(&self) -> PoolHealth {
+        PoolHealth {
+            size: self.options().get_max_connections(),
+            idle: self.num_idle(),
+            num_connections: self.size(),
+        }
+    }
+}
+
+// 交易中的 savepoint，可只復原交易的一部分；名稱以 ident 驗證後加上引號
+#[async_trait::async_trait]
+pub trait TransactionExt {
+    async fn savepoint(&mut self, name: &str) -> Result<(), Error>;
+
+    // 復原到 savepoint 之後的所有變更，savepoint 本身仍保留，可再次復原
+    async fn rollback_to(&mut self, name: &str) -> Result<(), Error>;
+
+    async fn release(&mut self, name: &str) -> Result<(), Error>;
+}
+
+#[async_trait::async_trait]
+impl TransactionExt for Transaction<'_, Postgres> {
+    async fn savepoint(&mut self, name: &str) -> Result<(), Error> {
+        execute_savepoint_command(self, "SAVEPOINT", name).await
+    }
+
+    async fn rollback_to(&mut self, name: &str) -> Result<(), Error> {
+        execute_savepoint_command(self, "ROLLBACK TO SAVEPOINT", name).await
+    }
+
+    async fn release(&mut self, name: &str) -> Result<(), Error> {
+        execute_savepoint_command(self, "RELEASE SAVEPOINT", name).await
+    }
+}
+
+async fn execute_savepoint_command(
+    tx: &mut Transaction<'_, Postgres>,
+    command: &str,
+    name: &str,
+) -> Result<(), Error> {
+    let query = format!(
+        "{} {}",
+        command,
+        ident(name).map_err(|e| Error::Configuration(Box::new(e)))?
+    );
+    info!("執行查詢: {}", query);
+    sqlx::query(&query).execute(&mut **tx).await?;
+    Ok(())
+}
+
+async fn run_transaction<T, F>(
+    pool: &PgPool,
+    serializable: bool,
+    policy: &RetryPolicy,
+    mut f: F,
+) -> Result<T, Error>
+where
+    T: Send,
+    F: for<'c> FnMut(&'c mut Transaction<'static, Postgres>) -> TxFuture<'c, T> + Send,
+{
+    let mut attempt = 0;
+    loop {
+        let result = async {
+            let mut tx = pool.begin().await?;
+            if serializable {
+                sqlx::query("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE")
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            let value = f(&mut tx).await?;
+            tx.commit().await?;
+            Ok(value)
+        }
+        .await;
+
+        match result {
+            Err(e) if attempt < policy.max_retries && is_retryable_error(&e) => {
+                let delay = policy.delay_for(attempt);
+                attempt += 1;
+                info!(
+                    "交易遇到序列化失敗或死結，{:?} 後進行第 {} 次重試：{}",
+                    delay, attempt, e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn row_to_json(row: &PgRow) -> Result<serde_json::Value, Error> {
+    use sqlx::Column;
+
+    let mut object = serde_json::Map::with_capacity(row.columns().len());
+    for (index, column) in row.columns().iter().enumerate() {
+        let value = column_to_json(row, index, column.type_info())?;
+        object.insert(column.name().to_string(), value);
+    }
+    Ok(serde_json::Value::Object(object))
+}
+
+fn column_to_json(
+    row: &PgRow,
+    index: usize,
+    type_info: &PgTypeInfo,
+) -> Result<serde_json::Value, Error> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+    use serde_json::Value;
+    use sqlx::postgres::PgTypeKind;
+    use sqlx::TypeInfo;
+
+    fn get<'r, T>(row: &'r PgRow, index: usize) -> Result<Value, Error>
+    where
+        T: sqlx::Decode<'r, Postgres> + sqlx::Type<Postgres> + Into<Value>,
+    {
+        Ok(row
+            .try_get::<Option<T>, _>(index)?
+            .map_or(Value::Null, Into::into))
+    }
+
+    fn get_string<'r, T>(row: &'r PgRow, index: usize) -> Result<Value, Error>
+    where
+        T: sqlx::Decode<'r, Postgres> + sqlx::Type<Postgres> + ToString,
+    {
+        Ok(row
+            .try_get::<Option<T>, _>(index)?
+            .map_or(Value::Null, |value| Value::String(value.to_string())))
+    }
+
+    match type_info.name() {
+        "INT2" => get::<i16>(row, index),
+        "INT4" => get::<i32>(row, index),
+        "INT8" => get::<i64>(row, index),
+        "FLOAT4" => get::<f32>(row, index),
+        "FLOAT8" => get::<f64>(row, index),
+        "BOOL" => get::<bool>(row, index),
+        "TEXT" | "VARCHAR" | "BPCHAR" | "NAME" => get::<String>(row, index),
+        "JSON" | "JSONB" => get::<Value>(row, index),
+        "UUID" => get_string::<uuid::Uuid>(row, index),
+        "DATE" => get_string::<NaiveDate>(row, index),
+        "TIMESTAMP" => get_string::<NaiveDateTime>(row, index),
+        "TIMESTAMPTZ" => Ok(row
+            .try_get::<Option<DateTime<Utc>>, _>(index)?
+            .map_or(Value::Null, |value| Value::String(value.to_rfc3339()))),
+        // enum 的二進位格式就是標籤文字
+        _ if matches!(type_info.kind(), PgTypeKind::Enum(_)) => Ok(row
+            .try_get_unchecked::<Option<String>, _>(index)?
+            .map_or(Value::Null, Value::String)),
+        _ => Ok(Value::Null),
+    }
+}
+
+// 查詢中依序出現的每個 $N。略過字串常值（含 E'...'）、引號識別字、dollar-quoted 字串
+// （例如函式本體 $$ ... $$）與註解，識別字中的 $（例如 foo$1）也不算佔位符
+fn placeholders(query: &str) -> Vec<usize> {
+    let bytes = query.as_bytes();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' => {
+                // E'...' 中的反斜線會跳脫下一個字元
+                let escapes = i > 0 && matches!(bytes[i - 1], b'E' | b'e');
+                i = skip_quoted(bytes, i + 1, b'\'', escapes);
+            }
+            b'"' => i = skip_quoted(bytes, i + 1, b'"', false),
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = bytes[i..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(bytes.len(), |end| i + end + 1);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(bytes, i + 2),
+            b'$' => {
+                let digits = bytes[i + 1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                let in_identifier = i > 0 && is_ident_byte(bytes[i - 1]);
+                if digits > 0 && !in_identifier {
+                    if let Ok(n) = query[i + 1..i + 1 + digits].parse() {
+                        found.push(n);
+                    }
+                    i += 1 + digits;
+                } else if let Some(end) = (!in_identifier)
+                    .then(|| dollar_quote_end(query, i))
+                    .flatten()
+                {
+                    i = end;
+                } else {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    found
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || !b.is_ascii()
+}
+
+// 回傳結尾引號之後的位置；連續兩個引號代表引號本身
+fn skip_quoted(bytes: &[u8], mut i: usize, quote: u8, backslash_escapes: bool) -> usize {
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if backslash_escapes => i += 2,
+            b if b == quote && bytes.get(i + 1) == Some(&quote) => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+// Postgres 的區塊註解可以巢狀
+fn skip_block_comment(bytes: &[u8], mut i: usize) -> usize {
+    let mut depth = 1;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+// start 為 $tag$ 開頭的 $，tag 可為空或不以數字開頭的識別字；回傳對應的結尾 $tag$ 之後的位置，
+// 不是 dollar quote 時回傳 None
+fn dollar_quote_end(query: &str, start: usize) -> Option<usize> {
+    let rest = &query[start + 1..];
+    let tag_len = rest.find('$')?;
+    let tag = &rest[..tag_len];
+    let valid_tag = tag.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+        && !tag.starts_with(|c: char| c.is_ascii_digit());
+    if !valid_tag {
+        return None;
+    }
+    let delimiter = &query[start..start + tag_len + 2];
+    let body = start + delimiter.len();
+    Some(
+        query[body..]
+            .find(delimiter)
+            .map_or(query.len(), |end| body + end + delimiter.len()),
+    )
+}
+
+// 查詢中最大的 $N，用來決定要綁定幾個參數
+fn max_placeholder(query: &str) -> usize {
+    placeholders(query).into_iter().max().unwrap_or(0)
+}
+
+// 比對查詢中最大的 $N 與參數數量，在送出前就回報 QueryBuilder 的參數錯位；
+// Postgres 要求 $1..$N 的每個參數都要綁定，因此 $1, $3 需要 3 個參數
+pub fn validate_params(query: &str, params: &[Box<dyn PostgresParam>]) -> Result<(), BuildError> {
+    validate_param_count(query, params.len())
+}
+
+fn validate_param_count(query: &str, actual: usize) -> Result<(), BuildError> {
+    let expected = max_placeholder(query);
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(BuildError::ParamCountMismatch { expected, actual })
+    }
+}
+
+// 只在 debug build 檢查，release build 不增加額外的掃描成本
+fn check_param_count(query: &str, actual: usize) -> Result<(), Error> {
+    if cfg!(debug_assertions) {
+        validate_param_count(query, actual).map_err(|e| Error::Configuration(Box::new(e)))?;
+    }
+    Ok(())
+}
+
+// 將 values 依序加入 params，回傳對應的佔位符片段（例如已有兩個參數時為 "$3, $4, $5"），
+// 用來組出 column IN (...)。values 為空時回傳空字串，呼叫端需自行處理（IN () 是語法錯誤）
+pub fn push_in_params<T: PostgresParam + 'static>(
+    params: &mut Vec<Box<dyn PostgresParam>>,
+    values: Vec<T>,
+) -> String {
+    values
+        .into_iter()
+        .map(|value| {
+            params.push(Box::new(value));
+            format!("${}", params.len())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 參數特徵定義，添加 Debug trait
+pub trait PostgresParam: Send + Debug {
+    fn bind_to_query<'q>(
+        &'q self,
+        query: sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>,
+    ) -> sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>;
+
+    // 直接編碼進 PgArguments，讓查詢不必借用參數本身（例如 fetch_stream）
+    fn add_to_arguments(&self, arguments: &mut PgArguments);
+}
+
+// 為基本類型實現 PostgresParam。
+// 對應 Postgres enum 的 Rust enum 只要 derive sqlx::Type 就能直接 Box::new 成參數，不需額外包裝：
+//
+// #[derive(Debug, sqlx::Type)]
+// #[sqlx(type_name = "mood", rename_all = "lowercase")]
+// enum Mood { Happy, Sad }
+//
+// let params: Vec<Box<dyn PostgresParam>> = vec![Box::new(Mood::Happy)];
+//
+// 若要以 enum 陣列（例如 = ANY($1)）綁定，還需實作 PgHasArrayType 並回傳 "_mood"
+//
+// json / jsonb 參數可直接使用 serde_json::Value；要綁定自訂的 Serialize 結構時包成 sqlx::types::Json(value)
+impl<T> PostgresParam for T
+where
+    T: 'static + Send + Sync + Debug + for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres>,
+{
+    fn bind_to_query<'q>(
+        &'q self,
+        query: sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>,
+    ) -> sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments> {
+        query.bind(self)
+    }
+
+    fn add_to_arguments(&self, arguments: &mut PgArguments) {
+        arguments.add(self);
+    }
+}
+
+// 敏感參數（密碼、token 等）：綁定時使用原值，Debug 輸出一律為 ***，
+// 因此即使參數清單被記錄到日誌中也不會洩漏內容
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SensitiveParam<T>(pub T);
+
+impl<T> SensitiveParam<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Debug for SensitiveParam<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<T: sqlx::Type<Postgres>> sqlx::Type<Postgres> for SensitiveParam<T> {
+    fn type_info() -> PgTypeInfo {
+        T::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        T::compatible(ty)
+    }
+}
+
+impl<'q, T: sqlx::Encode<'q, Postgres>> sqlx::Encode<'q, Postgres> for SensitiveParam<T> {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+        self.0.encode_by_ref(buf)
+    }
+
+    fn produces(&self) -> Option<PgTypeInfo> {
+        self.0.produces()
+    }
+
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    InvalidIdentifier(String),
+    // 查詢中最大的 $N 與參數數量不符
+    ParamCountMismatch { expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::InvalidIdentifier(name) => write!(f, "Invalid SQL identifier: {}", name),
+            BuildError::ParamCountMismatch { expected, actual } => write!(
+                f,
+                "Query expects {} params (highest placeholder) but {} params were given",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+// Postgres 識別字長度上限（NAMEDATALEN - 1）
+const MAX_IDENTIFIER_LEN: usize = 63;
+
+// 驗證並加上雙引號的表名 / 欄位名，可用 "schema.table" 形式；
+// 只接受 [A-Za-z_][A-Za-z0-9_]*，注意加引號後名稱會區分大小寫
+pub fn ident(name: &str) -> Result<String, BuildError> {
+    let parts: Vec<&str> = name.split('.').collect();
+    if parts.len() > 2 || parts.iter().any(|part| !is_safe_identifier(part)) {
+        return Err(BuildError::InvalidIdentifier(name.to_string()));
+    }
+    Ok(parts
+        .iter()
+        .map(|part| format!("\"{}\"", part))
+        .collect::<Vec<_>>()
+        .join("."))
+}
+
+fn is_safe_identifier(part: &str) -> bool {
+    let mut chars = part.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    part.len() <= MAX_IDENTIFIER_LEN && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// 組合 WHERE 條件，欄位名稱以 ident 驗證，值一律以 $N 參數綁定
+// 例如 WhereBuilder::new().eq("status", "active")?.gte("age", 18)? 產生 WHERE "status" = $1 AND "age" >= $2
+#[derive(Debug, Default)]
+pub struct WhereBuilder {
+    conditions: Vec<String>,
+    params: Vec<Box<dyn PostgresParam + Sync>>,
+}
+
+impl WhereBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn eq<T: PostgresParam + Sync + 'static>(
+        self,
+        column: &str,
+        value: T,
+    ) -> Result<Self, BuildError> {
+        self.compare(column, "=", value)
+    }
+
+    pub fn ne<T: PostgresParam + Sync + 'static>(
+        self,
+        column: &str,
+        value: T,
+    ) -> Result<Self, BuildError> {
+        self.compare(column, "<>", value)
+    }
+
+    pub fn gt<T: PostgresParam + Sync + 'static>(
+        self,
+        column: &str,
+        value: T,
+    ) -> Result<Self, BuildError> {
+        self.compare(column, ">", value)
+    }
+
+    pub fn gte<T: PostgresParam + Sync + 'static>(
+        self,
+        column: &str,
+        value: T,
+    ) -> Result<Self, BuildError> {
+        self.compare(column, ">=", value)
+    }
+
+    pub fn lt<T: PostgresParam + Sync + 'static>(
+        self,
+        column: &str,
+        value: T,
+    ) -> Result<Self, BuildError> {
+        self.compare(column, "<", value)
+    }
+
+    pub fn lte<T: PostgresParam + Sync + 'static>(
+        self,
+        column: &str,
+        value: T,
+    ) -> Result<Self, BuildError> {
+        self.compare(column, "<=", value)
+    }
+
+    pub fn like<T: PostgresParam + Sync + 'static>(
+        self,
+        column: &str,
+        value: T,
+    ) -> Result<Self, BuildError> {
+        self.compare(column, "LIKE", value)
+    }
+
+    // 閉區間 column BETWEEN start AND end；只給其中一端時退化為 >= / <=，兩端都沒有時不加條件
+    pub fn between<T: PostgresParam + Sync + 'static>(
+        mut self,
+        column: &str,
+        start: Option<T>,
+        end: Option<T>,
+    ) -> Result<Self, BuildError> {
+        let column = ident(column)?;
+        let condition = match (start, end) {
+            (Some(start), Some(end)) => {
+                let start = self.push_param(start);
+                let end = self.push_param(end);
+                format!("{} BETWEEN {} AND {}", column, start, end)
+            }
+            (Some(start), None) => format!("{} >= {}", column, self.push_param(start)),
+            (None, Some(end)) => format!("{} <= {}", column, self.push_param(end)),
+            (None, None) => return Ok(self),
+        };
+        self.conditions.push(condition);
+        Ok(self)
+    }
+
+    // column IN ($N, ...)；空集合時不會有任何列符合，因此改為 FALSE
+    pub fn in_list<T: PostgresParam + Sync + 'static>(
+        mut self,
+        column: &str,
+        values: Vec<T>,
+    ) -> Result<Self, BuildError> {
+        let column = ident(column)?;
+        if values.is_empty() {
+            self.conditions.push("FALSE".to_string());
+            return Ok(self);
+        }
+        let placeholders: Vec<String> = values
+            .into_iter()
+            .map(|value| self.push_param(value))
+            .collect();
+        self.conditions
+            .push(format!("{} IN ({})", column, placeholders.join(", ")));
+        Ok(self)
     }
 
-    #[instrument(skip(self, params), fields(query = %query))]
-    async fn fetch<T>(
-        &self,
-        query: &str,
-        params: Vec<Box<dyn PostgresParam>>,
-    ) -> Result<Vec<T>, Error>
-    where
-        T: for<'r> FromRow<'r, PgRow> + DeserializeOwned + Send + Unpin,
-    {
-        info!("執行查詢: {}", query);
-        let param_count = params.len();
+    pub fn is_null(mut self, column: &str) -> Result<Self, BuildError> {
+        self.conditions.push(format!("{} IS NULL", ident(column)?));
+        Ok(self)
+    }
 
-        let mut sqlx_query = sqlx::query(query);
-        for param in params.iter() {
-            sqlx_query = param.bind_to_query(sqlx_query);
-        }
+    fn compare<T: PostgresParam + Sync + 'static>(
+        mut self,
+        column: &str,
+        op: &str,
+        value: T,
+    ) -> Result<Self, BuildError> {
+        let placeholder = self.push_param(value);
+        self.conditions
+            .push(format!("{} {} {}", ident(column)?, op, placeholder));
+        Ok(self)
+    }
 
-        info!("綁定了 {} 個參數", param_count);
+    // 加入參數並回傳對應的 $N
+    fn push_param<T: PostgresParam + Sync + 'static>(&mut self, value: T) -> String {
+        self.params.push(Box::new(value));
+        format!("${}", self.params.len())
+    }
 
-        let rows = sqlx_query.fetch_all(self).await?;
-        rows.into_iter()
-            .map(|row| T::from_row(&row))
-            .collect::<Result<Vec<_>, _>>()
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
     }
-}
 
-// 參數特徵定義，添加 Debug trait
-pub trait PostgresParam: Send + Debug {
-    fn bind_to_query<'q>(
-        &'q self,
-        query: sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>,
-    ) -> sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>;
-}
+    // 沒有條件時回傳空字串，否則回傳以空白開頭的 " WHERE ..."，可直接接在 FROM 之後
+    pub fn where_clause(&self) -> String {
+        if self.conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", self.conditions.join(" AND "))
+        }
+    }
 
-// 為基本類型實現 PostgresParam
-impl<T> PostgresParam for T
-where
-    T: 'static + Send + Sync + Debug + for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres>,
-{
-    fn bind_to_query<'q>(
+    pub fn param_count(&self) -> usize {
+        self.params.len()
+    }
+
+    fn bind<'q>(
         &'q self,
-        query: sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>,
-    ) -> sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments> {
-        query.bind(self)
+        mut query: Query<'q, Postgres, PgArguments>,
+    ) -> Query<'q, Postgres, PgArguments> {
+        for param in &self.params {
+            query = param.bind_to_query(query);
+        }
+        query
     }
 }
 
@@ -111,11 +1243,83 @@ pub trait QueryContext: Send + Debug {
     }
     fn into_params(self) -> Vec<Box<dyn PostgresParam>>;
 }
+
+// 以宣告式巨集產生 QueryContext 的實作，參數順序由 #[param(N)] 決定（對應 SQL 的 $N），
+// 不再依欄位宣告順序手動維護；#[getter(...)] 可選，指定要實作的 QueryContext getter
+//
+// query_context! {
+//     #[derive(Debug)]
+//     pub struct AuthContext {
+//         #[param(2)] #[getter(get_email)] pub email: String,
+//         #[param(1)] #[getter(get_tenant_id)] pub tenant_id: String,
+//     }
+// }
+//
+// #[param(N)] 必須剛好涵蓋 1..=參數數量且不可重複，否則編譯失敗
+#[macro_export]
+macro_rules! query_context {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[param($index:literal)])?
+                $(#[getter($getter:ident)])?
+                $field_vis:vis $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $ty,)*
+        }
+
+        const _: () = $crate::sqlx::check_param_indices(&[$($($index,)?)*]);
+
+        impl $crate::sqlx::QueryContext for $name {
+            $($(
+                fn $getter(&self) -> Option<String> {
+                    Some(self.$field.to_string())
+                }
+            )?)*
+
+            fn into_params(self) -> Vec<Box<dyn $crate::sqlx::PostgresParam>> {
+                let mut params: Vec<(usize, Box<dyn $crate::sqlx::PostgresParam>)> = vec![
+                    $($(($index, Box::new(self.$field)),)?)*
+                ];
+                params.sort_by_key(|(index, _)| *index);
+                params.into_iter().map(|(_, param)| param).collect()
+            }
+        }
+    };
+}
+
+// 供 query_context! 在編譯期檢查 #[param(N)] 是否為 1..=n 的排列
+#[doc(hidden)]
+pub const fn check_param_indices(indices: &[usize]) {
+    let mut expected = 1;
+    while expected <= indices.len() {
+        let mut count = 0;
+        let mut i = 0;
+        while i < indices.len() {
+            if indices[i] == expected {
+                count += 1;
+            }
+            i += 1;
+        }
+        if count != 1 {
+            panic!("#[param(N)] must cover 1..=n exactly once");
+        }
+        expected += 1;
+    }
+}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::setup_test_db;
     use serde::{Deserialize, Serialize};
     use sqlx::FromRow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
 
     #[derive(Debug, FromRow, Serialize, Deserialize)]
     struct TestUser {
@@ -124,18 +1328,9 @@ mod tests {
         email: String,
     }
 
-    async fn setup_test_db() -> PgPool {
-        let database_url = std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "postgres://Rex@localhost:5432/mydb".to_string());
-
-        PgPool::connect(&database_url)
-            .await
-            .expect("無法連接到測試數據庫")
-    }
-
     #[tokio::test]
     async fn test_execute_query() {
-        let pool = setup_test_db().await;
+        let pool = setup_test_db(10).await;
 
         // 創建測試表
         let create_table = "CREATE TEMPORARY TABLE IF NOT EXISTS test_users (
@@ -148,9 +1343,56 @@ mod tests {
         assert!(result.is_ok(), "無法創建測試表");
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Deserialize)]
+    #[sqlx(type_name = "mood", rename_all = "lowercase")]
+    enum Mood {
+        Happy,
+        Sad,
+    }
+
+    #[derive(Debug, FromRow, Deserialize)]
+    struct MoodRow {
+        name: String,
+        mood: Mood,
+    }
+
+    #[tokio::test]
+    async fn test_postgres_enum_round_trip() {
+        // enum 型別建在 pg_temp，只對這條連線可見
+        let pool = setup_test_db(1).await;
+        pool.execute(
+            "CREATE TYPE pg_temp.mood AS ENUM ('happy', 'sad')",
+            Vec::<String>::new(),
+        )
+        .await
+        .unwrap();
+        pool.execute(
+            "CREATE TEMPORARY TABLE moods (name TEXT NOT NULL, mood pg_temp.mood NOT NULL)",
+            Vec::<String>::new(),
+        )
+        .await
+        .unwrap();
+
+        pool.execute(
+            "INSERT INTO moods (name, mood) VALUES ('alice', $1), ('bob', $2)",
+            vec![Mood::Happy, Mood::Sad],
+        )
+        .await
+        .unwrap();
+
+        let params: Vec<Box<dyn PostgresParam>> = vec![Box::new(Mood::Sad)];
+        let rows: Vec<MoodRow> = pool
+            .fetch("SELECT name, mood FROM moods WHERE mood = $1", params)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].name, "bob");
+        assert_eq!(rows[0].mood, Mood::Sad);
+    }
+
     #[tokio::test]
     async fn test_fetch_query() {
-        let pool = setup_test_db().await;
+        let pool = setup_test_db(10).await;
 
         // 分步驟執行：先建表
         let create_table = "CREATE TABLE IF NOT EXISTS test_users (
@@ -242,6 +1484,150 @@ mod tests {
         }
     }
 
+    crate::query_context! {
+        #[derive(Debug)]
+        struct GeneratedContext {
+            #[param(3)]
+            #[getter(get_email)]
+            email: String,
+            #[param(1)]
+            #[getter(get_tenant_id)]
+            tenant_id: String,
+            // 沒有標註的欄位不會成為參數
+            #[getter(get_name)]
+            name: String,
+            #[param(2)]
+            user_id: i64,
+        }
+    }
+
+    #[test]
+    fn test_query_context_macro_orders_params_by_annotation() {
+        let context = GeneratedContext {
+            email: "test@example.com".to_string(),
+            tenant_id: "tenant-1".to_string(),
+            name: "test_user".to_string(),
+            user_id: 42,
+        };
+
+        assert_eq!(context.get_email(), Some("test@example.com".to_string()));
+        assert_eq!(context.get_tenant_id(), Some("tenant-1".to_string()));
+        assert_eq!(context.get_name(), Some("test_user".to_string()));
+        assert_eq!(context.get_user_id(), None);
+
+        let params = context.into_params();
+        assert_eq!(
+            format!("{:?}", params),
+            r#"["tenant-1", 42, "test@example.com"]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sensitive_param_binds_value_but_masks_debug() {
+        let pool = setup_test_db(10).await;
+        let params: Vec<Box<dyn PostgresParam>> = vec![
+            Box::new(SensitiveParam::new("s3cret-password".to_string())),
+            Box::new(7_i32),
+        ];
+        assert_eq!(format!("{:?}", params), "[***, 7]");
+
+        let value: Option<String> = pool
+            .fetch_optional_scalar("SELECT $1 || ':' || $2::text", params)
+            .await
+            .unwrap();
+        assert_eq!(value.as_deref(), Some("s3cret-password:7"));
+    }
+
+    #[tokio::test]
+    async fn test_copy_in_csv_imports_rows() {
+        // COPY 會從連線池另外取得連線，暫存表必須在同一條連線上
+        let pool = setup_test_db(1).await;
+        pool.execute(
+            "CREATE TEMPORARY TABLE copy_users (id INT, name TEXT, note TEXT)",
+            Vec::<i32>::new(),
+        )
+        .await
+        .unwrap();
+
+        let mut csv = String::new();
+        for i in 0..10_000 {
+            let id = i.to_string();
+            let name = format!("user \"{}\", the {}th", i, i);
+            let note = match i % 3 {
+                0 => None,
+                1 => Some(""),
+                _ => Some("line 1\nline 2"),
+            };
+            csv.push_str(&CopyFormat::Csv.encode_row(&[Some(&id), Some(&name), note]));
+        }
+
+        let copied = pool
+            .copy_in_csv("copy_users", &["id", "name", "note"], csv.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(copied, 10_000);
+
+        let count: Option<i64> = pool
+            .fetch_optional_scalar("SELECT count(*) FROM copy_users", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, Some(10_000));
+
+        let rows: Vec<(String, Option<String>)> =
+            sqlx::query_as("SELECT name, note FROM copy_users WHERE id IN (3, 4, 5) ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("user \"3\", the 3th".to_string(), None),
+                ("user \"4\", the 4th".to_string(), Some(String::new())),
+                (
+                    "user \"5\", the 5th".to_string(),
+                    Some("line 1\nline 2".to_string())
+                ),
+            ]
+        );
+
+        // tab 分隔的 text 格式
+        let text = CopyFormat::Text.encode_row(&[Some("10001"), Some("tab\there \\ slash"), None]);
+        let copied = pool
+            .copy_in(
+                "copy_users",
+                &["id", "name", "note"],
+                CopyFormat::Text,
+                text.as_bytes(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(copied, 1);
+        let name: Option<String> = pool
+            .fetch_optional_scalar("SELECT name FROM copy_users WHERE id = 10001", vec![])
+            .await
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("tab\there \\ slash"));
+    }
+
+    #[test]
+    #[should_panic(expected = "must cover 1..=n exactly once")]
+    fn test_param_indices_must_be_contiguous() {
+        check_param_indices(&[1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_ping_and_health_status() {
+        let pool = setup_test_db(3).await;
+
+        let latency = pool.ping().await.expect("ping 應該成功");
+        assert!(latency > Duration::ZERO);
+
+        let health = pool.health_status();
+        assert_eq!(health.size, 3);
+        assert!(health.num_connections >= 1);
+        assert!(health.idle <= health.num_connections as usize);
+    }
+
     #[tokio::test]
     async fn test_postgres_param_implementation() {
         let param: Box<dyn PostgresParam> = Box::new("test_value".to_string());
@@ -250,4 +1636,768 @@ mod tests {
 
         assert!(true, "Parameter binding should compile successfully");
     }
+
+    #[tokio::test]
+    async fn test_serializable_transaction_retries_on_serialization_failure() {
+        let pool = setup_test_db(10).await;
+        pool.execute(
+            "CREATE TABLE IF NOT EXISTS test_serializable (id SERIAL PRIMARY KEY, label TEXT NOT NULL)",
+            Vec::<String>::new(),
+        )
+        .await
+        .expect("無法創建測試表");
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let attempts_clone = attempts.clone();
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        };
+
+        let id: i32 = pool
+            .serializable_transaction(&policy, move |tx| {
+                let attempts = attempts_clone.clone();
+                Box::pin(async move {
+                    // 第一次嘗試時模擬 Postgres 的 serialization_failure
+                    if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                        sqlx::query(
+                            "DO $$ BEGIN RAISE EXCEPTION 'simulated' USING ERRCODE = 'serialization_failure'; END $$",
+                        )
+                        .execute(&mut **tx)
+                        .await?;
+                    }
+                    sqlx::query_scalar(
+                        "INSERT INTO test_serializable (label) VALUES ('retried') RETURNING id",
+                    )
+                    .fetch_one(&mut **tx)
+                    .await
+                })
+            })
+            .await
+            .expect("重試後應該成功");
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 2, "應該恰好重試一次");
+        assert!(id > 0);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_to_savepoint_keeps_earlier_work() {
+        // 暫存表只存在於建立它的連線上
+        let pool = setup_test_db(1).await;
+        pool.execute(
+            "CREATE TEMPORARY TABLE savepoint_items (label TEXT NOT NULL)",
+            Vec::<String>::new(),
+        )
+        .await
+        .unwrap();
+
+        let mut tx = pool.begin().await.unwrap();
+        sqlx::query("INSERT INTO savepoint_items (label) VALUES ('first')")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.savepoint("before_second").await.unwrap();
+        sqlx::query("INSERT INTO savepoint_items (label) VALUES ('second')")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.rollback_to("before_second").await.unwrap();
+        tx.release("before_second").await.unwrap();
+        tx.commit().await.unwrap();
+
+        let labels: Vec<(String,)> = sqlx::query_as("SELECT label FROM savepoint_items")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(labels, vec![("first".to_string(),)]);
+
+        // 不合法的名稱在送出前就被擋下
+        let mut tx = pool.begin().await.unwrap();
+        assert!(matches!(
+            tx.savepoint("x; DROP TABLE savepoint_items").await,
+            Err(Error::Configuration(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_transaction_with_retry_surfaces_non_retryable_errors() {
+        let pool = setup_test_db(10).await;
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        };
+
+        // 前兩次模擬死結，第三次成功
+        let attempts = Arc::new(AtomicU32::new(0));
+        let attempts_clone = attempts.clone();
+        let value: i32 = pool
+            .transaction_with_retry(&policy, move |tx| {
+                let attempts = attempts_clone.clone();
+                Box::pin(async move {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                        sqlx::query(
+                            "DO $$ BEGIN RAISE EXCEPTION 'simulated' USING ERRCODE = 'deadlock_detected'; END $$",
+                        )
+                        .execute(&mut **tx)
+                        .await?;
+                    }
+                    sqlx::query_scalar("SELECT 42").fetch_one(&mut **tx).await
+                })
+            })
+            .await
+            .expect("重試後應該成功");
+        assert_eq!(value, 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // 不可重試的錯誤應立即回傳
+        let attempts = Arc::new(AtomicU32::new(0));
+        let attempts_clone = attempts.clone();
+        let result: Result<i32, Error> = pool
+            .transaction_with_retry(&policy, move |tx| {
+                let attempts = attempts_clone.clone();
+                Box::pin(async move {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    sqlx::query_scalar("SELECT 1 / 0")
+                        .fetch_one(&mut **tx)
+                        .await
+                })
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_retry_counts_attempts() {
+        // 暫存 sequence 只存在於單一連線，因此限制連線池只有一條連線
+        let pool = setup_test_db(1).await;
+        // nextval 不受交易回滾影響，可用來計算實際執行次數
+        pool.execute(
+            "CREATE TEMPORARY SEQUENCE retry_attempts",
+            Vec::<i32>::new(),
+        )
+        .await
+        .unwrap();
+        let policy = RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        };
+
+        let result = pool
+            .execute_with_retry(
+                "DO $$ BEGIN PERFORM nextval('retry_attempts'); RAISE EXCEPTION 'simulated' USING ERRCODE = 'serialization_failure'; END $$",
+                Vec::new(),
+                &policy,
+            )
+            .await;
+        assert!(result.as_ref().is_err_and(is_retryable_error));
+        let attempts: Option<i64> = pool
+            .fetch_optional_scalar("SELECT currval('retry_attempts')", Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(attempts, Some(3), "應該執行 1 次並重試 2 次");
+
+        let result = pool
+            .execute_with_retry(
+                "DO $$ BEGIN PERFORM nextval('retry_attempts'); RAISE EXCEPTION 'simulated' USING ERRCODE = 'unique_violation'; END $$",
+                Vec::new(),
+                &policy,
+            )
+            .await;
+        assert!(result.is_err());
+        let attempts: Option<i64> = pool
+            .fetch_optional_scalar("SELECT currval('retry_attempts')", Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(attempts, Some(4), "不可重試的錯誤不應重試");
+    }
+
+    #[test]
+    fn test_retry_policy_delay_is_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+        };
+        assert_eq!(policy.delay_for(0), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(2), Duration::from_millis(400));
+        assert_eq!(policy.delay_for(5), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_optional_scalar() {
+        let pool = setup_test_db(10).await;
+        pool.execute(
+            "CREATE TABLE IF NOT EXISTS test_optional_scalar (id SERIAL PRIMARY KEY, label TEXT, score INT)",
+            Vec::<String>::new(),
+        )
+        .await
+        .expect("無法創建測試表");
+        pool.execute("DELETE FROM test_optional_scalar", Vec::<String>::new())
+            .await
+            .unwrap();
+
+        // 空表：max() 回傳一列 NULL
+        let max: Option<i32> = pool
+            .fetch_optional_scalar("SELECT max(score) FROM test_optional_scalar", Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(max, None);
+
+        // 沒有任何資料列
+        let label: Option<String> = pool
+            .fetch_optional_scalar(
+                "SELECT label FROM test_optional_scalar WHERE id = $1",
+                vec![Box::new(-1)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(label, None);
+
+        pool.execute(
+            "INSERT INTO test_optional_scalar (label, score) VALUES (NULL, 7), ('named', 3)",
+            Vec::<String>::new(),
+        )
+        .await
+        .unwrap();
+
+        let max: Option<i32> = pool
+            .fetch_optional_scalar("SELECT max(score) FROM test_optional_scalar", Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(max, Some(7));
+
+        // 資料列存在但值為 NULL
+        let label: Option<String> = pool
+            .fetch_optional_scalar(
+                "SELECT label FROM test_optional_scalar WHERE score = $1",
+                vec![Box::new(7)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(label, None);
+    }
+
+    #[test]
+    fn test_where_builder_numbers_placeholders() {
+        let builder = WhereBuilder::new()
+            .eq("status", "active".to_string())
+            .unwrap()
+            .gte("age", 18)
+            .unwrap()
+            .is_null("deleted_at")
+            .unwrap();
+        assert_eq!(
+            builder.where_clause(),
+            " WHERE \"status\" = $1 AND \"age\" >= $2 AND \"deleted_at\" IS NULL"
+        );
+        assert_eq!(builder.param_count(), 2);
+        assert_eq!(WhereBuilder::new().where_clause(), "");
+        assert!(WhereBuilder::new().eq("name; --", 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_in_list_fetches_matching_ids() {
+        // 暫存表只存在於單一連線，因此限制連線池只有一條連線
+        let pool = setup_test_db(1).await;
+        pool.execute(
+            "CREATE TEMPORARY TABLE in_list_users (id INT PRIMARY KEY, name TEXT NOT NULL, email TEXT NOT NULL)",
+            Vec::<String>::new(),
+        )
+        .await
+        .unwrap();
+        for id in 1..=5 {
+            pool.execute(
+                "INSERT INTO in_list_users (id, name, email) VALUES ($1, 'user', 'user@example.com')",
+                vec![id],
+            )
+            .await
+            .unwrap();
+        }
+
+        let mut params: Vec<Box<dyn PostgresParam>> = vec![Box::new("user".to_string())];
+        let placeholders = push_in_params(&mut params, vec![2, 4, 5]);
+        assert_eq!(placeholders, "$2, $3, $4");
+        let query = format!(
+            "SELECT * FROM in_list_users WHERE name = $1 AND id IN ({}) ORDER BY id",
+            placeholders
+        );
+        let rows: Vec<TestUser> = pool.fetch(&query, params).await.unwrap();
+        assert_eq!(rows.iter().map(|u| u.id).collect::<Vec<_>>(), vec![2, 4, 5]);
+
+        let builder = WhereBuilder::new()
+            .eq("name", "user".to_string())
+            .unwrap()
+            .in_list("id", vec![1, 3, 4])
+            .unwrap();
+        assert_eq!(
+            builder.where_clause(),
+            " WHERE \"name\" = $1 AND \"id\" IN ($2, $3, $4)"
+        );
+        let mut rows: Vec<TestUser> = pool.fetch_where("in_list_users", &builder).await.unwrap();
+        rows.sort_by_key(|u| u.id);
+        assert_eq!(rows.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1, 3, 4]);
+
+        let empty = WhereBuilder::new()
+            .in_list("id", Vec::<i32>::new())
+            .unwrap();
+        let rows: Vec<TestUser> = pool.fetch_where("in_list_users", &empty).await.unwrap();
+        assert!(rows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stream_reads_rows_incrementally() {
+        use futures::TryStreamExt;
+
+        // 暫存表只存在於單一連線，因此限制連線池只有一條連線
+        let pool = setup_test_db(1).await;
+        pool.execute(
+            "CREATE TEMPORARY TABLE stream_rows (id INT NOT NULL)",
+            Vec::<i32>::new(),
+        )
+        .await
+        .unwrap();
+        pool.execute(
+            "INSERT INTO stream_rows SELECT generate_series(1, 1000)",
+            Vec::<i32>::new(),
+        )
+        .await
+        .unwrap();
+
+        #[derive(FromRow)]
+        struct StreamRow {
+            id: i32,
+        }
+
+        let (count, sum) = pool
+            .fetch_stream::<StreamRow>(
+                "SELECT id FROM stream_rows WHERE id >= $1",
+                vec![Box::new(1)],
+            )
+            .try_fold((0usize, 0i64), |(count, sum), row| async move {
+                Ok((count + 1, sum + row.id as i64))
+            })
+            .await
+            .unwrap();
+        assert_eq!(count, 1000);
+        assert_eq!(sum, 500_500);
+    }
+
+    #[test]
+    fn test_where_builder_between() {
+        let start = chrono::Utc::now() - chrono::Duration::days(7);
+        let end = chrono::Utc::now();
+
+        let closed = WhereBuilder::new()
+            .eq("status", "active".to_string())
+            .unwrap()
+            .between("created_at", Some(start), Some(end))
+            .unwrap();
+        assert_eq!(
+            closed.where_clause(),
+            " WHERE \"status\" = $1 AND \"created_at\" BETWEEN $2 AND $3"
+        );
+        assert_eq!(closed.param_count(), 3);
+
+        let start_only = WhereBuilder::new()
+            .between("created_at", Some(start), None)
+            .unwrap()
+            .eq("status", "active".to_string())
+            .unwrap();
+        assert_eq!(
+            start_only.where_clause(),
+            " WHERE \"created_at\" >= $1 AND \"status\" = $2"
+        );
+        assert_eq!(start_only.param_count(), 2);
+
+        let end_only = WhereBuilder::new()
+            .between("created_at", None, Some(end))
+            .unwrap();
+        assert_eq!(end_only.where_clause(), " WHERE \"created_at\" <= $1");
+        assert_eq!(end_only.param_count(), 1);
+
+        let unbounded = WhereBuilder::new()
+            .between::<chrono::DateTime<chrono::Utc>>("created_at", None, None)
+            .unwrap();
+        assert!(unbounded.is_empty());
+        assert_eq!(unbounded.param_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_count_where_matches_fetch_where() {
+        let pool = setup_test_db(10).await;
+        pool.execute(
+            "CREATE TABLE IF NOT EXISTS test_count_where (id SERIAL PRIMARY KEY, name TEXT NOT NULL, email TEXT NOT NULL)",
+            Vec::<String>::new(),
+        )
+        .await
+        .expect("無法創建測試表");
+        pool.execute("DELETE FROM test_count_where", Vec::<String>::new())
+            .await
+            .unwrap();
+        for (name, email) in [
+            ("alice", "alice@example.com"),
+            ("bob", "bob@example.com"),
+            ("carol", "carol@other.com"),
+        ] {
+            pool.execute(
+                "INSERT INTO test_count_where (name, email) VALUES ($1, $2)",
+                vec![name, email],
+            )
+            .await
+            .unwrap();
+        }
+
+        let builder = WhereBuilder::new()
+            .like("email", "%@example.com".to_string())
+            .unwrap()
+            .ne("name", "nobody".to_string())
+            .unwrap();
+        let rows: Vec<TestUser> = pool
+            .fetch_where("test_count_where", &builder)
+            .await
+            .unwrap();
+        let count = pool
+            .count_where("test_count_where", &builder)
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(count, rows.len() as i64);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_with_count_returns_page_and_total() {
+        // 暫存表只存在於單一連線，因此限制連線池只有一條連線
+        let pool = setup_test_db(1).await;
+        pool.execute(
+            "CREATE TEMPORARY TABLE paged_users (id SERIAL PRIMARY KEY, name TEXT NOT NULL, email TEXT NOT NULL)",
+            Vec::<String>::new(),
+        )
+        .await
+        .unwrap();
+        for n in 1..=5 {
+            pool.execute(
+                "INSERT INTO paged_users (name, email) VALUES ($1, $2)",
+                vec![format!("user{}", n), format!("user{}@example.com", n)],
+            )
+            .await
+            .unwrap();
+        }
+
+        let page: CountedRows<TestUser> = pool
+            .fetch_with_count(
+                "SELECT * FROM paged_users WHERE email LIKE $1 ORDER BY id LIMIT $2 OFFSET $3",
+                "SELECT COUNT(*) FROM paged_users WHERE email LIKE $1",
+                vec![
+                    Box::new("%@example.com".to_string()),
+                    Box::new(2i64),
+                    Box::new(2i64),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(page.total, 5);
+        assert_eq!(
+            page.items
+                .iter()
+                .map(|u| u.name.as_str())
+                .collect::<Vec<_>>(),
+            ["user3", "user4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_json_mixed_types() {
+        let pool = setup_test_db(10).await;
+        let rows = pool
+            .fetch_json(
+                "SELECT 1::int2 AS small, $1::int4 AS id, 9000000000::int8 AS big, \
+                 1.5::float8 AS ratio, true AS active, 'alice'::text AS name, \
+                 NULL::text AS nickname, \
+                 '2024-01-02T03:04:05Z'::timestamptz AS created_at, \
+                 '2024-01-02'::date AS day, \
+                 'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'::uuid AS uid, \
+                 '{\"tags\": [\"a\"]}'::jsonb AS meta",
+                vec![Box::new(42)],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rows,
+            vec![serde_json::json!({
+                "small": 1,
+                "id": 42,
+                "big": 9000000000i64,
+                "ratio": 1.5,
+                "active": true,
+                "name": "alice",
+                "nickname": null,
+                "created_at": "2024-01-02T03:04:05+00:00",
+                "day": "2024-01-02",
+                "uid": "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11",
+                "meta": { "tags": ["a"] }
+            })]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_constraint_violations_are_typed() {
+        // 暫存表只存在於單一連線，因此限制連線池只有一條連線
+        let pool = setup_test_db(1).await;
+        pool.execute(
+            "CREATE TEMPORARY TABLE unique_users (id SERIAL PRIMARY KEY, \
+             email TEXT NOT NULL CONSTRAINT unique_users_email_key UNIQUE, \
+             age INT CONSTRAINT unique_users_age_check CHECK (age >= 0))",
+            Vec::<String>::new(),
+        )
+        .await
+        .unwrap();
+
+        let insert = "INSERT INTO unique_users (email, age) VALUES ($1, $2) RETURNING id";
+        let params = |email: Option<&str>, age: i32| -> Vec<Box<dyn PostgresParam>> {
+            vec![Box::new(email.map(str::to_string)), Box::new(age)]
+        };
+        let ids: Vec<(i32,)> = pool
+            .execute_returning(insert, params(Some("dup@example.com"), 30))
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+
+        let duplicate = pool
+            .execute_checked(insert, params(Some("dup@example.com"), 31))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            &duplicate,
+            PgExtError::UniqueViolation { constraint } if constraint.as_deref() == Some("unique_users_email_key")
+        ));
+
+        let negative = pool
+            .execute_checked(insert, params(Some("new@example.com"), -1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            &negative,
+            PgExtError::CheckViolation { constraint } if constraint.as_deref() == Some("unique_users_age_check")
+        ));
+
+        let missing = pool
+            .execute_checked(insert, params(None, 1))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            &missing,
+            PgExtError::NotNull { column } if column.as_deref() == Some("email")
+        ));
+
+        let other = pool
+            .execute_checked("SELECT * FROM missing_table", vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(other, PgExtError::Database(_)));
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Address {
+        city: String,
+        zip: Option<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Profile {
+        nickname: String,
+        addresses: Vec<Address>,
+    }
+
+    // jsonb 欄位必須宣告為 Json<T>
+    #[derive(Debug, FromRow, Serialize, Deserialize)]
+    struct ProfileRow {
+        id: i32,
+        profile: sqlx::types::Json<Profile>,
+    }
+
+    #[tokio::test]
+    async fn test_jsonb_round_trip_into_nested_struct() {
+        // 暫存表只存在於單一連線，因此限制連線池只有一條連線
+        let pool = setup_test_db(1).await;
+        pool.execute(
+            "CREATE TEMPORARY TABLE json_profiles (id INT PRIMARY KEY, profile JSONB NOT NULL)",
+            Vec::<String>::new(),
+        )
+        .await
+        .unwrap();
+
+        let profile = Profile {
+            nickname: "rex".to_string(),
+            addresses: vec![
+                Address {
+                    city: "Taipei".to_string(),
+                    zip: Some("100".to_string()),
+                },
+                Address {
+                    city: "Tainan".to_string(),
+                    zip: None,
+                },
+            ],
+        };
+        pool.execute(
+            "INSERT INTO json_profiles (id, profile) VALUES (1, $1)",
+            vec![sqlx::types::Json(profile.clone())],
+        )
+        .await
+        .unwrap();
+        pool.execute(
+            "INSERT INTO json_profiles (id, profile) VALUES (2, $1)",
+            vec![serde_json::json!({ "nickname": "ogre", "addresses": [] })],
+        )
+        .await
+        .unwrap();
+
+        let rows: Vec<ProfileRow> = pool
+            .fetch("SELECT id, profile FROM json_profiles ORDER BY id", vec![])
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].profile.0, profile);
+        assert_eq!(rows[1].profile.nickname, "ogre");
+
+        // serde_json::Value 與 Json<T> 都能直接作為 PostgresParam
+        let containing: Vec<Profile> = pool
+            .fetch_json_column(
+                "SELECT profile FROM json_profiles WHERE profile @> $1",
+                vec![Box::new(
+                    serde_json::json!({ "addresses": [{ "city": "Tainan" }] }),
+                )],
+            )
+            .await
+            .unwrap();
+        assert_eq!(containing, vec![profile.clone()]);
+
+        let exact: Vec<ProfileRow> = pool
+            .fetch(
+                "SELECT id, profile FROM json_profiles WHERE profile = $1",
+                vec![Box::new(sqlx::types::Json(profile.clone()))],
+            )
+            .await
+            .unwrap();
+        assert_eq!(exact.iter().map(|row| row.id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn test_validate_params() {
+        let params: Vec<Box<dyn PostgresParam>> = vec![Box::new(1), Box::new("a".to_string())];
+        assert!(validate_params("SELECT * FROM t WHERE a = $1 AND b = $2", &params).is_ok());
+        // 重複使用同一個 $N 只算一次
+        assert!(validate_params("SELECT $1, $2 WHERE $1 > 0", &params).is_ok());
+        assert!(matches!(
+            validate_params("SELECT * FROM t WHERE a = $1", &params),
+            Err(BuildError::ParamCountMismatch {
+                expected: 1,
+                actual: 2
+            })
+        ));
+        assert!(validate_params("SELECT 1", &[]).is_ok());
+        // 跳號時以最大的 $N 為準
+        assert!(matches!(
+            validate_params("SELECT $1, $3", &params),
+            Err(BuildError::ParamCountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_placeholders_skip_literals_and_comments() {
+        assert_eq!(
+            max_placeholder("CREATE FUNCTION f(int) RETURNS int AS 'SELECT $1 + 1' LANGUAGE sql"),
+            0
+        );
+        assert_eq!(
+            max_placeholder(
+                "CREATE FUNCTION f(int) RETURNS int AS $body$ SELECT $1 $body$ LANGUAGE sql"
+            ),
+            0
+        );
+        assert_eq!(max_placeholder("DO $$ BEGIN PERFORM $1; END $$"), 0);
+        assert_eq!(max_placeholder("SELECT 'it''s $3', $1"), 1);
+        assert_eq!(max_placeholder(r"SELECT E'\' $9', $1"), 1);
+        assert_eq!(max_placeholder("SELECT \"col$2\" FROM t WHERE a = $1"), 1);
+        assert_eq!(max_placeholder("SELECT $1 -- uses $4\n, $2"), 2);
+        assert_eq!(max_placeholder("SELECT /* $5 /* nested $6 */ */ $1"), 1);
+        assert_eq!(max_placeholder("SELECT foo$1 FROM t WHERE a = $2"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_param_count_mismatch() {
+        let pool = setup_test_db(10).await;
+        let params: Vec<Box<dyn PostgresParam>> = vec![Box::new(1)];
+        let result = pool
+            .fetch::<TestUser>(
+                "SELECT * FROM test_users WHERE id = $1 AND name = $2",
+                params,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Configuration(e)) if e.to_string().contains("expects 2 params")
+        ));
+
+        let first = pool
+            .fetch_stream::<TestUser>("SELECT * FROM test_users WHERE id = $2", vec![])
+            .next()
+            .await;
+        assert!(matches!(first, Some(Err(Error::Configuration(_)))));
+
+        // 字串常值中的 $1 不是佔位符，不帶參數也能執行
+        let value: Option<String> = pool
+            .fetch_optional_scalar("SELECT 'costs $1'", vec![])
+            .await
+            .unwrap();
+        assert_eq!(value.as_deref(), Some("costs $1"));
+    }
+
+    #[test]
+    fn test_max_placeholder() {
+        assert_eq!(max_placeholder("SELECT 1"), 0);
+        assert_eq!(
+            max_placeholder("SELECT * FROM t WHERE a = $2 AND b = $10"),
+            10
+        );
+        assert_eq!(max_placeholder("SELECT $1::text"), 1);
+    }
+
+    #[test]
+    fn test_ident_accepts_valid_identifiers() {
+        assert_eq!(ident("users").unwrap(), "\"users\"");
+        assert_eq!(ident("created_at").unwrap(), "\"created_at\"");
+        assert_eq!(ident("public.users").unwrap(), "\"public\".\"users\"");
+    }
+
+    #[test]
+    fn test_ident_rejects_injection() {
+        let attempts = [
+            "users; DROP TABLE users",
+            "users\"; DROP TABLE users; --",
+            "",
+            "1users",
+            "a.b.c",
+            "name with space",
+        ];
+        for attempt in attempts {
+            assert_eq!(
+                ident(attempt),
+                Err(BuildError::InvalidIdentifier(attempt.to_string())),
+                "應該拒絕: {}",
+                attempt
+            );
+        }
+        assert!(ident(&"a".repeat(64)).is_err(), "超過 63 字元應該被拒絕");
+    }
 }
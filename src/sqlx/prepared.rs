@@ -0,0 +1,108 @@
+use super::{check_param_count, PgPoolExt, PostgresParam};
+use serde::de::DeserializeOwned;
+use sqlx::postgres::{PgQueryResult, PgRow};
+use sqlx::{Error, FromRow, PgPool, Postgres};
+use std::sync::Arc;
+
+// 重複執行的熱門查詢。每條連線第一次執行時 parse 成具名 prepared statement，
+// 之後同一條連線直接沿用 statement cache，不會再 parse。
+// cache 以連線為單位，容量由 PgConnectOptions::statement_cache_capacity 決定（預設 100），
+// 熱門查詢的種類超過容量時最久未用的會被淘汰，需要時可調大：
+//
+// let options = PgConnectOptions::from_str(&url)?.statement_cache_capacity(500);
+// let pool = PgPoolOptions::new().connect_with(options).await?;
+#[derive(Debug, Clone)]
+pub struct PreparedQuery {
+    pool: PgPool,
+    sql: Arc<str>,
+}
+
+impl PreparedQuery {
+    pub(crate) fn new(pool: PgPool, sql: &str) -> Self {
+        Self {
+            pool,
+            sql: Arc::from(sql),
+        }
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub async fn fetch<T>(&self, params: Vec<Box<dyn PostgresParam>>) -> Result<Vec<T>, Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + DeserializeOwned + Send + Unpin,
+    {
+        self.pool.fetch(&self.sql, params).await
+    }
+
+    pub async fn fetch_optional_scalar<T>(
+        &self,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<Option<T>, Error>
+    where
+        T: for<'r> sqlx::Decode<'r, Postgres> + sqlx::Type<Postgres> + Send + Unpin,
+    {
+        self.pool.fetch_optional_scalar(&self.sql, params).await
+    }
+
+    pub async fn execute(
+        &self,
+        params: Vec<Box<dyn PostgresParam>>,
+    ) -> Result<PgQueryResult, Error> {
+        check_param_count(&self.sql, params.len())?;
+        let mut query = sqlx::query(&self.sql).persistent(true);
+        for param in params.iter() {
+            query = param.bind_to_query(query);
+        }
+        query.execute(&self.pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::setup_test_db;
+
+    #[tokio::test]
+    async fn test_prepared_query_reuses_statement_cache() {
+        // 單一連線，才能從 pg_prepared_statements 看到同一條連線上的 statement
+        let pool = setup_test_db(1).await;
+
+        let sql = format!(
+            "SELECT $1::int + 1 AS next /* {} */",
+            uuid::Uuid::new_v4().simple()
+        );
+        let prepared = pool.prepare_query(&sql).await.unwrap();
+        for i in 0..5 {
+            let next: Option<i32> = prepared
+                .fetch_optional_scalar(vec![Box::new(i)])
+                .await
+                .unwrap();
+            assert_eq!(next, Some(i + 1));
+        }
+
+        // 執行了 5 次，但連線上只 parse 過一次
+        let statements: Option<i64> = pool
+            .fetch_optional_scalar(
+                "SELECT count(*) FROM pg_prepared_statements WHERE statement = $1",
+                vec![Box::new(sql.clone())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(statements, Some(1));
+
+        // 語法錯誤在 prepare 時就會回報
+        assert!(pool.prepare_query("SELEC 1").await.is_err());
+
+        // 參數數量不符時與 PgPoolExt 的其他查詢一樣回傳 Configuration 錯誤
+        let sum = pool
+            .prepare_query("SELECT $1::int + $2::int")
+            .await
+            .unwrap();
+        assert!(matches!(
+            sum.execute(vec![Box::new(1)]).await,
+            Err(Error::Configuration(_))
+        ));
+    }
+}
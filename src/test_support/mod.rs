@@ -0,0 +1,40 @@
+use axum::Router;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
+// 測試專用的 RSA 金鑰對，用於簽發與驗證 RS256 JWT
+pub(crate) const TEST_RSA_PRIVATE_KEY: &str = include_str!("rsa_private.pem");
+pub(crate) const TEST_RSA_PUBLIC_KEY: &str = include_str!("rsa_public.pem");
+
+// 測試專用的 P-256 金鑰對，用於 ES256 JWT
+pub(crate) const TEST_EC_PRIVATE_KEY: &str = include_str!("ec_private.pem");
+pub(crate) const TEST_EC_PUBLIC_KEY: &str = include_str!("ec_public.pem");
+
+// 在隨機埠啟動一個本地 mock 伺服器，回傳其 base url（例如 http://127.0.0.1:12345）
+pub(crate) async fn spawn_mock_server(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("無法綁定 mock 伺服器埠");
+    let addr = listener.local_addr().expect("無法取得 mock 伺服器位址");
+    tokio::spawn(async move {
+        axum::serve(listener, app)
+            .await
+            .expect("mock 伺服器異常結束");
+    });
+    format!("http://{}", addr)
+}
+
+// 測試用資料庫，未設定 DATABASE_URL 時連到本機的開發資料庫
+pub(crate) fn database_url() -> String {
+    std::env::var("DATABASE_URL")
+        .unwrap_or_else(|_| "postgres://Rex@localhost:5432/mydb".to_string())
+}
+
+// 暫存表、pg_temp 型別與 session 設定只存在於建立它們的連線上，
+// 測試需要看到它們時以 max_connections = 1 建立連線池
+pub(crate) async fn setup_test_db(max_connections: u32) -> PgPool {
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .connect(&database_url())
+        .await
+        .expect("無法連接到測試數據庫")
+}
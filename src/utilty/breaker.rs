@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+// 取得公鑰端點的斷路器：連續失敗 failure_threshold 次後，cooldown 期間不再發出請求，
+// 避免端點故障時每個請求都等到逾時才失敗。取得失敗或斷路期間，
+// 若距離最後一次成功取得公鑰不超過 grace，繼續以快取的公鑰驗證
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyFetchBreaker {
+    pub failure_threshold: u32,
+    pub cooldown: Duration,
+    pub grace: Duration,
+}
+
+impl Default for KeyFetchBreaker {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+            grace: Duration::from_secs(3600),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<DateTime<Utc>>,
+    last_success: Option<DateTime<Utc>>,
+}
+
+impl BreakerState {
+    // 從磁碟載入的公鑰以檔案的修改時間作為最後一次成功取得的時間
+    pub fn with_last_success(at: Option<DateTime<Utc>>) -> Self {
+        Self {
+            last_success: at,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        self.open_until.is_some_and(|until| now < until)
+    }
+
+    pub fn record_success(&mut self, now: DateTime<Utc>) {
+        self.consecutive_failures = 0;
+        self.open_until = None;
+        self.last_success = Some(now);
+    }
+
+    // cooldown 結束後的第一次嘗試若仍失敗，會立即再次斷路
+    pub fn record_failure(&mut self, now: DateTime<Utc>, breaker: &KeyFetchBreaker) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.consecutive_failures >= breaker.failure_threshold {
+            let cooldown =
+                chrono::Duration::from_std(breaker.cooldown).unwrap_or(chrono::Duration::MAX);
+            self.open_until = Some(
+                now.checked_add_signed(cooldown)
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            );
+        }
+    }
+
+    pub fn within_grace(&self, now: DateTime<Utc>, breaker: &KeyFetchBreaker) -> bool {
+        self.last_success
+            .is_some_and(|at| (now - at).to_std().unwrap_or_default() <= breaker.grace)
+    }
+}
+
+// 由未知的 kid 或驗證失敗觸發的重新取得：generation 在每次實際發出請求時遞增，
+// 等待 last_attempt 鎖的請求若發現 generation 已改變，代表其他請求剛取得過，不必再取得一次
+#[derive(Debug, Default)]
+pub(crate) struct OnDemandRefresh {
+    pub last_attempt: tokio::sync::Mutex<Option<DateTime<Utc>>>,
+    pub generation: std::sync::atomic::AtomicU64,
+}
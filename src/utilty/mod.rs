@@ -1,36 +1,207 @@
-use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
-use chrono::{Duration, Utc};
+use crate::clock::{Clock, RealClock};
+use axum::middleware::{self, FromExtractorLayer};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
 use hyper::StatusCode;
-use jsonwebtoken::{decode, Algorithm, DecodingKey, TokenData, Validation};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, TokenData, Validation};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+
+mod breaker;
+mod revocation;
+
+pub use breaker::KeyFetchBreaker;
+use breaker::{BreakerState, OnDemandRefresh};
+pub use revocation::{InMemoryRevocationStore, RevocationStore};
+
+const FIREBASE_PUBLIC_KEYS_URL: &str =
+    "https://www.googleapis.com/robot/v1/metadata/x509/securetoken@system.gserviceaccount.com";
+const DEFAULT_AUDIENCE: &str = "leaveanote-4af85";
+// 容許的時鐘誤差（秒），與 jsonwebtoken 的預設值相同
+const DEFAULT_LEEWAY_SECS: u64 = 60;
+// 未知 kid 觸發重新取得公鑰的最短間隔
+const DEFAULT_MIN_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
-    aud: String,
+    // Firebase 的 aud 為單一字串，部分 OIDC 供應商則為陣列，兩種都接受
+    #[serde(
+        serialize_with = "serialize_audience",
+        deserialize_with = "deserialize_audience"
+    )]
+    aud: Vec<String>,
     exp: usize,
     iat: usize,
     pub email: String,
     pub name: Option<String>, // 其他你想要提取的字段
+    // 自訂的角色 claim，token 中沒有時為空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    // token 的唯一 id，用於撤銷檢查；Firebase 的 ID token 沒有這個 claim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    // 以下為 Firebase ID token 的標準 claim，其他來源的 token 可能沒有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    email_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firebase: Option<FirebaseClaims>,
+}
+
+// ID token 中的 firebase 區塊
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FirebaseClaims {
+    // 登入方式，例如 "password"、"google.com"、"custom"
+    #[serde(default)]
+    pub sign_in_provider: String,
+    // 各登入方式對應的識別值，例如 {"email": ["user@example.com"]}
+    #[serde(default)]
+    pub identities: HashMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+fn deserialize_audience<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(aud) => vec![aud],
+        OneOrMany::Many(auds) => auds,
+    })
+}
+
+// 只有一個 aud 時寫回字串，與 Firebase 的格式相同
+fn serialize_audience<S>(aud: &[String], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match aud {
+        [single] => serializer.serialize_str(single),
+        _ => aud.serialize(serializer),
+    }
 }
 
 impl Claims {
     pub fn mock() -> Self {
-        let now = Utc::now();
+        Self::mock_at(Utc::now())
+    }
+
+    pub(crate) fn mock_at(now: DateTime<Utc>) -> Self {
         let iat = now.timestamp() as usize;
         let exp = (now + Duration::hours(1)).timestamp() as usize;
 
         Claims {
             sub: "1234567890".to_string(),
-            aud: "example_audience".to_string(),
+            aud: vec!["example_audience".to_string()],
             exp,
             iat,
             email: "user@example.com".to_string(),
             name: Some("John Doe".to_string()),
+            roles: Vec::new(),
+            jti: None,
+            iss: None,
+            email_verified: None,
+            auth_time: None,
+            firebase: None,
         }
     }
+
+    // 不安全：不檢查簽章、aud 與 exp，只解析 payload。僅供除錯或記錄已由其他管道驗證過的 token，
+    // 任何人都能偽造能通過此函式的 token，絕不能用來判斷使用者身分
+    pub fn decode_unverified(token: &str) -> Result<Claims, JwtError> {
+        let header = decode_header(token).map_err(|_| JwtError::InvalidToken)?;
+        let mut validation = Validation::new(header.alg);
+        validation.insecure_disable_signature_validation();
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+        validation.required_spec_claims.clear();
+        decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)
+            .map(|token_data| token_data.claims)
+            .map_err(JwtError::ValidationError)
+    }
+
+    // exp 超出可表示的時間範圍（惡意或錯誤的 token）時為 None
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        i64::try_from(self.exp)
+            .ok()
+            .and_then(|exp| DateTime::from_timestamp(exp, 0))
+    }
+
+    // 距離過期還有多久，已過期時為 0；exp 過大時不會 panic，而是回傳 Duration::MAX
+    pub fn expires_in(&self, clock: &dyn Clock) -> Duration {
+        self.signed_expires_in(clock).max(Duration::zero())
+    }
+
+    // 已過期時為負值，供 is_expired 與 leeway 比較
+    fn signed_expires_in(&self, clock: &dyn Clock) -> Duration {
+        let exp = i64::try_from(self.exp).unwrap_or(i64::MAX);
+        exp.checked_sub(clock.now().timestamp())
+            .and_then(Duration::try_seconds)
+            .unwrap_or(Duration::MAX)
+    }
+
+    // 給 handler 回報 token 剩餘時間用，已過期時為 0
+    pub fn remaining_lifetime(&self, clock: &dyn Clock) -> std::time::Duration {
+        self.expires_in(clock).to_std().unwrap_or_default()
+    }
+
+    // exp 無法轉換成時間的 token 一律視為已過期
+    pub fn is_expired(&self, clock: &dyn Clock, leeway_secs: u64) -> bool {
+        if self.expires_at().is_none() {
+            return true;
+        }
+        let leeway = Duration::try_seconds(leeway_secs as i64).unwrap_or(Duration::MAX);
+        self.signed_expires_in(clock)
+            .checked_add(&leeway)
+            .is_some_and(|remaining| remaining < Duration::zero())
+    }
+
+    // token 沒有 email_verified claim 時視為未驗證
+    pub fn email_verified(&self) -> bool {
+        self.email_verified.unwrap_or(false)
+    }
+
+    pub fn sign_in_provider(&self) -> Option<&str> {
+        self.firebase
+            .as_ref()
+            .map(|firebase| firebase.sign_in_provider.as_str())
+    }
+
+    // 常見的單一 aud 情況；aud 為陣列時回傳第一個
+    pub fn audience(&self) -> Option<&str> {
+        self.aud.first().map(String::as_str)
+    }
+
+    pub fn audiences(&self) -> &[String] {
+        &self.aud
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
 }
 
 #[derive(Debug)]
@@ -39,7 +210,17 @@ pub enum JwtError {
     ValidationError(jsonwebtoken::errors::Error),
     MissingToken,
     InvalidToken,
+    // 已通過驗證但缺少所需的角色
+    Forbidden(String),
+    // 簽章與標準 claims 皆有效，但缺少 required_claims 中的 claim 或值不符
+    RequiredClaimMismatch(String),
+    // 缺少 required_claim_names 中的 claim，或其值為 null / 空字串 / 空陣列
+    MissingClaim(String),
+    // token 的 sub 或 jti 已被撤銷
+    Revoked,
     FetchError(reqwest::Error),
+    // 取得公鑰的斷路器開啟中，且沒有仍在 grace 內的快取公鑰可用
+    KeysUnavailable,
 }
 
 impl fmt::Display for JwtError {
@@ -49,7 +230,14 @@ impl fmt::Display for JwtError {
             JwtError::ValidationError(e) => write!(f, "JWT validation error: {}", e),
             JwtError::MissingToken => write!(f, "Missing authorization token"),
             JwtError::InvalidToken => write!(f, "Invalid token"),
+            JwtError::Forbidden(role) => write!(f, "Missing required role: {}", role),
+            JwtError::RequiredClaimMismatch(claim) => {
+                write!(f, "Required claim missing or mismatched: {}", claim)
+            }
+            JwtError::MissingClaim(claim) => write!(f, "Missing required claim: {}", claim),
+            JwtError::Revoked => write!(f, "Token has been revoked"),
             JwtError::FetchError(e) => write!(f, "Failed to fetch public keys: {}", e),
+            JwtError::KeysUnavailable => write!(f, "Public keys temporarily unavailable"),
         }
     }
 }
@@ -74,31 +262,137 @@ impl axum::response::IntoResponse for JwtError {
             JwtError::ValidationError(_) => (StatusCode::UNAUTHORIZED, "Invalid token"),
             JwtError::MissingToken => (StatusCode::UNAUTHORIZED, "Missing token"),
             JwtError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
+            JwtError::Revoked => (StatusCode::UNAUTHORIZED, "Token revoked"),
+            JwtError::MissingClaim(_) => (StatusCode::UNAUTHORIZED, "Missing required claim"),
+            JwtError::Forbidden(_) | JwtError::RequiredClaimMismatch(_) => {
+                (StatusCode::FORBIDDEN, "Forbidden")
+            }
             JwtError::FetchError(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to fetch public keys",
             ),
+            JwtError::KeysUnavailable => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Public keys temporarily unavailable",
+            ),
         };
 
         (status, error_message).into_response()
     }
 }
 
+// 不依賴 axum 的驗證入口，可用於 gRPC handler 或背景任務中驗證已保存的 token
+// 每次呼叫都會重新取得公鑰；需要快取時改用 JwtVerifier
+pub async fn verify_id_token(token: &str, config: &JwtConfig) -> Result<Claims, JwtError> {
+    extract_jwt_token_with(token.to_string(), config)
+        .await
+        .map(|token_data| token_data.claims)
+}
+
 pub async fn extract_jwt_token(token: String) -> Result<TokenData<Claims>, JwtError> {
-    let public_keys = fetch_firebase_public_keys()
+    extract_jwt_token_with(token, &JwtConfig::default()).await
+}
+
+// 依 config 的 keys_url 與 client 取得公鑰後驗證，不使用快取
+pub async fn extract_jwt_token_with(
+    token: String,
+    config: &JwtConfig,
+) -> Result<TokenData<Claims>, JwtError> {
+    let public_keys = fetch_public_keys(&config.client, &config.keys_url)
         .await
         .map_err(JwtError::FetchError)?;
 
-    for (_, key) in public_keys {
-        let decoding_key =
-            DecodingKey::from_rsa_pem(key.as_bytes()).map_err(JwtError::ValidationError)?;
-        let mut validation = Validation::new(Algorithm::RS256);
-        validation.set_audience(&["leaveanote-4af85"]);
-        match decode::<Claims>(&token, &decoding_key, &validation) {
+    let token_data = decode_with_keys(&token, &public_keys, config)?;
+    check_revocation(&token_data.claims, config).await?;
+    Ok(token_data)
+}
+
+// 簽章驗證後才查詢撤銷清單，sub 或 jti 任一個被撤銷即拒絕
+async fn check_revocation(claims: &Claims, config: &JwtConfig) -> Result<(), JwtError> {
+    let Some(store) = &config.revocation_store else {
+        return Ok(());
+    };
+    if store.is_revoked(&claims.sub).await {
+        return Err(JwtError::Revoked);
+    }
+    if let Some(jti) = &claims.jti {
+        if store.is_revoked(jti).await {
+            return Err(JwtError::Revoked);
+        }
+    }
+    Ok(())
+}
+
+// 以 header 的 kid 選擇公鑰：kid 在清單中時只用該公鑰驗證；沒有 kid 或 kid 不在清單中時
+// 才依序嘗試每一把與演算法相符的公鑰，直到有一把能驗證 Token 為止
+// （未知的 kid 會先由 JwtVerifier 節流地重新取得公鑰，仍找不到才走到這裡的逐一嘗試）
+// 過期檢查改由 config.clock 判斷，而非 jsonwebtoken 內部的系統時間
+//
+// token header 的 alg 由發送者決定，不能直接拿來選擇驗證方式，因此先比對 config.allowed_algorithms：
+// - alg 為 none 的 token 沒有簽章，decode_header 無法解析，一律以 InvalidToken 拒絕
+// - 若同時接受 HS* 與 RS* / ES*，攻擊者可以用公開的公鑰當作 HMAC 密鑰簽出 HS256 token
+//   （algorithm confusion），所以兩類演算法同時出現在清單中時拒絕所有 HS* token
+fn decode_with_keys(
+    token: &str,
+    public_keys: &HashMap<String, String>,
+    config: &JwtConfig,
+) -> Result<TokenData<Claims>, JwtError> {
+    let header = decode_header(token).map_err(|_| JwtError::InvalidToken)?;
+    let confusable =
+        is_hmac(header.alg) && config.allowed_algorithms.iter().any(|alg| !is_hmac(*alg));
+    if !config.allowed_algorithms.contains(&header.alg) || confusable {
+        tracing::warn!("不接受的簽章演算法: {:?}", header.alg);
+        return Err(JwtError::ValidationError(
+            jsonwebtoken::errors::ErrorKind::InvalidAlgorithm.into(),
+        ));
+    }
+
+    // header 帶有已知的 kid 時只用該公鑰驗證；沒有 kid 或 kid 不在清單中時才逐一嘗試
+    let candidates: Vec<&String> = match header.kid.as_deref().and_then(|kid| public_keys.get(kid))
+    {
+        Some(key) => vec![key],
+        None => public_keys.values().collect(),
+    };
+    for key in candidates {
+        // 公鑰清單可能混有 RSA 與 EC 公鑰，與演算法不符的公鑰直接略過
+        let decoding_key = match decoding_key_for(header.alg, key) {
+            Ok(decoding_key) => decoding_key,
+            Err(e) => {
+                tracing::debug!("公鑰不適用於 {:?}，略過: {:?}", header.alg, e);
+                continue;
+            }
+        };
+        let mut validation = Validation::new(header.alg);
+        validation.aud = Some(config.audience.clone());
+        // 有自訂的 aud 驗證時改由 check_audience 判斷
+        validation.validate_aud = config.audience_validator.is_none();
+        validation.validate_exp = false;
+        match decode::<Claims>(token, &decoding_key, &validation) {
             Ok(token_data) => {
+                check_audience(&token_data.claims, config)?;
+                check_issuer(&token_data.claims, config)?;
+                if token_data
+                    .claims
+                    .is_expired(config.clock.as_ref(), config.leeway)
+                {
+                    return Err(JwtError::ValidationError(
+                        jsonwebtoken::errors::ErrorKind::ExpiredSignature.into(),
+                    ));
+                }
+                if !config.required_claim_names.is_empty() {
+                    check_required_claim_names(token, &config.required_claim_names)?;
+                }
+                if !config.required_claims.is_empty() {
+                    check_required_claims(token, &config.required_claims)?;
+                }
                 tracing::info!("Token 驗證成功，數據: {:?}", token_data.claims);
                 return Ok(token_data);
             }
+            // 簽章已通過才會檢查 aud，換公鑰也沒有意義，直接回報
+            Err(e) if *e.kind() == jsonwebtoken::errors::ErrorKind::InvalidAudience => {
+                tracing::warn!("Token 的 aud 不在允許的清單中: {:?}", config.audience);
+                return Err(JwtError::ValidationError(e));
+            }
             Err(e) => {
                 tracing::debug!("嘗試解碼失敗，嘗試下一個公鑰: {:?}", e);
                 continue;
@@ -109,13 +403,389 @@ pub async fn extract_jwt_token(token: String) -> Result<TokenData<Claims>, JwtEr
     Err(JwtError::NoValidKeyError)
 }
 
-pub async fn fetch_firebase_public_keys(
-) -> Result<std::collections::HashMap<String, String>, reqwest::Error> {
-    let url =
-        "https://www.googleapis.com/robot/v1/metadata/x509/securetoken@system.gserviceaccount.com";
-    let response = reqwest::get(url).await?.json().await?;
+// token 的任一 aud 在靜態清單中，或通過 audience_validator 即可
+fn check_audience(claims: &Claims, config: &JwtConfig) -> Result<(), JwtError> {
+    let Some(validator) = &config.audience_validator else {
+        return Ok(());
+    };
+    if claims
+        .aud
+        .iter()
+        .any(|aud| config.audience.contains(aud) || validator.check(aud))
+    {
+        return Ok(());
+    }
+    tracing::warn!("Token 的 aud 未通過驗證: {:?}", claims.aud);
+    Err(JwtError::ValidationError(
+        jsonwebtoken::errors::ErrorKind::InvalidAudience.into(),
+    ))
+}
+
+// 設定了 issuer_validator 時，token 必須帶有 iss 且通過驗證
+fn check_issuer(claims: &Claims, config: &JwtConfig) -> Result<(), JwtError> {
+    let Some(validator) = &config.issuer_validator else {
+        return Ok(());
+    };
+    match claims.iss.as_deref() {
+        Some(iss) if validator.check(iss) => Ok(()),
+        iss => {
+            tracing::warn!("Token 的 iss 未通過驗證: {:?}", iss);
+            Err(JwtError::ValidationError(
+                jsonwebtoken::errors::ErrorKind::InvalidIssuer.into(),
+            ))
+        }
+    }
+}
+
+fn is_hmac(alg: Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn decoding_key_for(alg: Algorithm, key: &str) -> Result<DecodingKey, jsonwebtoken::errors::Error> {
+    match alg {
+        Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(key.as_bytes()),
+        Algorithm::EdDSA => DecodingKey::from_ed_pem(key.as_bytes()),
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            Ok(DecodingKey::from_secret(key.as_bytes()))
+        }
+        _ => DecodingKey::from_rsa_pem(key.as_bytes()),
+    }
+}
+
+// 簽章驗證通過後才會呼叫，直接解析 payload 以取得 Claims 未定義的自訂 claim
+fn decode_payload(token: &str) -> Result<serde_json::Map<String, serde_json::Value>, JwtError> {
+    let payload = token.split('.').nth(1).ok_or(JwtError::InvalidToken)?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| JwtError::InvalidToken)?;
+    serde_json::from_slice(&bytes).map_err(|_| JwtError::InvalidToken)
+}
+
+fn check_required_claims(
+    token: &str,
+    required: &HashMap<String, serde_json::Value>,
+) -> Result<(), JwtError> {
+    let claims = decode_payload(token)?;
+    for (name, expected) in required {
+        if claims.get(name) != Some(expected) {
+            tracing::warn!("Token 的 claim {} 不符合要求的值 {}", name, expected);
+            return Err(JwtError::RequiredClaimMismatch(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn check_required_claim_names(token: &str, names: &[String]) -> Result<(), JwtError> {
+    let claims = decode_payload(token)?;
+    for name in names {
+        let present = match claims.get(name) {
+            None | Some(serde_json::Value::Null) => false,
+            Some(serde_json::Value::String(s)) => !s.is_empty(),
+            Some(serde_json::Value::Array(a)) => !a.is_empty(),
+            Some(_) => true,
+        };
+        if !present {
+            tracing::warn!("Token 缺少必要的 claim: {}", name);
+            return Err(JwtError::MissingClaim(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+pub async fn fetch_firebase_public_keys() -> Result<HashMap<String, String>, reqwest::Error> {
+    fetch_firebase_public_keys_with(&reqwest::Client::new()).await
+}
+
+// 使用自訂的 client（例如設定了 proxy 或自訂 CA）取得 Firebase 公鑰
+pub async fn fetch_firebase_public_keys_with(
+    client: &reqwest::Client,
+) -> Result<HashMap<String, String>, reqwest::Error> {
+    fetch_public_keys(client, FIREBASE_PUBLIC_KEYS_URL).await
+}
+
+async fn fetch_public_keys(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<HashMap<String, String>, reqwest::Error> {
+    let response = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
     Ok(response)
 }
+
+// Cookie 中 token 的編碼方式，部分框架會先將 JWT 以 base64url 編碼後再放入 cookie
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CookieEncoding {
+    #[default]
+    Raw,
+    Base64Url,
+}
+
+// 以 closure 自訂 aud 或 iss 的驗證邏輯，例如依租戶動態決定接受的 aud
+#[derive(Clone)]
+pub struct ClaimValidator(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl ClaimValidator {
+    pub fn new(validator: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(validator))
+    }
+
+    pub fn check(&self, value: &str) -> bool {
+        (self.0)(value)
+    }
+}
+
+impl fmt::Debug for ClaimValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClaimValidator")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct JwtConfig {
+    // 接受的 aud，token 的 aud 符合其中任一個即可
+    pub audience: HashSet<String>,
+    pub keys_url: String,
+    // 判斷 exp 時容許的時鐘誤差（秒）
+    pub leeway: u64,
+    pub clock: Arc<dyn Clock>,
+    // 沒有 Authorization header 時改從這個 cookie 讀取 token
+    pub cookie_name: Option<String>,
+    pub cookie_encoding: CookieEncoding,
+    // 標頭與 cookie 都沒有時改從這個 query 參數讀取，例如瀏覽器無法設定標頭的 WebSocket 連線（?token=...）。
+    // query string 常被代理與存取日誌記錄，只建議用於 WebSocket upgrade 這類無法帶標頭的請求
+    pub query_param: Option<String>,
+    // 驗證通過後額外要求的 claim 與其值，例如 {"plan": "enterprise"}
+    pub required_claims: HashMap<String, serde_json::Value>,
+    // 必須存在且不為空的 claim 名稱，例如 ["email", "sub"]；只檢查有無，不比對值
+    pub required_claim_names: Vec<String>,
+    // 取得公鑰用的 HTTP client，可自行設定 proxy、CA 或連線池
+    pub client: reqwest::Client,
+    pub revocation_store: Option<Arc<dyn RevocationStore>>,
+    // 接受的簽章演算法，依 token header 的 alg 選擇；alg 為 none 的 token 一律拒絕。
+    // 預設只接受 RS256，避免降級到其他演算法；需要 ES256 等演算法時明確加入。
+    // HS* 只有在清單中沒有任何非對稱演算法時才會被接受，見 decode_with_keys。
+    // HS* 必須明確加入才會接受，此時公鑰清單中的值會被當作共用密鑰
+    pub allowed_algorithms: Vec<Algorithm>,
+    // 讀取 token 的標頭，例如代理轉送時使用的 X-Id-Token
+    pub header_name: String,
+    // 為 false 時整個標頭值即為 token，不要求 "Bearer " 前綴
+    pub require_bearer: bool,
+    // 與 audience 並用：aud 在清單中或通過此驗證即可；只想用 closure 時將 audience 設為空集合
+    pub audience_validator: Option<ClaimValidator>,
+    pub issuer_validator: Option<ClaimValidator>,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            audience: HashSet::from([DEFAULT_AUDIENCE.to_string()]),
+            keys_url: FIREBASE_PUBLIC_KEYS_URL.to_string(),
+            leeway: DEFAULT_LEEWAY_SECS,
+            clock: Arc::new(RealClock),
+            cookie_name: None,
+            cookie_encoding: CookieEncoding::Raw,
+            query_param: None,
+            required_claims: HashMap::new(),
+            required_claim_names: Vec::new(),
+            client: reqwest::Client::new(),
+            revocation_store: None,
+            allowed_algorithms: vec![Algorithm::RS256],
+            header_name: "Authorization".to_string(),
+            require_bearer: true,
+            audience_validator: None,
+            issuer_validator: None,
+        }
+    }
+}
+
+// 帶公鑰快取的驗證器，可選擇將公鑰持久化到磁碟，重啟後無需連網即可驗證
+#[derive(Clone, Debug)]
+pub struct JwtVerifier {
+    config: JwtConfig,
+    keys: Arc<RwLock<HashMap<String, String>>>,
+    persist_path: Option<PathBuf>,
+    breaker: KeyFetchBreaker,
+    breaker_state: Arc<Mutex<BreakerState>>,
+    min_refresh_interval: std::time::Duration,
+    on_demand: Arc<OnDemandRefresh>,
+}
+
+impl JwtVerifier {
+    pub fn new(config: JwtConfig) -> Self {
+        Self {
+            config,
+            keys: Arc::new(RwLock::new(HashMap::new())),
+            persist_path: None,
+            breaker: KeyFetchBreaker::default(),
+            breaker_state: Arc::new(Mutex::new(BreakerState::default())),
+            min_refresh_interval: DEFAULT_MIN_REFRESH_INTERVAL,
+            on_demand: Arc::new(OnDemandRefresh::default()),
+        }
+    }
+
+    // 未知 kid 或驗證失敗時，兩次重新取得公鑰之間的最短間隔（預設 60 秒）；
+    // 間隔內直接以快取的公鑰驗證。快取為空時不受此限制
+    pub fn with_min_refresh_interval(mut self, interval: std::time::Duration) -> Self {
+        self.min_refresh_interval = interval;
+        self
+    }
+
+    // 取代預設的斷路器設定（連續失敗 3 次後停止 30 秒，快取公鑰最多沿用 1 小時）
+    pub fn with_key_fetch_breaker(mut self, breaker: KeyFetchBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    pub fn config(&self) -> &JwtConfig {
+        &self.config
+    }
+
+    // 以不同的 aud 清單驗證，公鑰快取仍與原本的驗證器共用
+    pub fn with_audiences(mut self, audiences: HashSet<String>) -> Self {
+        self.config.audience = audiences;
+        self
+    }
+
+    // 初始化時從磁碟載入上次的公鑰，之後每次成功取得新公鑰都會寫回該檔案
+    pub fn with_persisted_keys(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        match load_persisted_keys(&path) {
+            Ok(keys) => {
+                tracing::info!("從 {:?} 載入了 {} 把快取公鑰", path, keys.len());
+                *self.keys.write().unwrap() = keys;
+                // 檔案在每次成功取得公鑰時寫入，修改時間即為最後一次成功取得的時間
+                let fetched_at = std::fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .map(DateTime::<Utc>::from);
+                *self.breaker_state.lock().unwrap() = BreakerState::with_last_success(fetched_at);
+            }
+            Err(e) => tracing::warn!("無法載入快取公鑰 {:?}: {}", path, e),
+        }
+        self.persist_path = Some(path);
+        self
+    }
+
+    // 斷路器開啟時不發出請求，直接回傳 KeysUnavailable
+    pub async fn refresh_keys(&self) -> Result<(), JwtError> {
+        if self
+            .breaker_state
+            .lock()
+            .unwrap()
+            .is_open(self.config.clock.now())
+        {
+            return Err(JwtError::KeysUnavailable);
+        }
+        let keys = match fetch_public_keys(&self.config.client, &self.config.keys_url).await {
+            Ok(keys) => keys,
+            Err(e) => {
+                self.breaker_state
+                    .lock()
+                    .unwrap()
+                    .record_failure(self.config.clock.now(), &self.breaker);
+                return Err(JwtError::FetchError(e));
+            }
+        };
+
+        if let Some(path) = &self.persist_path {
+            if let Err(e) = persist_keys(path, &keys) {
+                tracing::warn!("無法寫入快取公鑰 {:?}: {}", path, e);
+            }
+        }
+        *self.keys.write().unwrap() = keys;
+        self.breaker_state
+            .lock()
+            .unwrap()
+            .record_success(self.config.clock.now());
+        Ok(())
+    }
+
+    // 由 token 觸發的重新取得公鑰。kid 來自尚未驗證簽章的 token，任何人都能送出任意的 kid，
+    // 因此同時發生的重新取得合併為一次，且兩次之間至少相隔 min_refresh_interval；
+    // 未實際取得時直接沿用快取的公鑰，由呼叫端以快取驗證
+    async fn refresh_on_demand(&self) -> Result<(), JwtError> {
+        let generation = self.on_demand.generation.load(Ordering::SeqCst);
+        let mut last_attempt = self.on_demand.last_attempt.lock().await;
+        if self.on_demand.generation.load(Ordering::SeqCst) != generation {
+            // 等待期間其他請求已重新取得過
+            return Ok(());
+        }
+        let now = self.config.clock.now();
+        let throttled = !self.keys.read().unwrap().is_empty()
+            && last_attempt.is_some_and(|at| {
+                (now - at).to_std().unwrap_or_default() < self.min_refresh_interval
+            });
+        if throttled {
+            tracing::debug!(
+                "距離上次重新取得公鑰未滿 {:?}，沿用快取",
+                self.min_refresh_interval
+            );
+            return Ok(());
+        }
+        *last_attempt = Some(now);
+        self.on_demand.generation.fetch_add(1, Ordering::SeqCst);
+        self.refresh_or_use_cached().await
+    }
+
+    // 重新取得公鑰；失敗時若快取的公鑰仍在 grace 內則沿用，否則回傳錯誤
+    async fn refresh_or_use_cached(&self) -> Result<(), JwtError> {
+        let error = match self.refresh_keys().await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        let usable = !self.keys.read().unwrap().is_empty()
+            && self
+                .breaker_state
+                .lock()
+                .unwrap()
+                .within_grace(self.config.clock.now(), &self.breaker);
+        if !usable {
+            return Err(error);
+        }
+        tracing::warn!("無法取得公鑰，沿用快取的公鑰：{}", error);
+        Ok(())
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<TokenData<Claims>, JwtError> {
+        // kid 不在快取中通常代表公鑰已輪替，先重新取得再驗證
+        let kid = decode_header(token).ok().and_then(|header| header.kid);
+        let needs_refresh = {
+            let keys = self.keys.read().unwrap();
+            keys.is_empty() || kid.is_some_and(|kid| !keys.contains_key(&kid))
+        };
+        if needs_refresh {
+            self.refresh_on_demand().await?;
+        }
+
+        let cached = self.keys.read().unwrap().clone();
+        let token_data = match decode_with_keys(token, &cached, &self.config) {
+            Err(JwtError::NoValidKeyError) if !needs_refresh => {
+                // 快取中的公鑰可能已輪替，重新取得後再試一次
+                self.refresh_on_demand().await?;
+                let refreshed = self.keys.read().unwrap().clone();
+                decode_with_keys(token, &refreshed, &self.config)
+            }
+            result => result,
+        }?;
+        check_revocation(&token_data.claims, &self.config).await?;
+        Ok(token_data)
+    }
+}
+
+fn load_persisted_keys(path: &Path) -> std::io::Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(std::io::Error::from)
+}
+
+fn persist_keys(path: &Path, keys: &HashMap<String, String>) -> std::io::Result<()> {
+    let content = serde_json::to_string_pretty(keys)?;
+    std::fs::write(path, content)
+}
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JwtAuth(pub Claims);
 
@@ -123,11 +793,103 @@ impl JwtAuth {
     pub fn new() -> Self {
         JwtAuth(Claims {
             sub: "".to_string(),
-            aud: "".to_string(),
+            aud: Vec::new(),
             exp: 0,
             iat: 0,
             email: "".to_string(),
             name: None,
+            roles: Vec::new(),
+            jti: None,
+            iss: None,
+            email_verified: None,
+            auth_time: None,
+            firebase: None,
+        })
+    }
+
+    pub fn require_role(&self, role: &str) -> Result<(), JwtError> {
+        if self.0.has_role(role) {
+            Ok(())
+        } else {
+            Err(JwtError::Forbidden(role.to_string()))
+        }
+    }
+}
+
+// 以型別表示所需的角色，讓 RequireRole 可以直接搭配 middleware::from_extractor 使用：
+//
+// struct Admin;
+// impl Role for Admin {
+//     const NAME: &'static str = "admin";
+// }
+// router.route_layer(middleware::from_extractor::<RequireRole<Admin>>())
+pub trait Role: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+// 驗證 JWT 並要求 roles claim 中包含 R::NAME，缺少時回傳 403
+#[derive(Debug, Clone)]
+pub struct RequireRole<R: Role> {
+    pub claims: Claims,
+    _role: PhantomData<R>,
+}
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    S: Send + Sync,
+    R: Role,
+{
+    type Rejection = JwtError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let auth = JwtAuth::from_request_parts(parts, state).await?;
+        auth.require_role(R::NAME)?;
+        Ok(RequireRole {
+            claims: auth.0,
+            _role: PhantomData,
+        })
+    }
+}
+
+// 以型別指定特定路由接受的 aud，覆寫 JwtVerifier 設定中的 audience：
+//
+// struct AdminConsole;
+// impl Audience for AdminConsole {
+//     const AUDIENCES: &'static [&'static str] = &["admin-console"];
+// }
+// async fn handler(auth: JwtAuthFor<AdminConsole>) { ... }
+pub trait Audience: Send + Sync + 'static {
+    const AUDIENCES: &'static [&'static str];
+}
+
+#[derive(Debug, Clone)]
+pub struct JwtAuthFor<A: Audience> {
+    pub claims: Claims,
+    _audience: PhantomData<A>,
+}
+
+#[async_trait]
+impl<S, A> FromRequestParts<S> for JwtAuthFor<A>
+where
+    S: Send + Sync,
+    A: Audience,
+{
+    type Rejection = JwtError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // 不沿用 extensions 中的 Claims：那是以全域 audience 驗證的結果
+        let verifier = parts
+            .extensions
+            .get::<JwtVerifier>()
+            .cloned()
+            .unwrap_or_else(|| JwtVerifier::new(JwtConfig::default()))
+            .with_audiences(A::AUDIENCES.iter().map(|aud| aud.to_string()).collect());
+        let token = extract_token(parts, Some(verifier.config()))?;
+        let token_data = verifier.verify(&token).await?;
+        Ok(JwtAuthFor {
+            claims: token_data.claims,
+            _audience: PhantomData,
         })
     }
 }
@@ -140,26 +902,134 @@ where
     type Rejection = JwtError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let auth_header = parts
-            .headers
-            .get("Authorization")
-            .ok_or(JwtError::MissingToken)?
-            .to_str()
-            .map_err(|_| JwtError::InvalidToken)?;
+        // 同一個請求中已驗證過（例如 from_extractor 層），直接沿用，不再重複驗證
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(JwtAuth(claims.clone()));
+        }
+
+        // 若以 Extension 提供了 JwtVerifier，則使用其設定與公鑰快取
+        let verifier = parts.extensions.get::<JwtVerifier>().cloned();
+        let token = extract_token(parts, verifier.as_ref().map(JwtVerifier::config))?;
+        let claims = match verifier {
+            Some(verifier) => verifier.verify(&token).await?.claims,
+            None => verify_id_token(&token, &JwtConfig::default()).await?,
+        };
+
+        // 將驗證後的 Claims 放入 request extensions，讓後續的 middleware 與 handler 以 Extension<Claims> 讀取
+        parts.extensions.insert(claims.clone());
+        Ok(JwtAuth(claims))
+    }
+}
+
+// 驗證 JWT 一次並將 Claims 放入 request extensions，handler 以 Extension<Claims> 讀取即可；
+// 驗證失敗時直接回傳 JwtError 對應的回應，不會進入 handler
+//
+// router.route_layer(jwt_auth_layer()).layer(Extension(verifier))
+pub fn jwt_auth_layer() -> FromExtractorLayer<JwtAuth, ()> {
+    middleware::from_extractor::<JwtAuth>()
+}
 
-        if !auth_header.starts_with("Bearer ") {
-            return Err(JwtError::InvalidToken);
+// 優先使用設定的標頭（預設為 Authorization: Bearer），沒有時再依設定從 cookie、query 參數讀取
+fn extract_token(parts: &Parts, config: Option<&JwtConfig>) -> Result<String, JwtError> {
+    let (header_name, require_bearer) = config
+        .map(|c| (c.header_name.as_str(), c.require_bearer))
+        .unwrap_or(("Authorization", true));
+    if let Some(auth_header) = parts.headers.get(header_name) {
+        let auth_header = auth_header.to_str().map_err(|_| JwtError::InvalidToken)?;
+        if !require_bearer {
+            return Ok(auth_header.trim().to_string());
         }
+        return auth_header
+            .strip_prefix("Bearer ")
+            .map(str::to_string)
+            .ok_or(JwtError::InvalidToken);
+    }
+
+    let cookie = config.and_then(|c| {
+        let value = find_cookie(parts, c.cookie_name.as_deref()?)?;
+        Some((value, c.cookie_encoding))
+    });
+    if let Some((value, encoding)) = cookie {
+        return decode_cookie(value, encoding);
+    }
 
-        let token = &auth_header["Bearer ".len()..];
-        let token_data = extract_jwt_token(token.to_string()).await?;
-        Ok(JwtAuth(token_data.claims))
+    config
+        .and_then(|c| find_query_param(parts, c.query_param.as_deref()?))
+        .ok_or(JwtError::MissingToken)
+}
+
+fn decode_cookie(value: &str, encoding: CookieEncoding) -> Result<String, JwtError> {
+    match encoding {
+        CookieEncoding::Raw => Ok(value.to_string()),
+        CookieEncoding::Base64Url => {
+            // 容許帶有 padding 的值
+            let bytes = URL_SAFE_NO_PAD
+                .decode(value.trim_end_matches('='))
+                .map_err(|_| JwtError::InvalidToken)?;
+            String::from_utf8(bytes).map_err(|_| JwtError::InvalidToken)
+        }
     }
 }
+
+fn find_query_param(parts: &Parts, name: &str) -> Option<String> {
+    let Query(mut params) = Query::<HashMap<String, String>>::try_from_uri(&parts.uri).ok()?;
+    params.remove(name).filter(|value| !value.is_empty())
+}
+
+fn find_cookie<'a>(parts: &'a Parts, name: &str) -> Option<&'a str> {
+    parts
+        .headers
+        .get_all(axum::http::header::COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::MockClock;
+    use crate::test_support::{
+        spawn_mock_server, TEST_EC_PRIVATE_KEY, TEST_EC_PUBLIC_KEY, TEST_RSA_PRIVATE_KEY,
+        TEST_RSA_PUBLIC_KEY,
+    };
     use axum::response::IntoResponse; // 改為
+    use axum::{
+        extract::Request,
+        middleware::{self, Next},
+        response::Response,
+        routing::get,
+        Extension, Json, Router,
+    };
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    fn sign_test_token(claims: &Claims) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        let key = EncodingKey::from_rsa_pem(TEST_RSA_PRIVATE_KEY.as_bytes()).unwrap();
+        encode(&header, claims, &key).unwrap()
+    }
+
+    fn test_key_set() -> HashMap<String, String> {
+        HashMap::from([("test-key".to_string(), TEST_RSA_PUBLIC_KEY.to_string())])
+    }
+
+    // 以 keys 回應 /keys 的公鑰端點，回傳完整的 keys_url
+    async fn spawn_key_server(keys: HashMap<String, String>) -> String {
+        let app = Router::new().route("/keys", get(move || async move { Json(keys) }));
+        format!("{}/keys", spawn_mock_server(app).await)
+    }
+
+    fn test_config(keys_url: String) -> JwtConfig {
+        JwtConfig {
+            audience: HashSet::from(["example_audience".to_string()]),
+            keys_url,
+            ..Default::default()
+        }
+    }
     #[tokio::test]
     async fn test_claims_mock() {
         let claims = Claims::mock();
@@ -176,6 +1046,115 @@ mod tests {
         assert_eq!(claims.exp - claims.iat, 3600);
     }
 
+    #[test]
+    fn test_decode_unverified_reads_claims_without_key() {
+        let mut claims = Claims::mock_at(Utc::now() - Duration::hours(2));
+        claims.roles = vec!["admin".to_string()];
+        let decoded = Claims::decode_unverified(&sign_test_token(&claims)).unwrap();
+        assert_eq!(decoded.sub, claims.sub);
+        assert_eq!(decoded.email, claims.email);
+        assert_eq!(decoded.roles, claims.roles);
+
+        assert!(matches!(
+            Claims::decode_unverified("not.a.jwt"),
+            Err(JwtError::InvalidToken)
+        ));
+        let payload_only = format!(
+            "{}.{}.sig",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256"}"#),
+            URL_SAFE_NO_PAD.encode(r#"{"sub":"1"}"#)
+        );
+        assert!(matches!(
+            Claims::decode_unverified(&payload_only),
+            Err(JwtError::ValidationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_extractor_never_accepts_unverified_tokens() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(test_config(keys_url));
+
+        // 以其他金鑰簽出的 token：decode_unverified 讀得到內容，但 extractor 必須拒絕
+        let forged_key = EncodingKey::from_ec_pem(TEST_EC_PRIVATE_KEY.as_bytes()).unwrap();
+        let forged = encode(&Header::new(Algorithm::ES256), &Claims::mock(), &forged_key).unwrap();
+        assert_eq!(
+            Claims::decode_unverified(&forged).unwrap().sub,
+            "1234567890"
+        );
+
+        let (mut parts, _) = axum::http::Request::builder()
+            .header("Authorization", format!("Bearer {}", forged))
+            .body(())
+            .unwrap()
+            .into_parts();
+        parts.extensions.insert(verifier);
+        assert!(JwtAuth::from_request_parts(&mut parts, &()).await.is_err());
+        assert!(parts.extensions.get::<Claims>().is_none());
+    }
+
+    #[test]
+    fn test_remaining_lifetime() {
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let clock = MockClock::new(now);
+
+        let mut claims = Claims::mock_at(now);
+        claims.exp = (now + Duration::minutes(10)).timestamp() as usize;
+        assert_eq!(
+            claims.remaining_lifetime(&clock),
+            std::time::Duration::from_secs(600)
+        );
+        assert_eq!(claims.expires_at(), Some(now + Duration::minutes(10)));
+        assert!(!claims.is_expired(&clock, 0));
+
+        claims.exp = (now - Duration::minutes(1)).timestamp() as usize;
+        assert_eq!(claims.remaining_lifetime(&clock), std::time::Duration::ZERO);
+        assert!(claims.is_expired(&clock, 0));
+
+        // 超出範圍的 exp 不應 panic，並視為已過期
+        claims.exp = usize::MAX;
+        assert_eq!(claims.expires_at(), None);
+        assert!(claims.is_expired(&clock, 60));
+    }
+
+    #[test]
+    fn test_token_expiry_with_mock_clock() {
+        let issued_at = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let clock = MockClock::new(issued_at);
+        let config = JwtConfig {
+            clock: Arc::new(clock.clone()),
+            ..test_config(String::new())
+        };
+        let claims = Claims::mock_at(issued_at);
+        let token = sign_test_token(&claims);
+
+        assert_eq!(claims.expires_in(&clock), Duration::hours(1));
+        assert!(decode_with_keys(&token, &test_key_set(), &config).is_ok());
+
+        // 過期後仍在 leeway 範圍內
+        clock.advance(Duration::seconds(3600 + 30));
+        assert_eq!(claims.expires_in(&clock), Duration::zero());
+        assert_eq!(claims.remaining_lifetime(&clock), std::time::Duration::ZERO);
+        assert!(claims.is_expired(&clock, 0));
+        assert!(!claims.is_expired(&clock, 60));
+        assert!(decode_with_keys(&token, &test_key_set(), &config).is_ok());
+
+        clock.advance(Duration::seconds(31));
+        match decode_with_keys(&token, &test_key_set(), &config) {
+            Err(JwtError::ValidationError(e)) => {
+                assert_eq!(e.kind(), &jsonwebtoken::errors::ErrorKind::ExpiredSignature)
+            }
+            other => panic!(
+                "超過 leeway 應該視為過期，實際得到 {:?}",
+                other.map(|t| t.claims)
+            ),
+        }
+    }
+
     #[tokio::test]
     async fn test_jwt_auth_new() {
         let jwt_auth = JwtAuth::new();
@@ -186,7 +1165,7 @@ mod tests {
         assert_eq!(jwt_auth.0.name, None);
         assert_eq!(jwt_auth.0.exp, 0);
         assert_eq!(jwt_auth.0.iat, 0);
-        assert_eq!(jwt_auth.0.aud, "");
+        assert!(jwt_auth.0.audiences().is_empty());
     }
 
     #[tokio::test]
@@ -196,6 +1175,14 @@ mod tests {
             (JwtError::MissingToken, "Missing authorization token"),
             (JwtError::InvalidToken, "Invalid token"),
             (JwtError::NoValidKeyError, "No valid public key found"),
+            (
+                JwtError::MissingClaim("email".to_string()),
+                "Missing required claim: email",
+            ),
+            (
+                JwtError::KeysUnavailable,
+                "Public keys temporarily unavailable",
+            ),
         ];
 
         for (error, expected_message) in errors {
@@ -210,6 +1197,20 @@ mod tests {
             (JwtError::MissingToken, StatusCode::UNAUTHORIZED),
             (JwtError::InvalidToken, StatusCode::UNAUTHORIZED),
             (JwtError::NoValidKeyError, StatusCode::INTERNAL_SERVER_ERROR),
+            (
+                JwtError::Forbidden("admin".to_string()),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                JwtError::RequiredClaimMismatch("plan".to_string()),
+                StatusCode::FORBIDDEN,
+            ),
+            (JwtError::Revoked, StatusCode::UNAUTHORIZED),
+            (
+                JwtError::MissingClaim("email".to_string()),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (JwtError::KeysUnavailable, StatusCode::SERVICE_UNAVAILABLE),
         ];
 
         for (error, expected_status) in test_cases {
@@ -246,4 +1247,917 @@ mod tests {
         let error = JwtError::ValidationError(jwt_error);
         assert!(error.source().is_some());
     }
+
+    #[tokio::test]
+    async fn test_verifier_uses_persisted_keys_without_network() {
+        let path =
+            std::env::temp_dir().join(format!("rex_axum_sdk_keys_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // 第一個實例從 mock 端點取得公鑰並寫入磁碟
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let online = JwtVerifier::new(test_config(keys_url)).with_persisted_keys(&path);
+        online.refresh_keys().await.expect("應該能取得公鑰");
+        assert!(path.exists(), "取得公鑰後應該寫入磁碟");
+
+        // 第二個實例指向無法連線的位址，只能依靠磁碟上的公鑰
+        let offline = JwtVerifier::new(test_config("http://127.0.0.1:9/keys".to_string()))
+            .with_persisted_keys(&path);
+        let token = sign_test_token(&Claims::mock());
+        let token_data = offline
+            .verify(&token)
+            .await
+            .expect("應該使用快取公鑰驗證成功");
+        assert_eq!(token_data.claims.sub, "1234567890");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    // 模擬日誌 middleware：讀取前一層注入的 Claims，並透過回應標頭回報使用者 id
+    async fn log_user_id(request: Request, next: Next) -> Response {
+        let user_id = request
+            .extensions()
+            .get::<Claims>()
+            .map(|claims| claims.sub.clone());
+        let mut response = next.run(request).await;
+        if let Some(user_id) = user_id {
+            response
+                .headers_mut()
+                .insert("x-user-id", user_id.parse().unwrap());
+        }
+        response
+    }
+
+    #[tokio::test]
+    async fn test_claims_injected_into_extensions() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(test_config(keys_url));
+
+        let app = Router::new()
+            .route(
+                "/me",
+                get(
+                    |auth: JwtAuth, Extension(claims): Extension<Claims>| async move {
+                        assert_eq!(auth.0.sub, claims.sub);
+                        claims.email
+                    },
+                ),
+            )
+            .route_layer(middleware::from_fn(log_user_id))
+            .route_layer(middleware::from_extractor::<JwtAuth>())
+            .layer(Extension(verifier));
+        let base_url = spawn_mock_server(app).await;
+
+        let token = sign_test_token(&Claims::mock());
+        let response = reqwest::Client::new()
+            .get(format!("{}/me", base_url))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-user-id"], "1234567890");
+        assert_eq!(response.text().await.unwrap(), "user@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth_layer() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(test_config(keys_url));
+
+        let app = Router::new()
+            .route(
+                "/me",
+                get(|Extension(claims): Extension<Claims>| async move { claims.sub }),
+            )
+            .route_layer(jwt_auth_layer())
+            .layer(Extension(verifier));
+        let base_url = spawn_mock_server(app).await;
+
+        let token = sign_test_token(&Claims::mock());
+        let response = reqwest::Client::new()
+            .get(format!("{}/me", base_url))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "1234567890");
+
+        // 沒有 token 時由 layer 直接回傳 401
+        let response = reqwest::get(format!("{}/me", base_url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_base64url_cookie_token() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(JwtConfig {
+            cookie_name: Some("session".to_string()),
+            cookie_encoding: CookieEncoding::Base64Url,
+            ..test_config(keys_url)
+        });
+
+        let request_with_cookie = |cookie: String| {
+            let (mut parts, _) = axum::http::Request::builder()
+                .header("Cookie", cookie)
+                .body(())
+                .unwrap()
+                .into_parts();
+            parts.extensions.insert(verifier.clone());
+            parts
+        };
+
+        let token = sign_test_token(&Claims::mock());
+        let encoded = URL_SAFE_NO_PAD.encode(token);
+        let mut parts = request_with_cookie(format!("theme=dark; session={}", encoded));
+        let JwtAuth(claims) = JwtAuth::from_request_parts(&mut parts, &())
+            .await
+            .expect("base64url 編碼的 cookie 應該能解碼並驗證");
+        assert_eq!(claims.sub, "1234567890");
+
+        let mut parts = request_with_cookie("session=not*valid*base64".to_string());
+        assert!(matches!(
+            JwtAuth::from_request_parts(&mut parts, &()).await,
+            Err(JwtError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_query_param_token() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(JwtConfig {
+            query_param: Some("token".to_string()),
+            ..test_config(keys_url)
+        });
+
+        let request = |uri: String, header: Option<String>| {
+            let mut builder = axum::http::Request::builder().uri(uri);
+            if let Some(header) = header {
+                builder = builder.header("Authorization", header);
+            }
+            let (mut parts, _) = builder.body(()).unwrap().into_parts();
+            parts.extensions.insert(verifier.clone());
+            parts
+        };
+
+        let token = sign_test_token(&Claims::mock());
+        let mut parts = request(format!("/ws?room=1&token={}", token), None);
+        let JwtAuth(claims) = JwtAuth::from_request_parts(&mut parts, &())
+            .await
+            .expect("query 參數中的 token 應該能驗證");
+        assert_eq!(claims.sub, "1234567890");
+
+        // 有標頭時優先使用標頭，query 中的值不會被讀取
+        let mut parts = request(
+            format!("/ws?token={}", token),
+            Some("Bearer not-a-jwt".to_string()),
+        );
+        assert!(matches!(
+            JwtAuth::from_request_parts(&mut parts, &()).await,
+            Err(JwtError::InvalidToken)
+        ));
+        let mut parts = request(
+            "/ws?token=not-a-jwt".to_string(),
+            Some(format!("Bearer {}", token)),
+        );
+        assert!(JwtAuth::from_request_parts(&mut parts, &()).await.is_ok());
+
+        let mut parts = request("/ws?token=not-a-jwt".to_string(), None);
+        assert!(matches!(
+            JwtAuth::from_request_parts(&mut parts, &()).await,
+            Err(JwtError::InvalidToken)
+        ));
+
+        let mut parts = request("/ws?room=1".to_string(), None);
+        assert!(matches!(
+            JwtAuth::from_request_parts(&mut parts, &()).await,
+            Err(JwtError::MissingToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_custom_header_name_and_raw_token() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let token = sign_test_token(&Claims::mock());
+
+        let parts_with = |verifier: &JwtVerifier, name: &str, value: String| {
+            let (mut parts, _) = axum::http::Request::builder()
+                .header(name, value)
+                .body(())
+                .unwrap()
+                .into_parts();
+            parts.extensions.insert(verifier.clone());
+            parts
+        };
+
+        // 自訂標頭，仍要求 Bearer 前綴
+        let verifier = JwtVerifier::new(JwtConfig {
+            header_name: "X-Id-Token".to_string(),
+            ..test_config(keys_url.clone())
+        });
+        let mut parts = parts_with(&verifier, "X-Id-Token", format!("Bearer {}", token));
+        let JwtAuth(claims) = JwtAuth::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(claims.sub, "1234567890");
+        let mut parts = parts_with(&verifier, "Authorization", format!("Bearer {}", token));
+        assert!(matches!(
+            JwtAuth::from_request_parts(&mut parts, &()).await,
+            Err(JwtError::MissingToken)
+        ));
+
+        // 不帶 scheme 的原始 token
+        let verifier = JwtVerifier::new(JwtConfig {
+            header_name: "X-Id-Token".to_string(),
+            require_bearer: false,
+            ..test_config(keys_url)
+        });
+        let mut parts = parts_with(&verifier, "X-Id-Token", token.clone());
+        let JwtAuth(claims) = JwtAuth::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(claims.sub, "1234567890");
+    }
+
+    struct Admin;
+
+    impl Role for Admin {
+        const NAME: &'static str = "admin";
+    }
+
+    #[test]
+    fn test_require_role() {
+        let mut claims = Claims::mock();
+        claims.roles = vec!["editor".to_string(), "admin".to_string()];
+        let auth = JwtAuth(claims);
+        assert!(auth.require_role("admin").is_ok());
+        assert!(matches!(
+            auth.require_role("owner"),
+            Err(JwtError::Forbidden(role)) if role == "owner"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_require_role_extractor() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(test_config(keys_url));
+
+        let app = Router::new()
+            .route("/admin", get(|| async { "ok" }))
+            .route_layer(middleware::from_extractor::<RequireRole<Admin>>())
+            .layer(Extension(verifier));
+        let base_url = spawn_mock_server(app).await;
+
+        let status_for = |claims: Claims| {
+            let url = format!("{}/admin", base_url);
+            async move {
+                reqwest::Client::new()
+                    .get(url)
+                    .bearer_auth(sign_test_token(&claims))
+                    .send()
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        let mut admin = Claims::mock();
+        admin.roles = vec!["admin".to_string()];
+        assert_eq!(status_for(admin).await, StatusCode::OK);
+
+        let mut editor = Claims::mock();
+        editor.roles = vec!["editor".to_string()];
+        assert_eq!(status_for(editor).await, StatusCode::FORBIDDEN);
+
+        // roles 為空時不會序列化，token 中完全沒有 roles claim
+        let no_roles = Claims::mock();
+        assert_eq!(status_for(no_roles).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_required_claims() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(JwtConfig {
+            required_claims: HashMap::from([
+                ("plan".to_string(), serde_json::json!("enterprise")),
+                ("region".to_string(), serde_json::json!("eu")),
+            ]),
+            ..test_config(keys_url)
+        });
+
+        let sign_with = |extra: serde_json::Value| {
+            let mut claims = serde_json::to_value(Claims::mock()).unwrap();
+            claims
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            let key = EncodingKey::from_rsa_pem(TEST_RSA_PRIVATE_KEY.as_bytes()).unwrap();
+            encode(&Header::new(Algorithm::RS256), &claims, &key).unwrap()
+        };
+
+        let matching = sign_with(serde_json::json!({ "plan": "enterprise", "region": "eu" }));
+        let token_data = verifier.verify(&matching).await.unwrap();
+        assert_eq!(token_data.claims.sub, "1234567890");
+
+        let missing = sign_with(serde_json::json!({ "plan": "enterprise" }));
+        assert!(matches!(
+            verifier.verify(&missing).await,
+            Err(JwtError::RequiredClaimMismatch(claim)) if claim == "region"
+        ));
+
+        let mismatched = sign_with(serde_json::json!({ "plan": "free", "region": "eu" }));
+        assert!(matches!(
+            verifier.verify(&mismatched).await,
+            Err(JwtError::RequiredClaimMismatch(claim)) if claim == "plan"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_required_claim_names() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(JwtConfig {
+            required_claim_names: vec!["sub".to_string(), "email".to_string(), "iss".to_string()],
+            ..test_config(keys_url)
+        });
+
+        let mut complete = Claims::mock();
+        complete.iss = Some("https://securetoken.google.com/example_audience".to_string());
+        assert!(verifier.verify(&sign_test_token(&complete)).await.is_ok());
+
+        // Claims::mock 沒有 iss，序列化時整個欄位不存在
+        assert!(matches!(
+            verifier.verify(&sign_test_token(&Claims::mock())).await,
+            Err(JwtError::MissingClaim(claim)) if claim == "iss"
+        ));
+
+        let mut claims = complete.clone();
+        claims.email = String::new();
+        assert!(matches!(
+            verifier.verify(&sign_test_token(&claims)).await,
+            Err(JwtError::MissingClaim(claim)) if claim == "email"
+        ));
+
+        let mut claims = complete.clone();
+        claims.sub = String::new();
+        assert!(matches!(
+            verifier.verify(&sign_test_token(&claims)).await,
+            Err(JwtError::MissingClaim(claim)) if claim == "sub"
+        ));
+    }
+
+    #[test]
+    fn test_firebase_standard_claims() {
+        let claims: Claims = serde_json::from_value(serde_json::json!({
+            "name": "Test User",
+            "iss": "https://securetoken.google.com/leaveanote-4af85",
+            "aud": "leaveanote-4af85",
+            "auth_time": 1700000000,
+            "user_id": "uid-1",
+            "sub": "uid-1",
+            "iat": 1700000100,
+            "exp": 1700003700,
+            "email": "user@example.com",
+            "email_verified": true,
+            "firebase": {
+                "identities": { "email": ["user@example.com"] },
+                "sign_in_provider": "password"
+            }
+        }))
+        .unwrap();
+        assert!(claims.email_verified());
+        assert_eq!(claims.auth_time, Some(1700000000));
+        assert_eq!(claims.sign_in_provider(), Some("password"));
+        let firebase = claims.firebase.as_ref().unwrap();
+        assert_eq!(firebase.identities["email"], ["user@example.com"]);
+        assert_eq!(firebase.tenant, None);
+
+        // 沒有這些 claim 的舊 token 仍可解析
+        let claims: Claims = serde_json::from_value(serde_json::json!({
+            "sub": "1", "aud": "a", "exp": 0, "iat": 0, "email": "a@example.com"
+        }))
+        .unwrap();
+        assert!(!claims.email_verified());
+        assert_eq!(claims.auth_time, None);
+        assert_eq!(claims.sign_in_provider(), None);
+    }
+
+    #[test]
+    fn test_audience_string_or_array() {
+        let claims: Claims = serde_json::from_value(serde_json::json!({
+            "sub": "1", "aud": "project-a", "exp": 0, "iat": 0, "email": "a@example.com"
+        }))
+        .unwrap();
+        assert_eq!(claims.audience(), Some("project-a"));
+        assert_eq!(serde_json::to_value(&claims).unwrap()["aud"], "project-a");
+
+        let claims: Claims = serde_json::from_value(serde_json::json!({
+            "sub": "1", "aud": ["project-a", "project-b"], "exp": 0, "iat": 0, "email": "a@example.com"
+        }))
+        .unwrap();
+        assert_eq!(claims.audiences(), ["project-a", "project-b"]);
+        assert_eq!(claims.audience(), Some("project-a"));
+        assert_eq!(
+            serde_json::to_value(&claims).unwrap()["aud"],
+            serde_json::json!(["project-a", "project-b"])
+        );
+    }
+
+    // aud 為陣列時，只要其中一個在允許的清單中即可
+    #[tokio::test]
+    async fn test_array_audience_token() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(test_config(keys_url));
+
+        let mut claims = Claims::mock();
+        claims.aud = vec!["other".to_string(), "example_audience".to_string()];
+        let token_data = verifier.verify(&sign_test_token(&claims)).await.unwrap();
+        assert_eq!(token_data.claims.audiences().len(), 2);
+
+        claims.aud = vec!["other".to_string(), "another".to_string()];
+        assert!(is_invalid_audience(
+            verifier.verify(&sign_test_token(&claims)).await
+        ));
+    }
+
+    fn sign_for_audience(aud: &str) -> String {
+        let mut claims = Claims::mock();
+        claims.aud = vec![aud.to_string()];
+        sign_test_token(&claims)
+    }
+
+    fn is_invalid_audience(result: Result<TokenData<Claims>, JwtError>) -> bool {
+        matches!(
+            result,
+            Err(JwtError::ValidationError(e))
+                if *e.kind() == jsonwebtoken::errors::ErrorKind::InvalidAudience
+        )
+    }
+
+    #[tokio::test]
+    async fn test_single_and_multiple_audiences() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+
+        let single = JwtVerifier::new(test_config(keys_url.clone()));
+        assert!(single
+            .verify(&sign_for_audience("example_audience"))
+            .await
+            .is_ok());
+        assert!(is_invalid_audience(
+            single.verify(&sign_for_audience("project-b")).await
+        ));
+
+        let multiple = JwtVerifier::new(JwtConfig {
+            audience: HashSet::from(["project-a".to_string(), "project-b".to_string()]),
+            ..test_config(keys_url)
+        });
+        assert!(multiple
+            .verify(&sign_for_audience("project-a"))
+            .await
+            .is_ok());
+        assert!(multiple
+            .verify(&sign_for_audience("project-b"))
+            .await
+            .is_ok());
+        assert!(is_invalid_audience(
+            multiple.verify(&sign_for_audience("project-c")).await
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_audience_and_issuer_validators() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+
+        let verifier = JwtVerifier::new(JwtConfig {
+            audience: HashSet::new(),
+            audience_validator: Some(ClaimValidator::new(|aud| aud.starts_with("tenant-"))),
+            ..test_config(keys_url.clone())
+        });
+        assert!(verifier
+            .verify(&sign_for_audience("tenant-acme"))
+            .await
+            .is_ok());
+        assert!(is_invalid_audience(
+            verifier.verify(&sign_for_audience("other-acme")).await
+        ));
+
+        // 靜態清單與 closure 並用時，符合其中之一即可
+        let combined = JwtVerifier::new(JwtConfig {
+            audience_validator: Some(ClaimValidator::new(|aud| aud.starts_with("tenant-"))),
+            ..test_config(keys_url.clone())
+        });
+        assert!(combined
+            .verify(&sign_for_audience("example_audience"))
+            .await
+            .is_ok());
+        assert!(combined
+            .verify(&sign_for_audience("tenant-acme"))
+            .await
+            .is_ok());
+
+        let issuer = JwtVerifier::new(JwtConfig {
+            issuer_validator: Some(ClaimValidator::new(|iss| {
+                iss.starts_with("https://securetoken.google.com/")
+            })),
+            ..test_config(keys_url)
+        });
+        let mut claims = Claims::mock();
+        claims.iss = Some("https://securetoken.google.com/example".to_string());
+        assert!(issuer.verify(&sign_test_token(&claims)).await.is_ok());
+        for iss in [Some("https://evil.example.com".to_string()), None] {
+            claims.iss = iss;
+            assert!(matches!(
+                issuer.verify(&sign_test_token(&claims)).await,
+                Err(JwtError::ValidationError(e))
+                    if *e.kind() == jsonwebtoken::errors::ErrorKind::InvalidIssuer
+            ));
+        }
+    }
+
+    struct AdminConsole;
+
+    impl Audience for AdminConsole {
+        const AUDIENCES: &'static [&'static str] = &["admin-console"];
+    }
+
+    #[tokio::test]
+    async fn test_route_level_audience_override() {
+        let keys_url = spawn_key_server(test_key_set()).await;
+        let verifier = JwtVerifier::new(test_config(keys_url));
+
+        let app = Router::new()
+            .route("/app", get(|auth: JwtAuth| async move { auth.0.sub }))
+            .route(
+                "/admin",
+                get(|auth: JwtAuthFor<AdminConsole>| async move { auth.claims.sub }),
+            )
+            .layer(Extension(verifier));
+        let base_url = spawn_mock_server(app).await;
+
+        let status_for = |path: &str, aud: &str| {
+            let request = reqwest::Client::new()
+                .get(format!("{}{}", base_url, path))
+                .bearer_auth(sign_for_audience(aud));
+            async move { request.send().await.unwrap().status() }
+        };
+
+        assert_eq!(status_for("/app", "example_audience").await, StatusCode::OK);
+        assert_eq!(
+            status_for("/app", "admin-console").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(status_for("/admin", "admin-console").await, StatusCode::OK);
+        assert_eq!(
+            status_for("/admin", "example_audience").await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_client_fetches_keys_from_mock() {
+        let keys = test_key_set();
+        let keys_app = Router::new().route(
+            "/keys",
+            get(move |headers: axum::http::HeaderMap| async move {
+                // 只有透過自訂 client 發出的請求才帶有這個標頭
+                if headers.get("x-proxy-auth").is_none() {
+                    return StatusCode::FORBIDDEN.into_response();
+                }
+                Json(keys).into_response()
+            }),
+        );
+        let keys_url = format!("{}/keys", spawn_mock_server(keys_app).await);
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-proxy-auth", "secret".parse().unwrap());
+        let config = JwtConfig {
+            client: reqwest::Client::builder()
+                .default_headers(headers)
+                .build()
+                .unwrap(),
+            ..test_config(keys_url.clone())
+        };
+
+        let token = sign_test_token(&Claims::mock());
+        let token_data = extract_jwt_token_with(token.clone(), &config)
+            .await
+            .expect("應該透過自訂 client 取得公鑰並驗證成功");
+        assert_eq!(token_data.claims.sub, "1234567890");
+
+        // 預設 client 沒有該標頭，取得公鑰會失敗
+        assert!(matches!(
+            extract_jwt_token_with(token, &test_config(keys_url)).await,
+            Err(JwtError::FetchError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_verify_id_token_without_axum() {
+        let config = test_config(spawn_key_server(test_key_set()).await);
+
+        let claims = verify_id_token(&sign_test_token(&Claims::mock()), &config)
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "1234567890");
+        assert_eq!(claims.email, "user@example.com");
+
+        let expired = Claims::mock_at(Utc::now() - Duration::hours(2));
+        assert!(matches!(
+            verify_id_token(&sign_test_token(&expired), &config).await,
+            Err(JwtError::ValidationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_kid_selects_key_and_unknown_kid_refreshes() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let fetches_clone = fetches.clone();
+        let keys_app = Router::new().route(
+            "/keys",
+            get(move || {
+                fetches_clone.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let mut keys = test_key_set();
+                    keys.insert("ec-key".to_string(), TEST_EC_PUBLIC_KEY.to_string());
+                    Json(keys)
+                }
+            }),
+        );
+        let clock = MockClock::new(Utc::now());
+        let verifier = JwtVerifier::new(JwtConfig {
+            clock: Arc::new(clock.clone()),
+            ..test_config(format!("{}/keys", spawn_mock_server(keys_app).await))
+        });
+        let sign_with_kid = |kid: &str| {
+            let mut header = Header::new(Algorithm::RS256);
+            header.kid = Some(kid.to_string());
+            let key = EncodingKey::from_rsa_pem(TEST_RSA_PRIVATE_KEY.as_bytes()).unwrap();
+            encode(&header, &Claims::mock(), &key).unwrap()
+        };
+
+        assert!(verifier.verify(&sign_with_kid("test-key")).await.is_ok());
+        assert!(verifier.verify(&sign_with_kid("test-key")).await.is_ok());
+        assert_eq!(
+            fetches.load(Ordering::SeqCst),
+            1,
+            "已知的 kid 不應重新取得公鑰"
+        );
+
+        // kid 指向另一把公鑰時不會改用其他公鑰驗證
+        assert!(matches!(
+            verifier.verify(&sign_with_kid("ec-key")).await,
+            Err(JwtError::NoValidKeyError)
+        ));
+
+        // 距離上次取得未滿最短間隔：未知的 kid 不重新取得，直接逐一嘗試快取中的公鑰
+        assert!(verifier.verify(&sign_with_kid("rotated-key")).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // 超過間隔後，未知的 kid 先重新取得公鑰，仍找不到時逐一嘗試所有公鑰
+        clock.advance(Duration::seconds(61));
+        assert!(verifier.verify(&sign_with_kid("rotated-key")).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    // kid 由用戶端控制：大量帶著隨機 kid 的 token（含同時送達的）只會讓公鑰端點被請求一次
+    #[tokio::test]
+    async fn test_bogus_kids_do_not_amplify_key_fetches() {
+        use std::sync::atomic::AtomicUsize;
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let fetches_clone = fetches.clone();
+        let keys_app = Router::new().route(
+            "/keys",
+            get(move || {
+                fetches_clone.fetch_add(1, Ordering::SeqCst);
+                async move { Json(test_key_set()) }
+            }),
+        );
+        let verifier = JwtVerifier::new(test_config(format!(
+            "{}/keys",
+            spawn_mock_server(keys_app).await
+        )));
+        let sign_with_kid = |kid: String| {
+            let mut header = Header::new(Algorithm::RS256);
+            header.kid = Some(kid);
+            let key = EncodingKey::from_rsa_pem(TEST_RSA_PRIVATE_KEY.as_bytes()).unwrap();
+            encode(&header, &Claims::mock(), &key).unwrap()
+        };
+
+        let tokens: Vec<String> = (0..20)
+            .map(|i| sign_with_kid(format!("bogus-{}", i)))
+            .collect();
+        let results =
+            futures::future::join_all(tokens.iter().map(|token| verifier.verify(token))).await;
+        assert!(results.iter().all(Result::is_ok));
+        for token in &tokens {
+            assert!(verifier.verify(token).await.is_ok());
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    // 端點故障時連續失敗 3 次後斷路，斷路期間不再請求並沿用快取公鑰；超過 grace 後才回傳錯誤
+    #[tokio::test]
+    async fn test_key_fetch_breaker_serves_cached_keys_while_open() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let down = Arc::new(AtomicBool::new(false));
+        let (fetches_clone, down_clone) = (fetches.clone(), down.clone());
+        let keys_app = Router::new().route(
+            "/keys",
+            get(move || {
+                fetches_clone.fetch_add(1, Ordering::SeqCst);
+                let down = down_clone.load(Ordering::SeqCst);
+                async move {
+                    if down {
+                        Err(StatusCode::SERVICE_UNAVAILABLE)
+                    } else {
+                        Ok(Json(test_key_set()))
+                    }
+                }
+            }),
+        );
+        let clock = MockClock::new(Utc::now());
+        let verifier = JwtVerifier::new(JwtConfig {
+            clock: Arc::new(clock.clone()),
+            ..test_config(format!("{}/keys", spawn_mock_server(keys_app).await))
+        })
+        .with_key_fetch_breaker(KeyFetchBreaker {
+            failure_threshold: 3,
+            cooldown: std::time::Duration::from_secs(30),
+            grace: std::time::Duration::from_secs(3600),
+        })
+        // 這裡只測斷路器，不限制未知 kid 的重新取得頻率
+        .with_min_refresh_interval(std::time::Duration::ZERO);
+        // 未知的 kid 每次都會觸發重新取得公鑰，找不到時再逐一嘗試快取中的公鑰
+        let token = |clock: &MockClock| {
+            let mut header = Header::new(Algorithm::RS256);
+            header.kid = Some("rotated-key".to_string());
+            let key = EncodingKey::from_rsa_pem(TEST_RSA_PRIVATE_KEY.as_bytes()).unwrap();
+            encode(&header, &Claims::mock_at(clock.now()), &key).unwrap()
+        };
+
+        assert!(verifier.verify(&token(&clock)).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        down.store(true, Ordering::SeqCst);
+        for _ in 0..3 {
+            assert!(verifier.verify(&token(&clock)).await.is_ok());
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 4);
+
+        // 斷路中：不發出請求，仍以快取公鑰驗證
+        assert!(verifier.verify(&token(&clock)).await.is_ok());
+        assert_eq!(
+            fetches.load(Ordering::SeqCst),
+            4,
+            "斷路期間不應請求公鑰端點"
+        );
+
+        // cooldown 後嘗試一次，仍失敗時立即再次斷路
+        clock.advance(Duration::seconds(31));
+        assert!(verifier.verify(&token(&clock)).await.is_ok());
+        assert!(verifier.verify(&token(&clock)).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 5);
+
+        // 超過 grace 後快取公鑰不再可用
+        clock.advance(Duration::hours(2));
+        assert!(matches!(
+            verifier.verify(&token(&clock)).await,
+            Err(JwtError::FetchError(_))
+        ));
+        assert!(matches!(
+            verifier.verify(&token(&clock)).await,
+            Err(JwtError::KeysUnavailable)
+        ));
+        assert_eq!(fetches.load(Ordering::SeqCst), 6);
+
+        // 端點恢復後第一次成功取得即關閉斷路器
+        down.store(false, Ordering::SeqCst);
+        clock.advance(Duration::seconds(31));
+        assert!(verifier.verify(&token(&clock)).await.is_ok());
+        assert!(verifier.verify(&token(&clock)).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 8);
+    }
+
+    #[tokio::test]
+    async fn test_es256_token_accepted_alongside_rs256() {
+        let mut keys = test_key_set();
+        keys.insert("ec-key".to_string(), TEST_EC_PUBLIC_KEY.to_string());
+        let config = JwtConfig {
+            allowed_algorithms: vec![Algorithm::RS256, Algorithm::ES256],
+            ..test_config(spawn_key_server(keys).await)
+        };
+
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some("ec-key".to_string());
+        let key = EncodingKey::from_ec_pem(TEST_EC_PRIVATE_KEY.as_bytes()).unwrap();
+        let token = encode(&header, &Claims::mock(), &key).unwrap();
+
+        let claims = verify_id_token(&token, &config).await.unwrap();
+        assert_eq!(claims.sub, "1234567890");
+        let claims = verify_id_token(&sign_test_token(&Claims::mock()), &config)
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "1234567890");
+
+        // 預設只接受 RS256，未列在 allowed_algorithms 中的演算法即使簽章正確也拒絕
+        let rs256_only = test_config(config.keys_url.clone());
+        assert!(matches!(
+            verify_id_token(&token, &rs256_only).await,
+            Err(JwtError::ValidationError(e))
+                if *e.kind() == jsonwebtoken::errors::ErrorKind::InvalidAlgorithm
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_forged_none_and_hs256_tokens_rejected() {
+        let config = test_config(spawn_key_server(test_key_set()).await);
+
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&Claims::mock()).unwrap());
+        let none_header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+        let forged = format!("{}.{}.", none_header, payload);
+        assert!(matches!(
+            verify_id_token(&forged, &config).await,
+            Err(JwtError::InvalidToken)
+        ));
+
+        // 以公鑰當作 HMAC 密鑰偽造的 token
+        let key = EncodingKey::from_secret(TEST_RSA_PUBLIC_KEY.as_bytes());
+        let forged = encode(&Header::new(Algorithm::HS256), &Claims::mock(), &key).unwrap();
+        assert!(matches!(
+            verify_id_token(&forged, &config).await,
+            Err(JwtError::ValidationError(_))
+        ));
+
+        // 即使明確加入 HS256，與 RS256 混用時仍拒絕，避免公鑰被當成 HMAC 密鑰
+        let mixed = JwtConfig {
+            allowed_algorithms: vec![Algorithm::RS256, Algorithm::HS256],
+            ..config
+        };
+        assert!(matches!(
+            verify_id_token(&forged, &mixed).await,
+            Err(JwtError::ValidationError(e))
+                if *e.kind() == jsonwebtoken::errors::ErrorKind::InvalidAlgorithm
+        ));
+        assert!(verify_id_token(&sign_test_token(&Claims::mock()), &mixed)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hmac_only_config_accepts_shared_secret() {
+        let keys = HashMap::from([("shared".to_string(), "test-shared-secret".to_string())]);
+        let config = JwtConfig {
+            allowed_algorithms: vec![Algorithm::HS256],
+            ..test_config(spawn_key_server(keys).await)
+        };
+
+        let key = EncodingKey::from_secret(b"test-shared-secret");
+        let token = encode(&Header::new(Algorithm::HS256), &Claims::mock(), &key).unwrap();
+        let claims = verify_id_token(&token, &config).await.unwrap();
+        assert_eq!(claims.sub, "1234567890");
+
+        assert!(matches!(
+            verify_id_token(&sign_test_token(&Claims::mock()), &config).await,
+            Err(JwtError::ValidationError(e))
+                if *e.kind() == jsonwebtoken::errors::ErrorKind::InvalidAlgorithm
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_subject_is_rejected() {
+        let store = Arc::new(InMemoryRevocationStore::new());
+        let config = JwtConfig {
+            revocation_store: Some(store.clone()),
+            ..test_config(spawn_key_server(test_key_set()).await)
+        };
+
+        let mut other = Claims::mock();
+        other.sub = "other-user".to_string();
+        store.revoke("1234567890");
+
+        assert!(matches!(
+            verify_id_token(&sign_test_token(&Claims::mock()), &config).await,
+            Err(JwtError::Revoked)
+        ));
+        let claims = verify_id_token(&sign_test_token(&other), &config)
+            .await
+            .expect("未撤銷的使用者應該通過驗證");
+        assert_eq!(claims.sub, "other-user");
+
+        // 以 jti 撤銷單一 token，同一使用者的其他 token 不受影響
+        let mut with_jti = other.clone();
+        with_jti.jti = Some("token-1".to_string());
+        store.revoke("token-1");
+        assert!(matches!(
+            verify_id_token(&sign_test_token(&with_jti), &config).await,
+            Err(JwtError::Revoked)
+        ));
+
+        assert!(store.restore("1234567890"));
+        assert!(verify_id_token(&sign_test_token(&Claims::mock()), &config)
+            .await
+            .is_ok());
+    }
 }
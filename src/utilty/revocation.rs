@@ -0,0 +1,38 @@
+use axum::async_trait;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::RwLock;
+
+// 撤銷清單：簽章與期限都有效的 token，仍可能因登出或帳號遭盜用而必須拒絕
+// 傳入的值可能是 token 的 jti 或 sub，實作只需判斷是否在清單中
+#[async_trait]
+pub trait RevocationStore: Debug + Send + Sync {
+    async fn is_revoked(&self, jti_or_sub: &str) -> bool;
+}
+
+// 存在記憶體中的撤銷清單，重啟後即清空，適合單一實例或測試使用
+#[derive(Debug, Default)]
+pub struct InMemoryRevocationStore {
+    revoked: RwLock<HashSet<String>>,
+}
+
+impl InMemoryRevocationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn revoke(&self, jti_or_sub: impl Into<String>) {
+        self.revoked.write().unwrap().insert(jti_or_sub.into());
+    }
+
+    pub fn restore(&self, jti_or_sub: &str) -> bool {
+        self.revoked.write().unwrap().remove(jti_or_sub)
+    }
+}
+
+#[async_trait]
+impl RevocationStore for InMemoryRevocationStore {
+    async fn is_revoked(&self, jti_or_sub: &str) -> bool {
+        self.revoked.read().unwrap().contains(jti_or_sub)
+    }
+}